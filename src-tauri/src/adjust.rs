@@ -0,0 +1,186 @@
+//! Adjust module
+//!
+//! Tonal and colour adjustments such as brightness, contrast and grayscale,
+//! applied in place on the image buffer.
+
+use image::{DynamicImage, Rgba};
+use serde::Deserialize;
+
+/// Colour adjustments; the defaults leave the image unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AdjustOptions {
+    /// Added brightness, from -1 (black) to 1 (white)
+    pub brightness: f32,
+    /// From -1 (flat grey) through 0 (unchanged) to 1 (double contrast)
+    pub contrast: f32,
+    /// Values above 1 lighten midtones, below 1 darken them
+    pub gamma: f32,
+    /// Colour intensity multiplier; 0 is grayscale
+    pub saturation: f32,
+    pub grayscale: bool,
+    pub invert: bool,
+    pub sepia: bool,
+}
+
+impl Default for AdjustOptions {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 0.0,
+            gamma: 1.0,
+            saturation: 1.0,
+            grayscale: false,
+            invert: false,
+            sepia: false,
+        }
+    }
+}
+
+impl AdjustOptions {
+    fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Apply adjustments, reusing the image's buffer when it is already RGBA
+pub fn adjust_image(img: DynamicImage, options: &AdjustOptions) -> DynamicImage {
+    if options.is_identity() {
+        return img;
+    }
+
+    let mut buffer = img.into_rgba8();
+    let table = tone_table(options);
+    let saturation = match options.grayscale || options.sepia {
+        true => 0.0,
+        false => options.saturation.max(0.0),
+    };
+
+    for pixel in buffer.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = table[pixel[c] as usize];
+        }
+        if saturation != 1.0 {
+            saturate(pixel, saturation);
+        }
+        if options.sepia {
+            tint_sepia(pixel);
+        }
+    }
+
+    DynamicImage::ImageRgba8(buffer)
+}
+
+/// Lookup table for the per-channel adjustments
+fn tone_table(options: &AdjustOptions) -> [u8; 256] {
+    let contrast = (1.0 + options.contrast.clamp(-1.0, 1.0)).max(0.0);
+    let gamma = 1.0 / options.gamma.max(0.01);
+    let brightness = options.brightness.clamp(-1.0, 1.0);
+
+    let mut table = [0u8; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut value = i as f32 / 255.0 + brightness;
+        value = (value - 0.5) * contrast + 0.5;
+        value = value.clamp(0.0, 1.0).powf(gamma);
+        if options.invert {
+            value = 1.0 - value;
+        }
+        *entry = (value * 255.0).round() as u8;
+    }
+    table
+}
+
+/// Move a pixel towards (or away from) its own luma
+fn saturate(pixel: &mut Rgba<u8>, amount: f32) {
+    let luma = 0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32;
+    for c in 0..3 {
+        let value = luma + (pixel[c] as f32 - luma) * amount;
+        pixel[c] = value.round().clamp(0.0, 255.0) as u8;
+    }
+}
+
+/// Warm brown tone, applied to an already grayscale pixel
+fn tint_sepia(pixel: &mut Rgba<u8>) {
+    let grey = pixel[0] as f32;
+    pixel[0] = (grey * 1.07).min(255.0).round() as u8;
+    pixel[1] = (grey * 0.74 + 20.0).min(255.0).round() as u8;
+    pixel[2] = (grey * 0.43 + 10.0).min(255.0).round() as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    fn pixel(rgba: [u8; 4], options: &AdjustOptions) -> Rgba<u8> {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba(rgba)));
+        *adjust_image(img, options).to_rgba8().get_pixel(0, 0)
+    }
+
+    #[test]
+    fn test_defaults_leave_image_unchanged() {
+        let color = [12, 200, 99, 128];
+        assert_eq!(pixel(color, &AdjustOptions::default()), Rgba(color));
+    }
+
+    #[test]
+    fn test_brightness_contrast_and_gamma() {
+        let brighter = AdjustOptions {
+            brightness: 0.2,
+            ..Default::default()
+        };
+        assert_eq!(
+            pixel([100, 100, 100, 255], &brighter),
+            Rgba([151, 151, 151, 255])
+        );
+
+        let contrast = AdjustOptions {
+            contrast: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            pixel([60, 255, 230, 255], &contrast),
+            Rgba([0, 255, 255, 255])
+        );
+
+        let gamma = AdjustOptions {
+            gamma: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(pixel([64, 0, 255, 255], &gamma), Rgba([128, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_grayscale_invert_and_sepia() {
+        let grayscale = AdjustOptions {
+            grayscale: true,
+            ..Default::default()
+        };
+        let grey = pixel([255, 0, 0, 255], &grayscale);
+        assert!(grey[0] == grey[1] && grey[1] == grey[2]);
+        assert_eq!(grey[0], 54);
+
+        let invert = AdjustOptions {
+            invert: true,
+            ..Default::default()
+        };
+        assert_eq!(pixel([10, 200, 255, 77], &invert), Rgba([245, 55, 0, 77]));
+
+        let sepia = AdjustOptions {
+            sepia: true,
+            ..Default::default()
+        };
+        let toned = pixel([200, 200, 200, 255], &sepia);
+        assert!(toned[0] > toned[1] && toned[1] > toned[2], "{:?}", toned);
+    }
+
+    #[test]
+    fn test_saturation_boost() {
+        let options = AdjustOptions {
+            saturation: 2.0,
+            ..Default::default()
+        };
+        let boosted = pixel([150, 100, 100, 255], &options);
+        assert!(boosted[0] > 150 && boosted[1] < 100, "{:?}", boosted);
+    }
+}
@@ -0,0 +1,197 @@
+//! Backdrop module
+//!
+//! Backgrounds computed from the screenshot itself, so a beautified capture
+//! sits on colours that match its content.
+
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::draw::Color;
+use crate::gradient::{render_gradient, GradientSpec, MeshPoint};
+use crate::image::{blur_image, MAX_EXPORT_DIMENSION};
+use crate::palette::extract_palette;
+use crate::utils::AppResult;
+
+/// Where mesh points go for palette backdrops, most common colour first
+const PALETTE_POINTS: [(f32, f32); 4] = [(0.0, 0.0), (1.0, 1.0), (1.0, 0.0), (0.0, 1.0)];
+
+/// How the backdrop is derived from the screenshot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackdropMode {
+    /// Heavily blurred copy scaled up to cover the canvas
+    #[default]
+    Blurred,
+    /// Average colour of the screenshot's border
+    EdgeAverage,
+    /// Most common colour of the screenshot's border
+    EdgeDominant,
+    /// Mesh gradient of the screenshot's main colours
+    Palette,
+}
+
+/// Backdrop mode and its tuning
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct BackdropOptions {
+    pub mode: BackdropMode,
+    /// Blur radius of blurred backdrops, as a fraction of the shorter side
+    pub blur: f32,
+}
+
+impl Default for BackdropOptions {
+    fn default() -> Self {
+        Self {
+            mode: BackdropMode::Blurred,
+            blur: 0.05,
+        }
+    }
+}
+
+/// Render a backdrop of the given size from a screenshot
+pub fn render_backdrop(
+    img: &DynamicImage,
+    options: &BackdropOptions,
+    width: u32,
+    height: u32,
+) -> AppResult<RgbaImage> {
+    if width == 0 || height == 0 || width > MAX_EXPORT_DIMENSION || height > MAX_EXPORT_DIMENSION {
+        return Err(format!("Invalid backdrop size: {}x{}", width, height));
+    }
+
+    match options.mode {
+        BackdropMode::Blurred => {
+            let cover = img
+                .resize_to_fill(width, height, FilterType::Triangle)
+                .to_rgba8();
+            let sigma = width.min(height) as f32 * options.blur.max(0.0);
+            Ok(blur_image(&cover, sigma))
+        }
+        BackdropMode::EdgeAverage | BackdropMode::EdgeDominant => {
+            let dominant = options.mode == BackdropMode::EdgeDominant;
+            let color = edge_color(img, dominant).ok_or("Screenshot has no opaque edge")?;
+            Ok(RgbaImage::from_pixel(width, height, color))
+        }
+        BackdropMode::Palette => {
+            let palette = extract_palette(img, PALETTE_POINTS.len());
+            if palette.is_empty() {
+                return Err("Screenshot has no opaque pixels".to_string());
+            }
+            let points = palette
+                .iter()
+                .zip(PALETTE_POINTS)
+                .map(|(entry, (x, y))| MeshPoint {
+                    x,
+                    y,
+                    color: Color(entry.color),
+                })
+                .collect();
+            let spec = GradientSpec::Mesh {
+                points,
+                smoothness: 2.0,
+            };
+            render_gradient(&spec, width, height)
+        }
+    }
+}
+
+/// Average or most common colour of the opaque pixels along the image border
+pub fn edge_color(img: &DynamicImage, dominant: bool) -> Option<Rgba<u8>> {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let band = (width.min(height) / 50).max(1);
+
+    let edge = rgba.enumerate_pixels().filter(|&(x, y, p)| {
+        let on_edge = x < band || y < band || x >= width - band || y >= height - band;
+        on_edge && p[3] >= 128
+    });
+
+    // Buckets of 16 levels per channel; a single bucket when averaging
+    let mut buckets = HashMap::<[u8; 3], ([u64; 3], u64)>::new();
+    for (_, _, p) in edge {
+        let key = match dominant {
+            true => [p[0] >> 4, p[1] >> 4, p[2] >> 4],
+            false => [0; 3],
+        };
+        let (sum, count) = buckets.entry(key).or_default();
+        for c in 0..3 {
+            sum[c] += p[c] as u64;
+        }
+        *count += 1;
+    }
+
+    let (sum, count) = buckets.into_values().max_by_key(|&(_, count)| count)?;
+    let mean = |c: usize| ((sum[c] + count / 2) / count) as u8;
+    Some(Rgba([mean(0), mean(1), mean(2), 255]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mostly white page with a dark toolbar along the top edge
+    fn page() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(100, 60, |x, y| {
+            if y < 10 {
+                Rgba([30, 30, 40, 255])
+            } else if (40..60).contains(&x) && (25..45).contains(&y) {
+                Rgba([220, 40, 40, 255])
+            } else {
+                Rgba([250, 250, 250, 255])
+            }
+        }))
+    }
+
+    fn options(mode: BackdropMode) -> BackdropOptions {
+        BackdropOptions {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_edge_colours() {
+        let img = page();
+
+        assert_eq!(edge_color(&img, true), Some(Rgba([250, 250, 250, 255])));
+        let average = edge_color(&img, false).unwrap();
+        // Pulled towards the toolbar colour, but still mostly white
+        assert!(average[0] > 150 && average[0] < 250, "{:?}", average);
+
+        let backdrop = render_backdrop(&img, &options(BackdropMode::EdgeDominant), 30, 20).unwrap();
+        assert_eq!(backdrop.dimensions(), (30, 20));
+        assert!(backdrop.pixels().all(|p| *p == Rgba([250, 250, 250, 255])));
+    }
+
+    #[test]
+    fn test_blurred_covers_canvas() {
+        let backdrop = render_backdrop(&page(), &options(BackdropMode::Blurred), 300, 300).unwrap();
+
+        assert_eq!(backdrop.dimensions(), (300, 300));
+        // The red block ends up smeared around the middle
+        let centre = backdrop.get_pixel(150, 150);
+        assert!(centre[0] > centre[1] + 20, "{:?}", centre);
+        assert!(backdrop.get_pixel(150, 0)[0] < 200);
+    }
+
+    #[test]
+    fn test_palette_gradient_uses_image_colours() {
+        let backdrop = render_backdrop(&page(), &options(BackdropMode::Palette), 64, 64).unwrap();
+
+        // The most common colour, white, anchors the top-left corner
+        let corner = backdrop.get_pixel(0, 0);
+        assert!(corner[0] > 240 && corner[2] > 240, "{:?}", corner);
+        assert!(backdrop.get_pixel(63, 63) != corner);
+    }
+
+    #[test]
+    fn test_transparent_screenshot_is_an_error() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(10, 10));
+
+        assert!(render_backdrop(&img, &options(BackdropMode::EdgeAverage), 10, 10).is_err());
+        assert!(render_backdrop(&img, &options(BackdropMode::Palette), 10, 10).is_err());
+        assert!(render_backdrop(&page(), &options(BackdropMode::Blurred), 0, 10).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+//! Canvas module
+//!
+//! Places a screenshot on a canvas of fixed size, such as 16:9 for slides or
+//! 1200x630 for link previews, with padding, alignment and a background.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::backdrop::{render_backdrop, BackdropMode, BackdropOptions};
+use crate::compose::ComposeAlign;
+use crate::draw::{mask_corners, Color, Corners};
+use crate::gradient::{render_gradient, GradientSpec};
+use crate::image::MAX_EXPORT_DIMENSION;
+use crate::utils::AppResult;
+
+/// Named output size
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanvasPreset {
+    pub id: String,
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CanvasPreset {
+    fn new(id: &str, name: &str, width: u32, height: u32) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            width,
+            height,
+        }
+    }
+}
+
+/// Presets shipped with the app
+pub fn builtin_presets() -> Vec<CanvasPreset> {
+    vec![
+        CanvasPreset::new("widescreen", "16:9 Slides", 1920, 1080),
+        CanvasPreset::new("standard", "4:3 Slides", 1600, 1200),
+        CanvasPreset::new("square", "1:1 Square", 1080, 1080),
+        CanvasPreset::new("link_preview", "Link preview (1200x630)", 1200, 630),
+        CanvasPreset::new("product_hunt", "Product Hunt gallery", 1270, 760),
+    ]
+}
+
+/// Built-in presets followed by custom ones; custom presets replace built-ins with the same id
+pub fn all_presets(custom: &[CanvasPreset]) -> Vec<CanvasPreset> {
+    let mut presets: Vec<CanvasPreset> = builtin_presets()
+        .into_iter()
+        .filter(|preset| !custom.iter().any(|c| c.id == preset.id))
+        .collect();
+    presets.extend(custom.iter().cloned());
+    presets
+}
+
+/// What fills the canvas around the screenshot
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CanvasBackground {
+    #[default]
+    Transparent,
+    Color {
+        color: Color,
+    },
+    Gradient {
+        gradient: GradientSpec,
+    },
+    /// Derived from the screenshot itself
+    Backdrop {
+        mode: BackdropMode,
+    },
+}
+
+/// Fixed-size canvas the screenshot is fitted into
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CanvasOptions {
+    /// Preset id; when set it decides the width and height
+    pub preset: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    /// Minimum space around the screenshot, as a fraction of the shorter side
+    pub padding: f32,
+    pub align_x: ComposeAlign,
+    pub align_y: ComposeAlign,
+    /// Enlarge screenshots smaller than the space inside the padding
+    pub upscale: bool,
+    pub corner_radius: f32,
+    pub background: CanvasBackground,
+}
+
+impl Default for CanvasOptions {
+    fn default() -> Self {
+        Self {
+            preset: None,
+            width: 0,
+            height: 0,
+            padding: 0.08,
+            align_x: ComposeAlign::Center,
+            align_y: ComposeAlign::Center,
+            upscale: false,
+            corner_radius: 0.0,
+            background: CanvasBackground::Transparent,
+        }
+    }
+}
+
+impl CanvasOptions {
+    /// Take the size from the named preset
+    pub fn resolve_preset(&mut self, presets: &[CanvasPreset]) -> AppResult<()> {
+        let Some(id) = &self.preset else {
+            return Ok(());
+        };
+        let preset = presets
+            .iter()
+            .find(|preset| &preset.id == id)
+            .ok_or_else(|| format!("Unknown canvas preset: {}", id))?;
+        self.width = preset.width;
+        self.height = preset.height;
+        Ok(())
+    }
+}
+
+/// Fit a screenshot onto a canvas of the configured size
+pub fn fit_to_canvas(img: &DynamicImage, options: &CanvasOptions) -> AppResult<RgbaImage> {
+    let (width, height) = (options.width, options.height);
+    if width == 0 || height == 0 || width > MAX_EXPORT_DIMENSION || height > MAX_EXPORT_DIMENSION {
+        return Err(format!("Invalid canvas size: {}x{}", width, height));
+    }
+
+    // Always leave at least one pixel inside the padding
+    let min_side = width.min(height);
+    let padding = ((min_side as f32 * options.padding.clamp(0.0, 0.45)).round() as u32)
+        .min((min_side - 1) / 2);
+    let (inner_width, inner_height) = (width - padding * 2, height - padding * 2);
+    let mut scale =
+        (inner_width as f32 / img.width() as f32).min(inner_height as f32 / img.height() as f32);
+    if !options.upscale {
+        scale = scale.min(1.0);
+    }
+    let fitted_width = ((img.width() as f32 * scale).round() as u32).clamp(1, inner_width);
+    let fitted_height = ((img.height() as f32 * scale).round() as u32).clamp(1, inner_height);
+    let mut fitted = match (fitted_width, fitted_height) == (img.width(), img.height()) {
+        true => img.to_rgba8(),
+        false => img
+            .resize_exact(fitted_width, fitted_height, FilterType::Lanczos3)
+            .to_rgba8(),
+    };
+    mask_corners(&mut fitted, options.corner_radius, Corners::ALL);
+
+    let mut canvas = match &options.background {
+        CanvasBackground::Transparent => RgbaImage::new(width, height),
+        CanvasBackground::Color { color } => RgbaImage::from_pixel(width, height, color.0),
+        CanvasBackground::Gradient { gradient } => render_gradient(gradient, width, height)?,
+        CanvasBackground::Backdrop { mode } => {
+            let backdrop = BackdropOptions {
+                mode: *mode,
+                ..Default::default()
+            };
+            render_backdrop(img, &backdrop, width, height)?
+        }
+    };
+
+    let x = padding + options.align_x.offset(inner_width - fitted_width);
+    let y = padding + options.align_y.offset(inner_height - fitted_height);
+    imageops::overlay(&mut canvas, &fitted, x as i64, y as i64);
+
+    Ok(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn red(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, RED))
+    }
+
+    fn options(width: u32, height: u32) -> CanvasOptions {
+        CanvasOptions {
+            width,
+            height,
+            padding: 0.1,
+            background: CanvasBackground::Color {
+                color: Color(WHITE),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_large_screenshot_is_scaled_to_fit() {
+        let result = fit_to_canvas(&red(400, 400), &options(200, 100)).unwrap();
+
+        // 10px padding leaves 180x80, so the square becomes 80x80 in the middle
+        assert_eq!(result.dimensions(), (200, 100));
+        assert_eq!(result.get_pixel(60, 10), &RED);
+        assert_eq!(result.get_pixel(139, 89), &RED);
+        assert_eq!(result.get_pixel(59, 50), &WHITE);
+        assert_eq!(result.get_pixel(140, 50), &WHITE);
+        assert_eq!(result.get_pixel(100, 9), &WHITE);
+    }
+
+    #[test]
+    fn test_small_screenshot_is_aligned_without_upscaling() {
+        let canvas = CanvasOptions {
+            align_x: ComposeAlign::End,
+            align_y: ComposeAlign::Start,
+            ..options(200, 100)
+        };
+
+        let result = fit_to_canvas(&red(20, 10), &canvas).unwrap();
+
+        assert_eq!(result.get_pixel(189, 10), &RED);
+        assert_eq!(result.get_pixel(170, 19), &RED);
+        assert_eq!(result.get_pixel(169, 10), &WHITE);
+        assert_eq!(result.get_pixel(189, 20), &WHITE);
+
+        let upscaled = fit_to_canvas(
+            &red(20, 10),
+            &CanvasOptions {
+                upscale: true,
+                ..canvas
+            },
+        )
+        .unwrap();
+        // 160x80 fills the full height inside the padding
+        assert_eq!(upscaled.get_pixel(30, 89), &RED);
+        assert_eq!(upscaled.get_pixel(29, 50), &WHITE);
+    }
+
+    #[test]
+    fn test_small_custom_preset_keeps_room_for_the_image() {
+        for side in [2, 6, 10] {
+            let presets = all_presets(&[CanvasPreset::new("tiny", "Tiny", side, side)]);
+            let mut canvas = CanvasOptions {
+                preset: Some("tiny".to_string()),
+                padding: 0.45,
+                ..options(0, 0)
+            };
+            canvas.resolve_preset(&presets).unwrap();
+
+            let result = fit_to_canvas(&red(40, 40), &canvas).unwrap();
+
+            assert_eq!(result.dimensions(), (side, side));
+            assert_eq!(result.get_pixel(side / 2, side / 2), &RED);
+        }
+    }
+
+    #[test]
+    fn test_presets() {
+        let custom = vec![
+            CanvasPreset::new("square", "Small square", 500, 500),
+            CanvasPreset::new("banner", "Banner", 1500, 500),
+        ];
+        let presets = all_presets(&custom);
+
+        assert_eq!(presets.len(), builtin_presets().len() + 1);
+        let mut canvas = CanvasOptions {
+            preset: Some("square".to_string()),
+            ..Default::default()
+        };
+        canvas.resolve_preset(&presets).unwrap();
+        assert_eq!((canvas.width, canvas.height), (500, 500));
+
+        canvas.preset = Some("link_preview".to_string());
+        canvas.resolve_preset(&presets).unwrap();
+        assert_eq!((canvas.width, canvas.height), (1200, 630));
+
+        canvas.preset = Some("missing".to_string());
+        assert!(canvas.resolve_preset(&presets).is_err());
+    }
+
+    #[test]
+    fn test_options_from_json() {
+        let json = r##"{"preset": "square", "align_y": "end",
+                        "background": {"type": "color", "color": "#102030"}}"##;
+        let canvas: CanvasOptions = serde_json::from_str(json).unwrap();
+
+        assert_eq!(canvas.align_y, ComposeAlign::End);
+        assert_eq!(
+            canvas.background,
+            CanvasBackground::Color {
+                color: Color(Rgba([16, 32, 48, 255]))
+            }
+        );
+        assert!(fit_to_canvas(&red(10, 10), &canvas).is_err());
+    }
+}
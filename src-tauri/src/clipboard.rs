@@ -1,16 +1,35 @@
 //! Clipboard operations module
 
-use crate::utils::AppResult;
-use std::process::Command;
+use crate::image::{encode_image, load_image, ExportFormat};
+use crate::utils::{generate_filename, AppResult};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
 
 /// Copy an image file to the system clipboard using macOS native APIs
 /// This approach works with clipboard managers like Raycast
 pub fn copy_image_to_clipboard(image_path: &str) -> AppResult<()> {
+    let extension = Path::new(image_path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    let class = match extension.as_deref() {
+        Some("jpg") | Some("jpeg") => "JPEG",
+        Some("png") | None => "PNGf",
+        Some("webp") => return copy_png_rendition(image_path),
+        Some(other) => {
+            return Err(format!(
+                "Copying .{} images to the clipboard is not supported",
+                other
+            ))
+        }
+    };
+
     // Use osascript to copy the image file to clipboard
     // This method properly integrates with macOS clipboard and clipboard managers
     let script = format!(
-        r#"set the clipboard to (read (POSIX file "{}") as «class PNGf»)"#,
-        image_path
+        r#"set the clipboard to (read (POSIX file "{}") as «class {}»)"#,
+        image_path, class
     );
 
     let output = Command::new("osascript")
@@ -26,3 +45,39 @@ pub fn copy_image_to_clipboard(image_path: &str) -> AppResult<()> {
 
     Ok(())
 }
+
+/// Copy an image the clipboard has no type for, such as WebP, as a PNG
+fn copy_png_rendition(image_path: &str) -> AppResult<()> {
+    let img = load_image(image_path)?;
+    let png_path = std::env::temp_dir().join(generate_filename("clipboard", "png")?);
+    let png_path = png_path.to_string_lossy().into_owned();
+    fs::write(&png_path, encode_image(&img, ExportFormat::Png, 100)?)
+        .map_err(|e| format!("Failed to write clipboard image: {}", e))?;
+
+    let result = copy_image_to_clipboard(&png_path);
+    let _ = fs::remove_file(&png_path);
+    result
+}
+
+/// Copy text to the system clipboard
+pub fn copy_text_to_clipboard(text: &str) -> AppResult<()> {
+    let mut child = Command::new("pbcopy")
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute pbcopy: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("Failed to copy text to clipboard: {}", e))?;
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to copy text to clipboard: {}", e))?;
+    if !status.success() {
+        return Err("Failed to copy text to clipboard".to_string());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,294 @@
+//! Colour profile module
+//!
+//! Captures from wide-gamut displays are in the display's colour space.
+//! Saved images either carry that profile or are converted to sRGB.
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::{DynamicImage, RgbaImage};
+use png::chunk::ChunkType;
+use qcms::{DataType, Intent, Profile, Transform};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Cursor, Write};
+
+use crate::image::ExportFormat;
+use crate::metadata::jpeg_segments;
+use crate::optimize::{insert_chunks, remove_chunks};
+use crate::utils::AppResult;
+
+/// Identifier that starts every ICC APP2 segment in a JPEG
+const JPEG_ICC_MARKER: &[u8] = b"ICC_PROFILE\0";
+
+/// Largest profile slice that fits in one APP2 segment
+const JPEG_ICC_CHUNK: usize = 65_519;
+
+/// Profile name written to iCCP chunks
+const PNG_ICC_NAME: &[u8] = b"ICC Profile";
+
+/// How saved images handle the colour profile of the source display
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorProfileMode {
+    /// Keep the display's pixels and attach its profile
+    #[default]
+    Embed,
+    /// Convert pixels to sRGB and mark the file as sRGB
+    Convert,
+}
+
+/// Read the colour profile embedded in an image file, if any
+pub fn read_icc_profile(path: &str) -> AppResult<Option<Vec<u8>>> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read image: {}", e))?;
+    icc_profile(&data)
+}
+
+/// Read the colour profile embedded in PNG or JPEG data, if any
+pub fn icc_profile(data: &[u8]) -> AppResult<Option<Vec<u8>>> {
+    if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+        let reader = png::Decoder::new(Cursor::new(data))
+            .read_info()
+            .map_err(|e| format!("Failed to read PNG header: {}", e))?;
+        return Ok(reader.info().icc_profile.as_ref().map(|p| p.to_vec()));
+    }
+
+    if data.starts_with(&[0xFF, 0xD8]) {
+        // Profiles larger than one segment are split and numbered from 1
+        let (segments, _) = jpeg_segments(data)?;
+        let mut parts: Vec<(u8, &[u8])> = segments
+            .into_iter()
+            .filter(|(marker, segment)| {
+                *marker == 0xE2 && segment[4..].starts_with(JPEG_ICC_MARKER)
+            })
+            .filter_map(|(_, segment)| {
+                let body = &segment[4 + JPEG_ICC_MARKER.len()..];
+                (body.len() >= 2).then(|| (body[0], &body[2..]))
+            })
+            .collect();
+        if parts.is_empty() {
+            return Ok(None);
+        }
+        parts.sort_by_key(|(seq, _)| *seq);
+        return Ok(Some(
+            parts
+                .into_iter()
+                .flat_map(|(_, part)| part.to_vec())
+                .collect(),
+        ));
+    }
+
+    Ok(None)
+}
+
+/// Convert pixels from a display profile to sRGB
+pub fn convert_to_srgb(img: &DynamicImage, profile: &[u8]) -> AppResult<DynamicImage> {
+    let input = Profile::new_from_slice(profile, false).ok_or("Unsupported colour profile")?;
+    let mut output = Profile::new_sRGB();
+    output.precache_output_transform();
+
+    let transform = Transform::new(&input, &output, DataType::RGBA8, Intent::Perceptual)
+        .ok_or("Failed to create sRGB colour transform")?;
+
+    let mut rgba: RgbaImage = img.to_rgba8();
+    transform.apply(&mut rgba);
+    Ok(DynamicImage::ImageRgba8(rgba))
+}
+
+/// Attach a colour profile to encoded image data
+///
+/// PNG gets an iCCP chunk and JPEG APP2 segments; WebP is left unchanged.
+pub fn embed_icc_profile(
+    bytes: Vec<u8>,
+    format: ExportFormat,
+    profile: &[u8],
+) -> AppResult<Vec<u8>> {
+    match format {
+        ExportFormat::Png => {
+            let mut body = PNG_ICC_NAME.to_vec();
+            // Name terminator, then compression method 0 (zlib)
+            body.extend_from_slice(&[0, 0]);
+            let mut encoder = ZlibEncoder::new(body, Compression::best());
+            encoder
+                .write_all(profile)
+                .map_err(|e| format!("Failed to compress colour profile: {}", e))?;
+            let body = encoder
+                .finish()
+                .map_err(|e| format!("Failed to compress colour profile: {}", e))?;
+
+            // A PNG may carry either iCCP or sRGB, not both
+            let cleared = remove_chunks(&bytes, &[b"iCCP", b"sRGB"])?;
+            insert_chunks(&cleared, &[(png::chunk::iCCP, &body)])
+        }
+        ExportFormat::Jpeg => embed_jpeg_icc(&bytes, profile),
+        ExportFormat::Webp => Ok(bytes),
+    }
+}
+
+/// Mark PNG data as sRGB, replacing any embedded profile
+pub fn mark_srgb(bytes: Vec<u8>, format: ExportFormat) -> AppResult<Vec<u8>> {
+    if format != ExportFormat::Png {
+        return Ok(bytes);
+    }
+
+    let cleared = remove_chunks(&bytes, &[b"iCCP", b"sRGB"])?;
+    // Rendering intent 0: perceptual
+    insert_chunks(&cleared, &[(ChunkType(*b"sRGB"), &[0])])
+}
+
+/// Insert ICC APP2 segments after the JFIF header, replacing any existing ones
+fn embed_jpeg_icc(data: &[u8], profile: &[u8]) -> AppResult<Vec<u8>> {
+    let parts: Vec<&[u8]> = profile.chunks(JPEG_ICC_CHUNK).collect();
+    let count = u8::try_from(parts.len()).map_err(|_| "Colour profile too large for JPEG")?;
+    let (segments, scan_start) = jpeg_segments(data)?;
+
+    let mut out = Vec::with_capacity(data.len() + profile.len() + parts.len() * 18);
+    out.extend_from_slice(&[0xFF, 0xD8]);
+
+    let mut inserted = false;
+    for (marker, segment) in segments {
+        if marker == 0xE2 && segment[4..].starts_with(JPEG_ICC_MARKER) {
+            continue;
+        }
+        if !inserted && marker != 0xE0 {
+            write_app2(&mut out, &parts, count);
+            inserted = true;
+        }
+        out.extend_from_slice(segment);
+    }
+    if !inserted {
+        write_app2(&mut out, &parts, count);
+    }
+
+    out.extend_from_slice(&data[scan_start..]);
+    Ok(out)
+}
+
+fn write_app2(out: &mut Vec<u8>, parts: &[&[u8]], count: u8) {
+    for (index, part) in parts.iter().enumerate() {
+        // Parts are at most JPEG_ICC_CHUNK bytes, so the length fits in u16
+        let length = (2 + JPEG_ICC_MARKER.len() + 2 + part.len()) as u16;
+        out.extend_from_slice(&[0xFF, 0xE2]);
+        out.extend_from_slice(&length.to_be_bytes());
+        out.extend_from_slice(JPEG_ICC_MARKER);
+        out.extend_from_slice(&[index as u8 + 1, count]);
+        out.extend_from_slice(part);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::encode_image;
+    use crate::optimize::read_chunks;
+    use image::Rgba;
+
+    /// D50-adapted sRGB primaries as s15Fixed16 XYZ
+    const RED: [f64; 3] = [0.4361, 0.2225, 0.0139];
+    const GREEN: [f64; 3] = [0.3851, 0.7169, 0.0971];
+    const BLUE: [f64; 3] = [0.1431, 0.0606, 0.7141];
+
+    /// Build a minimal matrix/TRC display profile with gamma 2.2 curves
+    fn matrix_profile(red: [f64; 3], green: [f64; 3], blue: [f64; 3]) -> Vec<u8> {
+        fn xyz(values: [f64; 3]) -> Vec<u8> {
+            let mut tag = b"XYZ \0\0\0\0".to_vec();
+            for v in values {
+                tag.extend_from_slice(&((v * 65536.0).round() as i32).to_be_bytes());
+            }
+            tag
+        }
+        // curv with a single u8Fixed8 gamma entry, padded to 4 bytes
+        let curve = b"curv\0\0\0\0\0\0\0\x01\x02\x33\0\0".to_vec();
+
+        let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+            (b"wtpt", xyz([0.9642, 1.0, 0.8249])),
+            (b"rXYZ", xyz(red)),
+            (b"gXYZ", xyz(green)),
+            (b"bXYZ", xyz(blue)),
+            (b"rTRC", curve.clone()),
+            (b"gTRC", curve.clone()),
+            (b"bTRC", curve),
+        ];
+
+        let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+        let mut data = Vec::new();
+        let data_start = 128 + 4 + tags.len() * 12;
+        for (sig, body) in &tags {
+            table.extend_from_slice(*sig);
+            table.extend_from_slice(&((data_start + data.len()) as u32).to_be_bytes());
+            table.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            data.extend_from_slice(body);
+        }
+
+        let mut header = vec![0u8; 128];
+        let size = (128 + table.len() + data.len()) as u32;
+        header[0..4].copy_from_slice(&size.to_be_bytes());
+        header[8..12].copy_from_slice(&[2, 0x10, 0, 0]);
+        header[12..16].copy_from_slice(b"mntr");
+        header[16..20].copy_from_slice(b"RGB ");
+        header[20..24].copy_from_slice(b"XYZ ");
+        header[36..40].copy_from_slice(b"acsp");
+
+        [header, table, data].concat()
+    }
+
+    fn red_image() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 200])))
+    }
+
+    #[test]
+    fn test_png_profile_round_trip() {
+        let profile = matrix_profile(RED, GREEN, BLUE);
+        let png = encode_image(&red_image(), ExportFormat::Png, 90).unwrap();
+
+        let embedded = embed_icc_profile(png, ExportFormat::Png, &profile).unwrap();
+
+        assert_eq!(icc_profile(&embedded).unwrap(), Some(profile));
+        assert!(image::load_from_memory(&embedded).is_ok());
+    }
+
+    #[test]
+    fn test_jpeg_profile_round_trip() {
+        let profile = matrix_profile(RED, GREEN, BLUE);
+        let jpeg = encode_image(&red_image(), ExportFormat::Jpeg, 90).unwrap();
+
+        let embedded = embed_icc_profile(jpeg, ExportFormat::Jpeg, &profile).unwrap();
+        let replaced = embed_icc_profile(embedded, ExportFormat::Jpeg, &profile).unwrap();
+
+        assert_eq!(icc_profile(&replaced).unwrap(), Some(profile));
+        assert!(image::load_from_memory(&replaced).is_ok());
+    }
+
+    #[test]
+    fn test_convert_applies_profile_primaries() {
+        // A profile whose red primary is sRGB green
+        let profile = matrix_profile(GREEN, RED, BLUE);
+
+        let converted = convert_to_srgb(&red_image(), &profile).unwrap().to_rgba8();
+        let Rgba([r, g, b, a]) = *converted.get_pixel(0, 0);
+
+        assert!(g > 200 && r < 50 && b < 50, "got {:?}", (r, g, b));
+        assert_eq!(a, 200);
+    }
+
+    #[test]
+    fn test_convert_rejects_invalid_profile() {
+        assert!(convert_to_srgb(&red_image(), b"not a profile").is_err());
+    }
+
+    #[test]
+    fn test_mark_srgb_replaces_profile() {
+        let profile = matrix_profile(RED, GREEN, BLUE);
+        let png = encode_image(&red_image(), ExportFormat::Png, 90).unwrap();
+        let embedded = embed_icc_profile(png, ExportFormat::Png, &profile).unwrap();
+
+        let marked = mark_srgb(embedded, ExportFormat::Png).unwrap();
+        let kinds: Vec<ChunkType> = read_chunks(&marked)
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+
+        assert!(kinds.contains(&ChunkType(*b"sRGB")));
+        assert!(!kinds.contains(&png::chunk::iCCP));
+    }
+}
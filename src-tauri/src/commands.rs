@@ -1,19 +1,217 @@
 //! Tauri commands module
 
+use image::DynamicImage;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
-use std::sync::Mutex;
-use tauri::AppHandle;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
 
-use crate::clipboard::copy_image_to_clipboard;
-use crate::image::{copy_screenshot_to_dir, crop_image, save_base64_image, CropRegion};
+use crate::adjust::{adjust_image as apply_adjustments, AdjustOptions};
+use crate::backdrop::{render_backdrop as derive_backdrop, BackdropOptions};
+use crate::canvas::{all_presets, CanvasPreset};
+use crate::clipboard::{copy_image_to_clipboard, copy_text_to_clipboard};
+use crate::color::{convert_to_srgb, read_icc_profile};
+use crate::compose::{compose_images as compose, ComposeOptions};
+use crate::dedupe::{
+    find_duplicates as find_duplicate_images, hash_file, record_saved_hash, DuplicateCluster,
+    DuplicateOptions, HashAlgorithm,
+};
+use crate::diff::{diff_images as compare_images, DiffOptions, ImageDiff};
+use crate::export::{save_within_size, SizeLimitOptions, SizeLimitedExport};
+use crate::gradient::{render_gradient as render_background, GradientSpec};
+use crate::image::{
+    copy_screenshot_to_dir, crop_image, decode_base64_image, decode_base64_image_with_profile,
+    encode_data_url, load_image, save_base64_image, save_image, save_image_with,
+    transform_image as apply_transform, CropRegion, ExportOptions, ImageTransform, ResizeMode,
+    ResizeOptions,
+};
+use crate::jobs::{
+    cancel_job as cancel_queued_job, job_status as read_job_status, list_jobs as list_queued_jobs,
+    submit_job as queue_job, JobContext, JobRequest, JobStatus, ProgressSink,
+};
+use crate::metadata::{
+    capture_metadata, embed_metadata_file, strip_metadata_file, track_capture, write_sidecar,
+    CaptureMetadata,
+};
+use crate::mockup::{
+    frame_infos, load_frames, render_mockup as render_frame, FrameInfo, MockupOptions,
+};
+use crate::operations::{apply_operations, ImageOperation};
+use crate::optimize::{optimize_png_file, OptimizeOptions, OptimizeReport};
+use crate::palette::{extract_palette as find_palette, PaletteEntry};
+use crate::scan::{scan_codes as detect_codes, ScannedCode};
 use crate::screenshot::{
-    capture_all_monitors as capture_monitors, capture_primary_monitor, MonitorShot,
+    capture_all_monitors as capture_monitors, capture_primary_monitor, monitor_info_at,
+    primary_monitor_info, primary_scale_factor, scale_factor_at, window_info_at, MonitorShot,
 };
-use crate::utils::{generate_filename, get_desktop_path};
+use crate::settings::ExportSettings;
+use crate::shadow::{process_shadow_file, ShadowOptions};
+use crate::trim::{trim_png_file, TrimmedCapture};
+use crate::utils::{generate_filename, get_desktop_path, AppResult};
 
 static SCREENCAPTURE_LOCK: Mutex<()> = Mutex::new(());
 
+/// Post-save stage for every command that writes a final image
+///
+/// Failures are logged rather than returned, since the image is already saved.
+/// Streamed exports skip hashing and optimisation, which decode the whole image.
+fn finish_saved_image(
+    app_handle: &AppHandle,
+    settings: &ExportSettings,
+    path: &str,
+    metadata: Option<&CaptureMetadata>,
+    streamed: bool,
+) {
+    if settings.strip_metadata {
+        if let Err(e) = strip_metadata_file(path) {
+            eprintln!("Failed to strip metadata from {}: {}", path, e);
+        }
+    } else if let Some(metadata) = metadata {
+        if let Err(e) = embed_metadata_file(path, metadata) {
+            eprintln!("Failed to embed metadata in {}: {}", path, e);
+        }
+        if settings.metadata_sidecar {
+            if let Err(e) = write_sidecar(path, metadata) {
+                eprintln!("Failed to write metadata sidecar for {}: {}", path, e);
+            }
+        }
+    }
+
+    if streamed {
+        return;
+    }
+
+    // Hashing decodes the whole image, so it only runs when the warning is on
+    if settings.duplicate_warning.enabled {
+        match hash_file(path, HashAlgorithm::PHash) {
+            Ok(hash) => {
+                let duplicate = record_saved_hash(path, hash, &settings.duplicate_warning);
+                if let Some(duplicate) = duplicate {
+                    let _ = app_handle.emit("duplicate-capture", &duplicate);
+                }
+            }
+            Err(e) => eprintln!("Failed to hash {}: {}", path, e),
+        }
+    }
+
+    if !settings.png_optimization.is_enabled() || !path.ends_with(".png") {
+        return;
+    }
+
+    match optimize_png_file(path, &settings.png_optimization) {
+        Ok(report) => {
+            let _ = app_handle.emit("image-optimized", &report);
+        }
+        Err(e) => eprintln!("Failed to optimize {}: {}", path, e),
+    }
+}
+
+/// Record metadata for a fresh capture, using the mouse position to find
+/// the monitor (and window, for window captures) it was taken on
+fn record_capture(path: &str, capture_mode: &str) {
+    let mut metadata = CaptureMetadata::now(capture_mode);
+    let position = mouse_position()
+        .ok()
+        .map(|(x, y)| (x.round() as i32, y.round() as i32));
+
+    metadata.monitor = match (capture_mode, position) {
+        ("fullscreen", _) | (_, None) => primary_monitor_info().ok(),
+        (_, Some((x, y))) => monitor_info_at(x, y).ok(),
+    };
+    if let ("window", Some((x, y))) = (capture_mode, position) {
+        metadata.window = window_info_at(x, y).ok();
+    }
+
+    track_capture(path, metadata);
+}
+
+/// Post-capture trim of uniform borders, when enabled in settings
+fn trim_capture(app_handle: &AppHandle, path: &str) {
+    let options = ExportSettings::load(app_handle).auto_trim;
+    if !options.enabled {
+        return;
+    }
+
+    match trim_png_file(path, options.tolerance) {
+        Ok(region) => {
+            let trimmed = TrimmedCapture {
+                path: path.to_string(),
+                region,
+            };
+            let _ = app_handle.emit("capture-trimmed", &trimmed);
+        }
+        Err(e) => eprintln!("Failed to trim {}: {}", path, e),
+    }
+}
+
+/// Post-capture removal or normalization of the window shadow, per settings
+/// Returns whether the capture was changed
+fn process_window_shadow(app_handle: &AppHandle, path: &str) -> bool {
+    let options = ExportSettings::load(app_handle).window_shadow;
+
+    match process_shadow_file(path, &options) {
+        Ok(Some(region)) => {
+            let processed = TrimmedCapture {
+                path: path.to_string(),
+                region,
+            };
+            let _ = app_handle.emit("window-shadow-processed", &processed);
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            eprintln!("Failed to process window shadow in {}: {}", path, e);
+            false
+        }
+    }
+}
+
+/// Prefer per-call export options over the persisted ones
+/// The display scale is only known from the capture's monitor, logical resizes
+/// without it use the source monitor's, the watermark always comes from settings,
+/// and canvas presets include the custom ones
+fn resolve_export_options(
+    settings: &ExportSettings,
+    options: Option<ExportOptions>,
+    metadata: Option<&CaptureMetadata>,
+) -> AppResult<ExportOptions> {
+    let mut options = options.unwrap_or_else(|| settings.export_options.clone());
+
+    let display_scale = metadata
+        .and_then(|metadata| metadata.monitor.as_ref())
+        .map(|monitor| monitor.scale_factor);
+    options.display_scale = display_scale;
+
+    if let Some(ResizeOptions {
+        mode: ResizeMode::Logical { scale_factor },
+        ..
+    }) = &mut options.resize
+    {
+        if scale_factor.is_none() {
+            *scale_factor = display_scale.or_else(source_scale_factor);
+        }
+    }
+
+    if settings.watermark.enabled {
+        options.watermark = Some(settings.watermark.clone());
+    }
+
+    if let Some(canvas) = &mut options.canvas {
+        canvas.resolve_preset(&all_presets(&settings.canvas_presets))?;
+    }
+
+    Ok(options)
+}
+
+/// Scale factor of the monitor under the mouse, where the capture was taken
+/// and the editor opens, falling back to the primary monitor's
+fn source_scale_factor() -> Option<f32> {
+    mouse_position()
+        .ok()
+        .and_then(|(x, y)| scale_factor_at(x.round() as i32, y.round() as i32).ok())
+        .or_else(|| primary_scale_factor().ok())
+}
+
 /// Quick capture of primary monitor
 #[tauri::command]
 pub async fn capture_once(
@@ -26,6 +224,16 @@ pub async fn capture_once(
 
     let saved_path = copy_screenshot_to_dir(&screenshot_path_str, &save_dir)?;
 
+    let mut metadata = CaptureMetadata::now("fullscreen");
+    metadata.monitor = primary_monitor_info().ok();
+    finish_saved_image(
+        &app_handle,
+        &ExportSettings::load(&app_handle),
+        &saved_path,
+        Some(&metadata),
+        false,
+    );
+
     if copy_to_clip {
         copy_image_to_clipboard(&saved_path)?;
     }
@@ -45,12 +253,14 @@ pub async fn capture_all_monitors(
 /// Crop a region from a screenshot
 #[tauri::command]
 pub async fn capture_region(
+    app_handle: AppHandle,
     screenshot_path: String,
     x: u32,
     y: u32,
     width: u32,
     height: u32,
     save_dir: String,
+    export_options: Option<ExportOptions>,
 ) -> Result<String, String> {
     let region = CropRegion {
         x,
@@ -58,22 +268,648 @@ pub async fn capture_region(
         width,
         height,
     };
-    crop_image(&screenshot_path, region, &save_dir)
+    // A crop of a monitor shot is a region capture of that monitor
+    let metadata = capture_metadata(&screenshot_path).map(|mut metadata| {
+        metadata.capture_mode = "region".to_string();
+        metadata
+    });
+    let settings = ExportSettings::load(&app_handle);
+    let options = resolve_export_options(&settings, export_options, metadata.as_ref())?;
+
+    let saved = crop_image(&screenshot_path, region, &save_dir, &options)?;
+    finish_saved_image(
+        &app_handle,
+        &settings,
+        &saved.path,
+        metadata.as_ref(),
+        saved.streamed,
+    );
+
+    Ok(saved.path)
 }
 
 /// Save an edited image from base64 data
+/// `source_path` is the original capture, used to look up its metadata
 #[tauri::command]
 pub async fn save_edited_image(
+    app_handle: AppHandle,
     image_data: String,
     save_dir: String,
     copy_to_clip: bool,
+    export_options: Option<ExportOptions>,
+    source_path: Option<String>,
 ) -> Result<String, String> {
-    let saved_path = save_base64_image(&image_data, &save_dir, "bettershot")?;
+    let metadata = source_path.as_deref().and_then(capture_metadata);
+    let settings = ExportSettings::load(&app_handle);
+    let options = resolve_editor_options(
+        &settings,
+        export_options,
+        source_path.as_deref(),
+        metadata.as_ref(),
+    )?;
+
+    let saved = save_base64_image(&image_data, &save_dir, "bettershot", &options)?;
+    finish_saved_image(
+        &app_handle,
+        &settings,
+        &saved.path,
+        metadata.as_ref(),
+        saved.streamed,
+    );
 
     if copy_to_clip {
-        copy_image_to_clipboard(&saved_path)?;
+        copy_image_to_clipboard(&saved.path)?;
     }
 
+    Ok(saved.path)
+}
+
+/// Save an edited image using the best encoding that fits under a byte budget
+/// The export options apply as in `save_edited_image`, except for format and quality
+#[tauri::command]
+pub async fn save_edited_image_under_size(
+    app_handle: AppHandle,
+    image_data: String,
+    save_dir: String,
+    options: SizeLimitOptions,
+    copy_to_clip: bool,
+    export_options: Option<ExportOptions>,
+    source_path: Option<String>,
+) -> Result<SizeLimitedExport, String> {
+    let (img, profile) = decode_base64_image_with_profile(&image_data)?;
+    let metadata = source_path.as_deref().and_then(capture_metadata);
+    let settings = ExportSettings::load(&app_handle);
+    let export_options = resolve_editor_options(
+        &settings,
+        export_options,
+        source_path.as_deref(),
+        metadata.as_ref(),
+    )?;
+
+    let saved = save_within_size(
+        &img,
+        &save_dir,
+        "bettershot",
+        &export_options,
+        profile.as_deref(),
+        &options,
+        &settings.png_optimization,
+    )?;
+    finish_saved_image(
+        &app_handle,
+        &settings,
+        &saved.path,
+        metadata.as_ref(),
+        false,
+    );
+
+    if copy_to_clip {
+        copy_image_to_clipboard(&saved.path)?;
+    }
+
+    Ok(saved)
+}
+
+/// Resolve export options for a save from the editor
+///
+/// Edits of a capture whose metadata is gone take the display scale of the
+/// monitor under the mouse, where the editor opens.
+fn resolve_editor_options(
+    settings: &ExportSettings,
+    export_options: Option<ExportOptions>,
+    source_path: Option<&str>,
+    metadata: Option<&CaptureMetadata>,
+) -> AppResult<ExportOptions> {
+    let mut options = resolve_export_options(settings, export_options, metadata)?;
+    if source_path.is_some() && options.display_scale.is_none() {
+        options.display_scale = source_scale_factor();
+    }
+    Ok(options)
+}
+
+/// Optimise existing PNG files, reporting the bytes saved for each
+/// Uses the persisted optimisation settings unless options are given
+#[tauri::command]
+pub async fn optimize_images(
+    app_handle: AppHandle,
+    paths: Vec<String>,
+    options: Option<OptimizeOptions>,
+) -> Result<Vec<OptimizeReport>, String> {
+    let options = options.unwrap_or_else(|| ExportSettings::load(&app_handle).png_optimization);
+
+    paths
+        .iter()
+        .map(|path| optimize_png_file(path, &options))
+        .collect()
+}
+
+/// Compare two screenshots and save an image highlighting what changed
+#[tauri::command]
+pub async fn diff_images(
+    before_path: String,
+    after_path: String,
+    save_dir: String,
+    options: Option<DiffOptions>,
+) -> Result<ImageDiff, String> {
+    let before = load_image(&before_path)?;
+    let after = load_image(&after_path)?;
+
+    let (diff, report) = compare_images(&before, &after, &options.unwrap_or_default())?;
+    let path = save_image(&DynamicImage::ImageRgba8(diff), &save_dir, "diff")?;
+
+    Ok(ImageDiff { path, report })
+}
+
+/// Trim uniform or transparent borders from a PNG capture in place
+/// Returns the region that was kept, in the original image's coordinates
+#[tauri::command]
+pub async fn auto_trim(
+    app_handle: AppHandle,
+    image_path: String,
+    tolerance: Option<u8>,
+) -> Result<CropRegion, String> {
+    let tolerance =
+        tolerance.unwrap_or_else(|| ExportSettings::load(&app_handle).auto_trim.tolerance);
+    trim_png_file(&image_path, tolerance)
+}
+
+/// Remove or normalize the system shadow of a PNG window capture in place
+/// Returns the window body within the original capture, if a shadow was found
+#[tauri::command]
+pub async fn window_shadow(
+    app_handle: AppHandle,
+    image_path: String,
+    options: Option<ShadowOptions>,
+) -> Result<Option<CropRegion>, String> {
+    let options = options.unwrap_or_else(|| ExportSettings::load(&app_handle).window_shadow);
+    process_shadow_file(&image_path, &options)
+}
+
+/// Find groups of duplicate or near-duplicate images in a directory
+#[tauri::command]
+pub async fn find_duplicates(
+    dir: String,
+    options: Option<DuplicateOptions>,
+) -> Result<Vec<DuplicateCluster>, String> {
+    find_duplicate_images(&dir, &options.unwrap_or_default())
+}
+
+/// Rotate or flip an image and save the result
+/// Imported files are turned upright by their EXIF orientation first
+#[tauri::command]
+pub async fn transform_image(
+    app_handle: AppHandle,
+    image_path: String,
+    save_dir: String,
+    transform: ImageTransform,
+    export_options: Option<ExportOptions>,
+) -> Result<String, String> {
+    let img = load_image(&image_path)?;
+    let profile = read_icc_profile(&image_path)?;
+
+    let metadata = capture_metadata(&image_path);
+    let settings = ExportSettings::load(&app_handle);
+    let options = resolve_export_options(&settings, export_options, metadata.as_ref())?;
+    let saved_path = save_image_with(
+        &apply_transform(&img, &transform)?,
+        &save_dir,
+        "transformed",
+        &options,
+        profile.as_deref(),
+    )?;
+    finish_saved_image(
+        &app_handle,
+        &settings,
+        &saved_path,
+        metadata.as_ref(),
+        false,
+    );
+
+    Ok(saved_path)
+}
+
+/// Longest side of adjustment previews when the caller doesn't choose one
+const PREVIEW_SIZE: u32 = 1200;
+
+/// Apply colour adjustments to an image
+/// Saves full size when `save_dir` is given, otherwise returns a downscaled
+/// PNG data URL for live previews
+#[tauri::command]
+pub async fn adjust_image(
+    app_handle: AppHandle,
+    image_path: String,
+    options: AdjustOptions,
+    save_dir: Option<String>,
+    preview_size: Option<u32>,
+    export_options: Option<ExportOptions>,
+) -> Result<String, String> {
+    let img = load_image(&image_path)?;
+
+    match save_dir {
+        Some(save_dir) => {
+            let profile = read_icc_profile(&image_path)?;
+            let metadata = capture_metadata(&image_path);
+            let settings = ExportSettings::load(&app_handle);
+            let export_options =
+                resolve_export_options(&settings, export_options, metadata.as_ref())?;
+            let saved_path = save_image_with(
+                &apply_adjustments(img, &options),
+                &save_dir,
+                "adjusted",
+                &export_options,
+                profile.as_deref(),
+            )?;
+            finish_saved_image(
+                &app_handle,
+                &settings,
+                &saved_path,
+                metadata.as_ref(),
+                false,
+            );
+            Ok(saved_path)
+        }
+        None => {
+            let size = preview_size.unwrap_or(PREVIEW_SIZE).max(1);
+            let img = match img.width() > size || img.height() > size {
+                true => img.thumbnail(size, size),
+                false => img,
+            };
+            encode_data_url(&apply_adjustments(img, &options))
+        }
+    }
+}
+
+/// Render a gradient background at any size
+/// Saves it when `save_dir` is given, otherwise returns a PNG data URL
+#[tauri::command]
+pub async fn render_gradient(
+    app_handle: AppHandle,
+    spec: GradientSpec,
+    width: u32,
+    height: u32,
+    save_dir: Option<String>,
+    export_options: Option<ExportOptions>,
+) -> Result<String, String> {
+    let img = DynamicImage::ImageRgba8(render_background(&spec, width, height)?);
+
+    match save_dir {
+        Some(save_dir) => {
+            // Gradients are generated in sRGB, so no profile is attached
+            let settings = ExportSettings::load(&app_handle);
+            let options = resolve_export_options(&settings, export_options, None)?;
+            let saved_path = save_image_with(&img, &save_dir, "gradient", &options, None)?;
+            finish_saved_image(&app_handle, &settings, &saved_path, None, false);
+            Ok(saved_path)
+        }
+        None => encode_data_url(&img),
+    }
+}
+
+/// Render a background derived from a screenshot, returned as a PNG data URL
+#[tauri::command]
+pub async fn render_backdrop(
+    image_path: String,
+    options: Option<BackdropOptions>,
+    width: u32,
+    height: u32,
+) -> Result<String, String> {
+    let img = load_image(&image_path)?;
+    let backdrop = derive_backdrop(&img, &options.unwrap_or_default(), width, height)?;
+    encode_data_url(&DynamicImage::ImageRgba8(backdrop))
+}
+
+/// Render editor operations such as magnifier insets, returning a PNG data URL
+#[tauri::command]
+pub async fn preview_operations(
+    image_data: String,
+    operations: Vec<ImageOperation>,
+) -> Result<String, String> {
+    let img = decode_base64_image(&image_data)?;
+    encode_data_url(&apply_operations(&img, &operations)?)
+}
+
+/// Apply the same operations to several images headlessly and save each result
+#[tauri::command]
+pub async fn process_images(
+    app_handle: AppHandle,
+    paths: Vec<String>,
+    operations: Vec<ImageOperation>,
+    save_dir: String,
+    export_options: Option<ExportOptions>,
+) -> Result<Vec<String>, String> {
+    let settings = ExportSettings::load(&app_handle);
+
+    paths
+        .iter()
+        .map(|path| {
+            process_image(
+                &app_handle,
+                &settings,
+                path,
+                &operations,
+                &save_dir,
+                export_options.as_ref(),
+            )
+        })
+        .collect()
+}
+
+/// Apply operations to one image of a batch and save the result
+/// Export options are resolved per file, since each has its own capture metadata
+fn process_image(
+    app_handle: &AppHandle,
+    settings: &ExportSettings,
+    path: &str,
+    operations: &[ImageOperation],
+    save_dir: &str,
+    export_options: Option<&ExportOptions>,
+) -> AppResult<String> {
+    let img = load_image(path)?;
+    let processed = apply_operations(&img, operations)
+        .map_err(|e| format!("Failed to process {}: {}", path, e))?;
+    let profile = read_icc_profile(path)?;
+
+    let metadata = capture_metadata(path);
+    let options = resolve_export_options(settings, export_options.cloned(), metadata.as_ref())?;
+    let saved_path = save_image_with(
+        &processed,
+        save_dir,
+        "processed",
+        &options,
+        profile.as_deref(),
+    )?;
+    finish_saved_image(app_handle, settings, &saved_path, metadata.as_ref(), false);
+    Ok(saved_path)
+}
+
+/// Start long-running work in the background and return its queued status
+/// Progress is sent as "job-progress" events carrying the job's status
+#[tauri::command]
+pub async fn submit_job(app_handle: AppHandle, request: JobRequest) -> Result<JobStatus, String> {
+    let events = app_handle.clone();
+    let sink: ProgressSink = Arc::new(move |status: &JobStatus| {
+        let _ = events.emit("job-progress", status);
+    });
+
+    Ok(queue_job(request.kind(), sink, move |ctx| {
+        run_job(&app_handle, request, ctx)
+    }))
+}
+
+/// Get the status of a background job
+#[tauri::command]
+pub async fn job_status(id: u64) -> Result<JobStatus, String> {
+    read_job_status(id)
+}
+
+/// Cancel a queued or running background job
+#[tauri::command]
+pub async fn cancel_job(id: u64) -> Result<JobStatus, String> {
+    cancel_queued_job(id)
+}
+
+/// List queued, running and recently finished background jobs
+#[tauri::command]
+pub async fn list_jobs() -> Result<Vec<JobStatus>, String> {
+    Ok(list_queued_jobs())
+}
+
+/// Do the work of a background job, reporting progress after each file
+fn run_job(
+    app_handle: &AppHandle,
+    request: JobRequest,
+    ctx: &JobContext,
+) -> AppResult<serde_json::Value> {
+    let output = match request {
+        JobRequest::Optimize { paths, options } => {
+            let options =
+                options.unwrap_or_else(|| ExportSettings::load(app_handle).png_optimization);
+            let mut reports = Vec::with_capacity(paths.len());
+            for (i, path) in paths.iter().enumerate() {
+                ctx.progress(
+                    i,
+                    paths.len(),
+                    &format!("Optimizing {} of {}", i + 1, paths.len()),
+                )?;
+                reports.push(optimize_png_file(path, &options)?);
+            }
+            serde_json::to_value(reports)
+        }
+        JobRequest::Process {
+            paths,
+            operations,
+            save_dir,
+            export_options,
+        } => {
+            let settings = ExportSettings::load(app_handle);
+            let mut saved = Vec::with_capacity(paths.len());
+            for (i, path) in paths.iter().enumerate() {
+                ctx.progress(
+                    i,
+                    paths.len(),
+                    &format!("Processing {} of {}", i + 1, paths.len()),
+                )?;
+                saved.push(process_image(
+                    app_handle,
+                    &settings,
+                    path,
+                    &operations,
+                    &save_dir,
+                    export_options.as_deref(),
+                )?);
+            }
+            serde_json::to_value(saved)
+        }
+        JobRequest::FindDuplicates { dir, options } => {
+            ctx.progress(0, 1, "Hashing images")?;
+            serde_json::to_value(find_duplicate_images(&dir, &options.unwrap_or_default())?)
+        }
+    };
+
+    output.map_err(|e| format!("Failed to serialize job result: {}", e))
+}
+
+/// Detect and decode QR codes in an image, optionally within a region
+/// Copies the first payload to the clipboard when asked
+#[tauri::command]
+pub async fn scan_codes(
+    image_path: String,
+    region: Option<CropRegion>,
+    copy_to_clip: bool,
+) -> Result<Vec<ScannedCode>, String> {
+    let img = load_image(&image_path)?;
+    let codes = detect_codes(&img, region)?;
+
+    if let Some(code) = codes.first().filter(|_| copy_to_clip) {
+        copy_text_to_clipboard(&code.payload)?;
+    }
+
+    Ok(codes)
+}
+
+/// Most colours `extract_palette` returns
+const MAX_PALETTE_COLORS: usize = 32;
+
+/// Find the main colours of an image, optionally within a region
+/// Returns up to `count` colours (5 by default), most common first
+#[tauri::command]
+pub async fn extract_palette(
+    image_path: String,
+    count: Option<usize>,
+    region: Option<CropRegion>,
+) -> Result<Vec<PaletteEntry>, String> {
+    let img = load_image(&image_path)?;
+    let img = match region {
+        Some(region) => {
+            let region = CropRegion::clamped(
+                region.x,
+                region.y,
+                region.width,
+                region.height,
+                img.width(),
+                img.height(),
+            );
+            if !region.is_valid() {
+                return Err(format!(
+                    "Invalid palette region: x={}, y={}, w={}, h={} (image: {}x{})",
+                    region.x,
+                    region.y,
+                    region.width,
+                    region.height,
+                    img.width(),
+                    img.height()
+                ));
+            }
+            img.crop_imm(region.x, region.y, region.width, region.height)
+        }
+        None => img,
+    };
+
+    let count = count.unwrap_or(5).clamp(1, MAX_PALETTE_COLORS);
+    Ok(find_palette(&img, count)
+        .into_iter()
+        .map(PaletteEntry::from)
+        .collect())
+}
+
+/// Combine several images into one, such as a grid or a before/after comparison
+#[tauri::command]
+pub async fn compose_images(
+    app_handle: AppHandle,
+    paths: Vec<String>,
+    save_dir: String,
+    options: Option<ComposeOptions>,
+    export_options: Option<ExportOptions>,
+) -> Result<String, String> {
+    let images = paths
+        .iter()
+        .map(|path| load_image(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let profiles = paths
+        .iter()
+        .map(|path| read_icc_profile(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Keep a profile every source shares, otherwise bring them all to sRGB
+    let (images, profile) = match profiles.windows(2).all(|pair| pair[0] == pair[1]) {
+        true => (images, profiles.into_iter().next().flatten()),
+        false => {
+            let converted = images
+                .iter()
+                .zip(&profiles)
+                .map(|(img, profile)| match profile {
+                    Some(profile) => convert_to_srgb(img, profile),
+                    None => Ok(img.clone()),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            (converted, None)
+        }
+    };
+
+    let composed = compose(&images, &options.unwrap_or_default())?;
+
+    let settings = ExportSettings::load(&app_handle);
+    let export_options = resolve_export_options(&settings, export_options, None)?;
+    let saved_path = save_image_with(
+        &DynamicImage::ImageRgba8(composed),
+        &save_dir,
+        "collage",
+        &export_options,
+        profile.as_deref(),
+    )?;
+    finish_saved_image(&app_handle, &settings, &saved_path, None, false);
+
+    Ok(saved_path)
+}
+
+/// List built-in and custom canvas presets
+#[tauri::command]
+pub async fn list_canvas_presets(app_handle: AppHandle) -> Result<Vec<CanvasPreset>, String> {
+    Ok(all_presets(
+        &ExportSettings::load(&app_handle).canvas_presets,
+    ))
+}
+
+/// Folder in the app config directory holding user frame definitions
+fn frames_dir(app_handle: &AppHandle) -> Option<PathBuf> {
+    app_handle
+        .path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join("frames"))
+}
+
+/// List bundled and user mockup frames
+#[tauri::command]
+pub async fn list_frames(app_handle: AppHandle) -> Result<Vec<FrameInfo>, String> {
+    Ok(frame_infos(&load_frames(
+        frames_dir(&app_handle).as_deref(),
+    )))
+}
+
+/// Wrap a screenshot in a mockup frame and save the result
+/// Stretched frames are drawn at the primary display's scale unless one is given
+#[tauri::command]
+pub async fn render_mockup(
+    app_handle: AppHandle,
+    image_path: String,
+    frame_id: String,
+    save_dir: String,
+    options: Option<MockupOptions>,
+    export_options: Option<ExportOptions>,
+) -> Result<String, String> {
+    let frame = load_frames(frames_dir(&app_handle).as_deref())
+        .into_iter()
+        .find(|frame| frame.definition.id == frame_id)
+        .ok_or_else(|| format!("Unknown frame: {}", frame_id))?;
+
+    let mut options = options.unwrap_or_default();
+    if options.scale.is_none() {
+        options.scale = primary_scale_factor().ok();
+    }
+
+    let img = load_image(&image_path)?;
+    let mockup = DynamicImage::ImageRgba8(render_frame(&img, &frame, &options)?);
+    let profile = read_icc_profile(&image_path)?;
+
+    let metadata = capture_metadata(&image_path);
+    let settings = ExportSettings::load(&app_handle);
+    let export_options = resolve_export_options(&settings, export_options, metadata.as_ref())?;
+    let saved_path = save_image_with(
+        &mockup,
+        &save_dir,
+        "mockup",
+        &export_options,
+        profile.as_deref(),
+    )?;
+    finish_saved_image(
+        &app_handle,
+        &settings,
+        &saved_path,
+        metadata.as_ref(),
+        false,
+    );
+
     Ok(saved_path)
 }
 
@@ -154,7 +990,10 @@ fn check_and_activate_permission() -> Result<(), String> {
 /// Capture screenshot using macOS native screencapture with interactive selection
 /// This properly handles Screen Recording permissions through the system
 #[tauri::command]
-pub async fn native_capture_interactive(save_dir: String) -> Result<String, String> {
+pub async fn native_capture_interactive(
+    app_handle: AppHandle,
+    save_dir: String,
+) -> Result<String, String> {
     let _lock = SCREENCAPTURE_LOCK
         .lock()
         .map_err(|e| format!("Failed to acquire lock: {}", e))?;
@@ -201,6 +1040,8 @@ pub async fn native_capture_interactive(save_dir: String) -> Result<String, Stri
     }
 
     if screenshot_path.exists() {
+        record_capture(&path_str, "region");
+        trim_capture(&app_handle, &path_str);
         Ok(path_str)
     } else {
         Err("Screenshot was cancelled or failed".to_string())
@@ -238,6 +1079,7 @@ pub async fn native_capture_fullscreen(save_dir: String) -> Result<String, Strin
     }
 
     if screenshot_path.exists() {
+        record_capture(&path_str, "fullscreen");
         Ok(path_str)
     } else {
         Err("Screenshot failed".to_string())
@@ -265,6 +1107,11 @@ pub async fn play_screenshot_sound() -> Result<(), String> {
 /// Get the current mouse cursor position (for determining which screen to open editor on)
 #[tauri::command]
 pub async fn get_mouse_position() -> Result<(f64, f64), String> {
+    mouse_position()
+}
+
+/// Read the mouse cursor position in screen points
+fn mouse_position() -> Result<(f64, f64), String> {
     // Use AppleScript to get mouse position - it's the most reliable cross-version approach
     let output = Command::new("osascript")
         .arg("-e")
@@ -295,7 +1142,10 @@ pub async fn get_mouse_position() -> Result<(f64, f64), String> {
 
 /// Capture specific window using macOS native screencapture
 #[tauri::command]
-pub async fn native_capture_window(save_dir: String) -> Result<String, String> {
+pub async fn native_capture_window(
+    app_handle: AppHandle,
+    save_dir: String,
+) -> Result<String, String> {
     let _lock = SCREENCAPTURE_LOCK
         .lock()
         .map_err(|e| format!("Failed to acquire lock: {}", e))?;
@@ -342,6 +1192,11 @@ pub async fn native_capture_window(save_dir: String) -> Result<String, String> {
     }
 
     if screenshot_path.exists() {
+        record_capture(&path_str, "window");
+        // A processed shadow already has the margin we want
+        if !process_window_shadow(&app_handle, &path_str) {
+            trim_capture(&app_handle, &path_str);
+        }
         Ok(path_str)
     } else {
         Err("Screenshot was cancelled or failed".to_string())
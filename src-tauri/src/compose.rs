@@ -0,0 +1,423 @@
+//! Composition module
+//!
+//! Combines several screenshots into one image: rows, columns, grids and
+//! split before/after comparisons.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::draw::{fill_rounded_rect, Color, Rect};
+use crate::image::MAX_EXPORT_DIMENSION;
+use crate::text::{draw_text, line_height, system_font, text_width, TextAlign, TextStyle};
+use crate::utils::AppResult;
+
+/// How the images are arranged
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ComposeLayout {
+    /// Side by side in one row
+    #[default]
+    Horizontal,
+    /// Stacked in one column
+    Vertical,
+    /// Rows of a fixed number of columns, filled left to right
+    Grid { columns: u32 },
+    /// Two images overlaid, the first shown left of a divider and the second right of it
+    BeforeAfter {
+        /// Position of the divider, from 0 (left edge) to 1 (right edge)
+        #[serde(default = "default_split")]
+        split: f32,
+        #[serde(default = "default_divider_width")]
+        divider_width: u32,
+        #[serde(default = "default_divider_color")]
+        divider_color: Color,
+    },
+}
+
+fn default_split() -> f32 {
+    0.5
+}
+
+fn default_divider_width() -> u32 {
+    4
+}
+
+fn default_divider_color() -> Color {
+    Color(Rgba([255, 255, 255, 255]))
+}
+
+/// Placement of images smaller than their row or column
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComposeAlign {
+    Start,
+    #[default]
+    Center,
+    End,
+}
+
+impl ComposeAlign {
+    /// Offset that places an item within `free` spare pixels
+    pub fn offset(self, free: u32) -> u32 {
+        match self {
+            ComposeAlign::Start => 0,
+            ComposeAlign::Center => free / 2,
+            ComposeAlign::End => free,
+        }
+    }
+}
+
+/// Layout, spacing and captions for a composition
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ComposeOptions {
+    pub layout: ComposeLayout,
+    /// Space between images, in pixels
+    pub gap: u32,
+    /// Space around the outside, in pixels
+    pub padding: u32,
+    pub background: Color,
+    pub align: ComposeAlign,
+    /// Caption for each image, in order; empty strings leave an image uncaptioned
+    pub captions: Vec<String>,
+    pub caption_size: f32,
+    pub caption_color: Color,
+}
+
+impl Default for ComposeOptions {
+    fn default() -> Self {
+        Self {
+            layout: ComposeLayout::Horizontal,
+            gap: 16,
+            padding: 16,
+            background: Color(Rgba([255, 255, 255, 255])),
+            align: ComposeAlign::Center,
+            captions: Vec::new(),
+            caption_size: 20.0,
+            caption_color: Color(Rgba([51, 51, 51, 255])),
+        }
+    }
+}
+
+/// Combine images into one according to the layout
+pub fn compose_images(images: &[DynamicImage], options: &ComposeOptions) -> AppResult<RgbaImage> {
+    if images.is_empty() {
+        return Err("At least one image is required".to_string());
+    }
+
+    match options.layout {
+        ComposeLayout::Horizontal => compose_grid(images, images.len() as u32, options),
+        ComposeLayout::Vertical => compose_grid(images, 1, options),
+        ComposeLayout::Grid { columns } => {
+            if columns == 0 {
+                return Err("Grid needs at least one column".to_string());
+            }
+            compose_grid(images, columns, options)
+        }
+        ComposeLayout::BeforeAfter {
+            split,
+            divider_width,
+            divider_color,
+        } => compose_before_after(images, split, divider_width, divider_color, options),
+    }
+}
+
+fn canvas(width: u64, height: u64, background: Color) -> AppResult<RgbaImage> {
+    let max = MAX_EXPORT_DIMENSION as u64;
+    if width > max || height > max {
+        return Err(format!(
+            "Composition of {}x{} exceeds the maximum of {} pixels per side",
+            width, height, max
+        ));
+    }
+    Ok(RgbaImage::from_pixel(
+        width as u32,
+        height as u32,
+        background.0,
+    ))
+}
+
+/// Lay images out in cells sized to the largest image in each row and column
+fn compose_grid(
+    images: &[DynamicImage],
+    columns: u32,
+    options: &ComposeOptions,
+) -> AppResult<RgbaImage> {
+    let columns = columns.min(images.len() as u32) as usize;
+    let rows = images.len().div_ceil(columns);
+
+    let mut column_widths = vec![0u32; columns];
+    let mut row_heights = vec![0u32; rows];
+    for (i, img) in images.iter().enumerate() {
+        column_widths[i % columns] = column_widths[i % columns].max(img.width());
+        row_heights[i / columns] = row_heights[i / columns].max(img.height());
+    }
+
+    // Captions get a line below each image, in rows that have any
+    let font = match options.captions.iter().any(|c| !c.is_empty()) {
+        true => system_font(),
+        false => None,
+    };
+    let caption_height = font
+        .as_ref()
+        .map(|font| (line_height(font, options.caption_size) * 1.5).ceil() as u32)
+        .unwrap_or(0);
+    let captioned_rows: Vec<bool> = (0..rows)
+        .map(|row| {
+            (row * columns..((row + 1) * columns).min(images.len()))
+                .any(|i| options.captions.get(i).is_some_and(|c| !c.is_empty()))
+        })
+        .collect();
+
+    let gap = options.gap as u64;
+    let padding = options.padding as u64;
+    let width = padding * 2
+        + column_widths.iter().map(|&w| w as u64).sum::<u64>()
+        + gap * (columns as u64 - 1);
+    let height = padding * 2
+        + row_heights.iter().map(|&h| h as u64).sum::<u64>()
+        + captioned_rows.iter().filter(|&&c| c).count() as u64 * caption_height as u64
+        + gap * (rows as u64 - 1);
+    let mut canvas = canvas(width, height, options.background)?;
+
+    let style = TextStyle {
+        size: options.caption_size,
+        color: options.caption_color.0,
+        align: TextAlign::Center,
+    };
+    let mut cell_y = padding as u32;
+    for (row, &row_height) in row_heights.iter().enumerate() {
+        let mut cell_x = padding as u32;
+        for (column, &column_width) in column_widths.iter().enumerate() {
+            let Some(img) = images.get(row * columns + column) else {
+                break;
+            };
+            let x = cell_x + options.align.offset(column_width - img.width());
+            let y = cell_y + options.align.offset(row_height - img.height());
+            imageops::overlay(&mut canvas, &img.to_rgba8(), x as i64, y as i64);
+
+            let caption = options.captions.get(row * columns + column);
+            if let (Some(font), Some(caption)) = (&font, caption) {
+                let centre = x as f32 + img.width() as f32 / 2.0;
+                let top = (cell_y + row_height) as f32 + caption_height as f32 / 6.0;
+                draw_text(&mut canvas, font, &style, centre, top, caption);
+            }
+
+            cell_x += column_width + options.gap;
+        }
+        cell_y += row_height + options.gap;
+        if captioned_rows[row] {
+            cell_y += caption_height;
+        }
+    }
+
+    Ok(canvas)
+}
+
+/// Show the left of the first image and the right of the second, split by a divider
+fn compose_before_after(
+    images: &[DynamicImage],
+    split: f32,
+    divider_width: u32,
+    divider_color: Color,
+    options: &ComposeOptions,
+) -> AppResult<RgbaImage> {
+    let [before, after] = images else {
+        return Err("Before/after needs exactly two images".to_string());
+    };
+
+    // The comparison is drawn at the size of the second image
+    let (width, height) = (after.width(), after.height());
+    let before = if (before.width(), before.height()) == (width, height) {
+        before.to_rgba8()
+    } else {
+        before
+            .resize_to_fill(width, height, FilterType::Lanczos3)
+            .to_rgba8()
+    };
+
+    let padding = options.padding as u64;
+    let mut canvas = canvas(
+        width as u64 + padding * 2,
+        height as u64 + padding * 2,
+        options.background,
+    )?;
+    let split_x = (width as f32 * split.clamp(0.0, 1.0)).round() as u32;
+    let mut combined = after.to_rgba8();
+    imageops::replace(
+        &mut combined,
+        &imageops::crop_imm(&before, 0, 0, split_x, height).to_image(),
+        0,
+        0,
+    );
+    imageops::overlay(&mut canvas, &combined, padding as i64, padding as i64);
+
+    let offset = padding as f32;
+    let divider = Rect::new(
+        offset + split_x as f32 - divider_width as f32 / 2.0,
+        offset,
+        divider_width as f32,
+        height as f32,
+    );
+    fill_rounded_rect(&mut canvas, &divider, 0.0, divider_color.0);
+
+    // Captions sit on a translucent label in each top corner
+    let font = match options.captions.iter().any(|c| !c.is_empty()) {
+        true => system_font(),
+        false => None,
+    };
+    if let Some(font) = font {
+        let size = options.caption_size;
+        let inset = size * 0.6;
+        for (i, caption) in options.captions.iter().take(2).enumerate() {
+            if caption.is_empty() {
+                continue;
+            }
+            let label_width = text_width(&font, size, caption) + inset * 2.0;
+            let label_height = line_height(&font, size) + inset;
+            let label_x = match i {
+                0 => offset + inset,
+                _ => offset + width as f32 - inset - label_width,
+            };
+            let label = Rect::new(label_x, offset + inset, label_width, label_height);
+            fill_rounded_rect(
+                &mut canvas,
+                &label,
+                label_height / 2.0,
+                Rgba([0, 0, 0, 140]),
+            );
+            let style = TextStyle {
+                size,
+                color: Rgba([255, 255, 255, 255]),
+                align: TextAlign::Center,
+            };
+            let centre = label_x + label_width / 2.0;
+            draw_text(
+                &mut canvas,
+                &font,
+                &style,
+                centre,
+                label.y + inset / 2.0,
+                caption,
+            );
+        }
+    }
+
+    Ok(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn solid(width: u32, height: u32, color: Rgba<u8>) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, color))
+    }
+
+    fn options(layout: ComposeLayout) -> ComposeOptions {
+        ComposeOptions {
+            layout,
+            gap: 10,
+            padding: 5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_horizontal_aligns_to_centre() {
+        let images = [solid(40, 20, RED), solid(30, 40, BLUE)];
+
+        let composed = compose_images(&images, &options(ComposeLayout::Horizontal)).unwrap();
+
+        assert_eq!(composed.dimensions(), (5 + 40 + 10 + 30 + 5, 5 + 40 + 5));
+        // The shorter image is centred vertically
+        assert_eq!(composed.get_pixel(10, 14), &WHITE);
+        assert_eq!(composed.get_pixel(10, 15), &RED);
+        assert_eq!(composed.get_pixel(10, 34), &RED);
+        assert_eq!(composed.get_pixel(55, 5), &BLUE);
+    }
+
+    #[test]
+    fn test_vertical_start_alignment() {
+        let images = [solid(40, 20, RED), solid(20, 20, BLUE)];
+        let options = ComposeOptions {
+            align: ComposeAlign::Start,
+            ..options(ComposeLayout::Vertical)
+        };
+
+        let composed = compose_images(&images, &options).unwrap();
+
+        assert_eq!(composed.dimensions(), (50, 5 + 20 + 10 + 20 + 5));
+        assert_eq!(composed.get_pixel(5, 35), &BLUE);
+        assert_eq!(composed.get_pixel(25, 35), &WHITE);
+    }
+
+    #[test]
+    fn test_grid_wraps_rows() {
+        let images: Vec<_> = (0..5).map(|_| solid(10, 10, RED)).collect();
+
+        let composed =
+            compose_images(&images, &options(ComposeLayout::Grid { columns: 2 })).unwrap();
+
+        assert_eq!(
+            composed.dimensions(),
+            (5 + 10 + 10 + 10 + 5, 5 + 30 + 20 + 5)
+        );
+        // The last row has one image and an empty cell
+        assert_eq!(composed.get_pixel(5, 45), &RED);
+        assert_eq!(composed.get_pixel(25, 45), &WHITE);
+        assert!(compose_images(&images, &options(ComposeLayout::Grid { columns: 0 })).is_err());
+    }
+
+    #[test]
+    fn test_before_after_split() {
+        let images = [solid(50, 20, RED), solid(100, 40, BLUE)];
+        let layout = ComposeLayout::BeforeAfter {
+            split: 0.25,
+            divider_width: 2,
+            divider_color: Color(Rgba([0, 255, 0, 255])),
+        };
+        let options = ComposeOptions {
+            padding: 0,
+            ..options(layout)
+        };
+
+        let composed = compose_images(&images, &options).unwrap();
+
+        assert_eq!(composed.dimensions(), (100, 40));
+        assert_eq!(composed.get_pixel(10, 20), &RED);
+        assert_eq!(composed.get_pixel(25, 20), &Rgba([0, 255, 0, 255]));
+        assert_eq!(composed.get_pixel(60, 20), &BLUE);
+        assert!(compose_images(&images[..1], &options).is_err());
+    }
+
+    #[test]
+    fn test_captions_add_a_line() {
+        if system_font().is_none() {
+            return;
+        }
+        let images = [solid(60, 20, RED), solid(60, 20, RED)];
+        let options = ComposeOptions {
+            captions: vec!["Before".to_string(), String::new()],
+            caption_color: Color(BLUE),
+            ..options(ComposeLayout::Horizontal)
+        };
+
+        let composed = compose_images(&images, &options).unwrap();
+
+        assert!(composed.height() > 5 + 20 + 5 + 20);
+        let caption_ink = |x0: u32, x1: u32| {
+            (x0..x1).any(|x| {
+                (25..composed.height())
+                    .any(|y| composed.get_pixel(x, y)[2] > 200 && composed.get_pixel(x, y)[0] < 100)
+            })
+        };
+        assert!(caption_ink(5, 65));
+        assert!(!caption_ink(75, 135));
+    }
+}
@@ -0,0 +1,388 @@
+//! Duplicate detection module
+//!
+//! Perceptual hashes stay close for images that look alike, so near-identical
+//! screenshots can be found by Hamming distance.
+
+use image::imageops::{self, FilterType};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::image::load_image;
+use crate::utils::AppResult;
+
+/// Extensions scanned when looking for duplicates
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "webp", "gif"];
+
+/// Maximum number of recent saves remembered for duplicate warnings
+const MAX_RECENT_HASHES: usize = 64;
+
+/// Side of the greyscale thumbnail the DCT runs on
+const PHASH_SIZE: usize = 32;
+
+/// Low-frequency block of DCT coefficients kept in the hash
+const PHASH_BLOCK: usize = 8;
+
+/// Hashes of recently saved images, oldest first
+static RECENT_HASHES: Mutex<Vec<(String, u64, Instant)>> = Mutex::new(Vec::new());
+
+/// Perceptual hash algorithm
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// Difference hash: compares neighbouring pixels, fast
+    DHash,
+    /// DCT hash: compares low frequencies, robust to scaling and compression
+    #[default]
+    PHash,
+}
+
+/// Options for finding duplicates in a directory
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct DuplicateOptions {
+    /// Largest Hamming distance, out of 64 bits, treated as a duplicate
+    pub max_distance: u32,
+    pub algorithm: HashAlgorithm,
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        Self {
+            max_distance: 6,
+            algorithm: HashAlgorithm::PHash,
+        }
+    }
+}
+
+/// Warning when a new save duplicates a recent one, persisted in settings
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct DuplicateWarning {
+    pub enabled: bool,
+    /// Largest Hamming distance treated as a duplicate
+    pub max_distance: u32,
+    /// How far back, in seconds, earlier saves are compared
+    pub window_secs: u64,
+}
+
+impl Default for DuplicateWarning {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_distance: 4,
+            window_secs: 300,
+        }
+    }
+}
+
+/// An image and its perceptual hash
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct HashedImage {
+    pub path: String,
+    /// Hex string, since JavaScript numbers cannot hold 64 bits
+    pub hash: String,
+}
+
+/// Images within the distance threshold of each other
+#[derive(Serialize, Clone, Debug)]
+pub struct DuplicateCluster {
+    pub images: Vec<HashedImage>,
+    /// Largest distance between two images in the cluster
+    pub max_distance: u32,
+}
+
+/// A new save that looks like a recent one
+#[derive(Serialize, Clone, Debug)]
+pub struct DuplicateCapture {
+    pub path: String,
+    pub duplicate_of: String,
+    pub distance: u32,
+}
+
+/// Compute a 64-bit perceptual hash
+pub fn perceptual_hash(img: &DynamicImage, algorithm: HashAlgorithm) -> u64 {
+    match algorithm {
+        HashAlgorithm::DHash => dhash(img),
+        HashAlgorithm::PHash => phash(img),
+    }
+}
+
+/// Number of differing bits between two hashes
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// One bit per pixel of a 9x8 thumbnail: set when brighter than its right neighbour
+fn dhash(img: &DynamicImage) -> u64 {
+    let small = imageops::resize(&img.to_luma8(), 9, 8, FilterType::Triangle);
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// One bit per low-frequency DCT coefficient: set when above the median
+fn phash(img: &DynamicImage) -> u64 {
+    let size = PHASH_SIZE as u32;
+    let small = imageops::resize(&img.to_luma8(), size, size, FilterType::Triangle);
+    let pixels: Vec<f64> = small.pixels().map(|p| p[0] as f64).collect();
+
+    // Separable DCT-II, keeping only the low-frequency block
+    let cosines: Vec<f64> = (0..PHASH_BLOCK * PHASH_SIZE)
+        .map(|i| {
+            let (u, x) = (i / PHASH_SIZE, i % PHASH_SIZE);
+            ((2 * x + 1) as f64 * u as f64 * PI / (2 * PHASH_SIZE) as f64).cos()
+        })
+        .collect();
+
+    let mut rows = vec![0.0; PHASH_SIZE * PHASH_BLOCK];
+    for y in 0..PHASH_SIZE {
+        for u in 0..PHASH_BLOCK {
+            rows[y * PHASH_BLOCK + u] = (0..PHASH_SIZE)
+                .map(|x| pixels[y * PHASH_SIZE + x] * cosines[u * PHASH_SIZE + x])
+                .sum();
+        }
+    }
+
+    let mut coefficients = [0.0; PHASH_BLOCK * PHASH_BLOCK];
+    for v in 0..PHASH_BLOCK {
+        for u in 0..PHASH_BLOCK {
+            coefficients[v * PHASH_BLOCK + u] = (0..PHASH_SIZE)
+                .map(|y| rows[y * PHASH_BLOCK + u] * cosines[v * PHASH_SIZE + y])
+                .sum();
+        }
+    }
+
+    // The DC term is overall brightness, so it is left out of the median
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+
+    coefficients
+        .iter()
+        .fold(0u64, |hash, &c| (hash << 1) | (c > median) as u64)
+}
+
+/// Hash an image file
+pub fn hash_file(path: &str, algorithm: HashAlgorithm) -> AppResult<u64> {
+    Ok(perceptual_hash(&load_image(path)?, algorithm))
+}
+
+/// Find clusters of duplicate or near-duplicate images in a directory
+///
+/// Images are linked when within `max_distance` of each other, and clusters
+/// are the connected groups. Unreadable files are skipped.
+pub fn find_duplicates(dir: &str, options: &DuplicateOptions) -> AppResult<Vec<DuplicateCluster>> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && is_image_path(path))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    paths.sort();
+
+    let hashed: Vec<(String, u64)> = paths
+        .into_iter()
+        .filter_map(|path| match hash_file(&path, options.algorithm) {
+            Ok(hash) => Some((path, hash)),
+            Err(e) => {
+                eprintln!("Skipping {}: {}", path, e);
+                None
+            }
+        })
+        .collect();
+
+    Ok(cluster(&hashed, options.max_distance))
+}
+
+fn is_image_path(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// Group hashes into clusters of two or more, using union-find
+fn cluster(hashed: &[(String, u64)], max_distance: u32) -> Vec<DuplicateCluster> {
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+
+    let mut parents: Vec<usize> = (0..hashed.len()).collect();
+    for i in 0..hashed.len() {
+        for j in i + 1..hashed.len() {
+            if hamming_distance(hashed[i].1, hashed[j].1) <= max_distance {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    for i in 0..hashed.len() {
+        let r = root(&mut parents, i);
+        match groups.iter_mut().find(|(group_root, _)| *group_root == r) {
+            Some((_, members)) => members.push(i),
+            None => groups.push((r, vec![i])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(_, members)| {
+            let max_distance = members
+                .iter()
+                .flat_map(|&a| members.iter().map(move |&b| (a, b)))
+                .map(|(a, b)| hamming_distance(hashed[a].1, hashed[b].1))
+                .max()
+                .unwrap_or(0);
+            let images = members
+                .into_iter()
+                .map(|i| HashedImage {
+                    path: hashed[i].0.clone(),
+                    hash: format!("{:016x}", hashed[i].1),
+                })
+                .collect();
+            DuplicateCluster {
+                images,
+                max_distance,
+            }
+        })
+        .collect()
+}
+
+/// Remember the hash of a saved image, returning the closest recent duplicate
+pub fn record_saved_hash(
+    path: &str,
+    hash: u64,
+    warning: &DuplicateWarning,
+) -> Option<DuplicateCapture> {
+    let mut recent = RECENT_HASHES.lock().ok()?;
+    let now = Instant::now();
+    let window = Duration::from_secs(warning.window_secs);
+
+    recent.retain(|(p, _, saved_at)| p != path && now.duration_since(*saved_at) <= window);
+    let duplicate = recent
+        .iter()
+        .map(|(p, h, _)| (p, hamming_distance(hash, *h)))
+        .filter(|(_, distance)| *distance <= warning.max_distance)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(p, distance)| DuplicateCapture {
+            path: path.to_string(),
+            duplicate_of: p.clone(),
+            distance,
+        });
+
+    if recent.len() >= MAX_RECENT_HASHES {
+        recent.remove(0);
+    }
+    recent.push((path.to_string(), hash, now));
+
+    duplicate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    /// Window-like layout: title bar, sidebar and a block of content
+    fn layout(accent: [u8; 3], sidebar_width: u32) -> DynamicImage {
+        let [r, g, b] = accent;
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(320, 200, |x, y| {
+            if y < 24 {
+                Rgba([r, g, b, 255])
+            } else if x < sidebar_width {
+                Rgba([40, 40, 48, 255])
+            } else if (y / 12) % 2 == 0 && x < 280 {
+                Rgba([200, 200, 200, 255])
+            } else {
+                Rgba([250, 250, 250, 255])
+            }
+        }))
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(hamming_distance(u64::MAX, 0), 64);
+    }
+
+    #[test]
+    fn test_near_duplicates_hash_close() {
+        let original = layout([30, 120, 220], 80);
+        let resized = original.resize_exact(160, 100, FilterType::Lanczos3);
+        let brighter = original.brighten(12);
+
+        for algorithm in [HashAlgorithm::DHash, HashAlgorithm::PHash] {
+            let hash = perceptual_hash(&original, algorithm);
+            assert!(hamming_distance(hash, perceptual_hash(&resized, algorithm)) <= 4);
+            assert!(hamming_distance(hash, perceptual_hash(&brighter, algorithm)) <= 4);
+        }
+    }
+
+    #[test]
+    fn test_different_images_hash_apart() {
+        let a = layout([30, 120, 220], 80);
+        let b = a.rotate90().resize_exact(320, 200, FilterType::Triangle);
+
+        for algorithm in [HashAlgorithm::DHash, HashAlgorithm::PHash] {
+            let distance = hamming_distance(
+                perceptual_hash(&a, algorithm),
+                perceptual_hash(&b, algorithm),
+            );
+            assert!(distance > 10, "{:?} distance {}", algorithm, distance);
+        }
+    }
+
+    #[test]
+    fn test_cluster_groups_transitive_matches() {
+        let hashed = vec![
+            ("a.png".to_string(), 0b0000),
+            ("b.png".to_string(), 0b0011),
+            ("c.png".to_string(), 0b1111),
+            ("d.png".to_string(), u64::MAX),
+        ];
+
+        let clusters = cluster(&hashed, 2);
+
+        assert_eq!(clusters.len(), 1);
+        let paths: Vec<&str> = clusters[0].images.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, ["a.png", "b.png", "c.png"]);
+        assert_eq!(clusters[0].max_distance, 4);
+        assert_eq!(clusters[0].images[2].hash, "000000000000000f");
+    }
+
+    #[test]
+    fn test_recent_duplicate_warning() {
+        let warning = DuplicateWarning {
+            enabled: true,
+            ..Default::default()
+        };
+
+        assert!(record_saved_hash("/tmp/dedupe_first.png", 0xF0F0, &warning).is_none());
+        let duplicate = record_saved_hash("/tmp/dedupe_second.png", 0xF0F1, &warning).unwrap();
+
+        assert_eq!(duplicate.duplicate_of, "/tmp/dedupe_first.png");
+        assert_eq!(duplicate.distance, 1);
+        assert!(record_saved_hash("/tmp/dedupe_third.png", !0xF0F0, &warning).is_none());
+    }
+}
@@ -0,0 +1,540 @@
+//! Visual diff module
+//!
+//! Compares two screenshots for visual regression checks: aligns them,
+//! highlights changed pixels and scores how similar they are.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::AppResult;
+
+/// Colour used for changed pixels in the diff image
+const HIGHLIGHT: Rgba<u8> = Rgba([255, 0, 64, 255]);
+
+/// Alignment search runs on thumbnails no larger than this per side
+const ALIGN_THUMBNAIL: u32 = 256;
+
+/// SSIM window size and stride, in pixels
+const SSIM_WINDOW: u32 = 8;
+const SSIM_STRIDE: u32 = 4;
+
+/// Options for comparing two images
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    /// Largest per-channel difference still treated as unchanged
+    pub tolerance: u8,
+    /// Largest shift, in pixels, searched when aligning the images
+    pub max_offset: u32,
+    /// Changed pixels closer than this are grouped into one region
+    pub merge_distance: u32,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 16,
+            max_offset: 32,
+            merge_distance: 16,
+        }
+    }
+}
+
+/// Bounding box of a group of changed pixels
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DiffRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub changed_pixels: u64,
+}
+
+/// Outcome of comparing two images, in the coordinates of the second one
+#[derive(Serialize, Clone, Debug)]
+pub struct DiffReport {
+    pub width: u32,
+    pub height: u32,
+    /// Shift of the second image's content relative to the first
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub changed_pixels: u64,
+    /// Percentage of pixels within tolerance
+    pub pixel_similarity: f64,
+    /// Mean structural similarity of the overlapping area, from -1 to 1
+    pub ssim: f64,
+    pub regions: Vec<DiffRegion>,
+}
+
+/// Saved diff image along with the comparison results
+#[derive(Serialize, Clone, Debug)]
+pub struct ImageDiff {
+    pub path: String,
+    #[serde(flatten)]
+    pub report: DiffReport,
+}
+
+/// Compare two images, returning the diff image and report
+///
+/// The diff image is a faded copy of `after` with changed pixels highlighted.
+/// Pixels of `after` with no aligned counterpart in `before` count as changed.
+pub fn diff_images(
+    before: &DynamicImage,
+    after: &DynamicImage,
+    options: &DiffOptions,
+) -> AppResult<(RgbaImage, DiffReport)> {
+    if before.width() == 0 || before.height() == 0 || after.width() == 0 || after.height() == 0 {
+        return Err("Cannot compare empty images".to_string());
+    }
+
+    let (offset_x, offset_y) = align(before, after, options.max_offset);
+    let before_rgba = before.to_rgba8();
+    let after_rgba = after.to_rgba8();
+    let (width, height) = after_rgba.dimensions();
+
+    let mut diff = RgbaImage::new(width, height);
+    let mut changed = vec![false; width as usize * height as usize];
+    let mut changed_pixels = 0u64;
+
+    for (x, y, pixel) in after_rgba.enumerate_pixels() {
+        let source = source_coords(x, y, offset_x, offset_y, before_rgba.dimensions())
+            .map(|(bx, by)| before_rgba.get_pixel(bx, by));
+        let is_changed = match source {
+            Some(before_pixel) => exceeds_tolerance(before_pixel, pixel, options.tolerance),
+            None => true,
+        };
+
+        if is_changed {
+            changed[(y * width + x) as usize] = true;
+            changed_pixels += 1;
+            diff.put_pixel(x, y, HIGHLIGHT);
+        } else {
+            diff.put_pixel(x, y, faded(pixel));
+        }
+    }
+
+    let total = width as u64 * height as u64;
+    let report = DiffReport {
+        width,
+        height,
+        offset_x,
+        offset_y,
+        changed_pixels,
+        pixel_similarity: (total - changed_pixels) as f64 / total as f64 * 100.0,
+        ssim: ssim(&before.to_luma8(), &after.to_luma8(), offset_x, offset_y),
+        regions: changed_regions(&changed, width, height, options.merge_distance.max(1)),
+    };
+
+    Ok((diff, report))
+}
+
+/// Position in `before` matching a pixel of `after`, if it lies inside the image
+fn source_coords(
+    x: u32,
+    y: u32,
+    offset_x: i32,
+    offset_y: i32,
+    (width, height): (u32, u32),
+) -> Option<(u32, u32)> {
+    let bx = x as i64 - offset_x as i64;
+    let by = y as i64 - offset_y as i64;
+    (bx >= 0 && by >= 0 && bx < width as i64 && by < height as i64)
+        .then_some((bx as u32, by as u32))
+}
+
+fn exceeds_tolerance(a: &Rgba<u8>, b: &Rgba<u8>, tolerance: u8) -> bool {
+    a.0.iter()
+        .zip(b.0.iter())
+        .any(|(a, b)| a.abs_diff(*b) > tolerance)
+}
+
+/// Light grey version of an unchanged pixel, so highlights stand out
+fn faded(pixel: &Rgba<u8>) -> Rgba<u8> {
+    let [r, g, b, _] = pixel.0;
+    let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+    let value = (255 - (255 - luma) / 3) as u8;
+    Rgba([value, value, value, 255])
+}
+
+/// Find the shift of `after` relative to `before` within `max_offset`
+///
+/// Searches coarsely on thumbnails, then refines on sampled full-size pixels.
+/// Ties prefer the smallest shift, so identical layouts stay unshifted.
+fn align(before: &DynamicImage, after: &DynamicImage, max_offset: u32) -> (i32, i32) {
+    if max_offset == 0 {
+        return (0, 0);
+    }
+
+    let longest = before
+        .width()
+        .max(before.height())
+        .max(after.width())
+        .max(after.height());
+    let factor = longest.div_ceil(ALIGN_THUMBNAIL).max(1);
+    let thumbnail = |img: &DynamicImage| {
+        let width = (img.width() / factor).max(1);
+        let height = (img.height() / factor).max(1);
+        imageops::resize(&img.to_luma8(), width, height, FilterType::Triangle)
+    };
+
+    let coarse_range = (max_offset / factor) as i32;
+    let (coarse_x, coarse_y) = best_offset(
+        &thumbnail(before),
+        &thumbnail(after),
+        (-coarse_range..=coarse_range, -coarse_range..=coarse_range),
+        1,
+    );
+    if factor == 1 {
+        return (coarse_x, coarse_y);
+    }
+
+    let max = max_offset as i32;
+    let spread = factor as i32;
+    let centre_x = coarse_x * spread;
+    let centre_y = coarse_y * spread;
+    best_offset(
+        &before.to_luma8(),
+        &after.to_luma8(),
+        (
+            (centre_x - spread).max(-max)..=(centre_x + spread).min(max),
+            (centre_y - spread).max(-max)..=(centre_y + spread).min(max),
+        ),
+        factor,
+    )
+}
+
+/// Offset with the lowest mean absolute difference over the overlap
+fn best_offset(
+    before: &GrayImage,
+    after: &GrayImage,
+    (range_x, range_y): (std::ops::RangeInclusive<i32>, std::ops::RangeInclusive<i32>),
+    step: u32,
+) -> (i32, i32) {
+    let mut best: (i32, i32) = (0, 0);
+    let mut best_score = mean_abs_diff(before, after, 0, 0, step).unwrap_or(f64::MAX);
+
+    for dy in range_y {
+        for dx in range_x.clone() {
+            let Some(score) = mean_abs_diff(before, after, dx, dy, step) else {
+                continue;
+            };
+            let shorter = dx.abs() + dy.abs() < best.0.abs() + best.1.abs();
+            if score < best_score || (score == best_score && shorter) {
+                best = (dx, dy);
+                best_score = score;
+            }
+        }
+    }
+
+    best
+}
+
+/// Mean absolute difference of the overlap, sampling every `step` pixels
+///
+/// Returns `None` when less than half of `after` overlaps `before`.
+fn mean_abs_diff(
+    before: &GrayImage,
+    after: &GrayImage,
+    dx: i32,
+    dy: i32,
+    step: u32,
+) -> Option<f64> {
+    let x_start = dx.max(0) as u32;
+    let y_start = dy.max(0) as u32;
+    let x_end = (before.width() as i64 + dx as i64).clamp(0, after.width() as i64) as u32;
+    let y_end = (before.height() as i64 + dy as i64).clamp(0, after.height() as i64) as u32;
+    if x_end <= x_start || y_end <= y_start {
+        return None;
+    }
+
+    let overlap = (x_end - x_start) as u64 * (y_end - y_start) as u64;
+    if overlap * 2 < after.width() as u64 * after.height() as u64 {
+        return None;
+    }
+
+    let mut sum = 0u64;
+    let mut count = 0u64;
+    for y in (y_start..y_end).step_by(step as usize) {
+        for x in (x_start..x_end).step_by(step as usize) {
+            let a = before.get_pixel((x as i32 - dx) as u32, (y as i32 - dy) as u32)[0];
+            let b = after.get_pixel(x, y)[0];
+            sum += a.abs_diff(b) as u64;
+            count += 1;
+        }
+    }
+
+    Some(sum as f64 / count as f64)
+}
+
+/// Mean SSIM of the overlapping area, over sliding luma windows
+fn ssim(before: &GrayImage, after: &GrayImage, dx: i32, dy: i32) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let x_start = dx.max(0) as u32;
+    let y_start = dy.max(0) as u32;
+    let x_end = (before.width() as i64 + dx as i64).clamp(0, after.width() as i64) as u32;
+    let y_end = (before.height() as i64 + dy as i64).clamp(0, after.height() as i64) as u32;
+    if x_end <= x_start || y_end <= y_start {
+        return 0.0;
+    }
+
+    // Small overlaps are scored as a single window
+    let window_w = SSIM_WINDOW.min(x_end - x_start);
+    let window_h = SSIM_WINDOW.min(y_end - y_start);
+
+    let mut total = 0.0;
+    let mut windows = 0u64;
+    let mut wy = y_start;
+    while wy + window_h <= y_end {
+        let mut wx = x_start;
+        while wx + window_w <= x_end {
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0.0, 0.0, 0.0, 0.0, 0.0);
+            for y in wy..wy + window_h {
+                for x in wx..wx + window_w {
+                    let a =
+                        before.get_pixel((x as i32 - dx) as u32, (y as i32 - dy) as u32)[0] as f64;
+                    let b = after.get_pixel(x, y)[0] as f64;
+                    sum_a += a;
+                    sum_b += b;
+                    sum_aa += a * a;
+                    sum_bb += b * b;
+                    sum_ab += a * b;
+                }
+            }
+
+            let n = (window_w * window_h) as f64;
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let var_a = sum_aa / n - mean_a * mean_a;
+            let var_b = sum_bb / n - mean_b * mean_b;
+            let covariance = sum_ab / n - mean_a * mean_b;
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+            wx += SSIM_STRIDE;
+        }
+        wy += SSIM_STRIDE;
+    }
+
+    total / windows as f64
+}
+
+/// Bounding box of changed pixels, inclusive on both ends
+#[derive(Clone, Copy)]
+struct Bounds {
+    min_x: u32,
+    min_y: u32,
+    max_x: u32,
+    max_y: u32,
+    count: u64,
+}
+
+impl Bounds {
+    fn point(x: u32, y: u32) -> Self {
+        Self {
+            min_x: x,
+            min_y: y,
+            max_x: x,
+            max_y: y,
+            count: 1,
+        }
+    }
+
+    fn merge(&mut self, other: &Bounds) {
+        self.min_x = self.min_x.min(other.min_x);
+        self.min_y = self.min_y.min(other.min_y);
+        self.max_x = self.max_x.max(other.max_x);
+        self.max_y = self.max_y.max(other.max_y);
+        self.count += other.count;
+    }
+}
+
+/// Group changed pixels into regions
+///
+/// The mask is split into cells of `cell` pixels; touching cells with
+/// changes form one region, bounded by the changed pixels inside them.
+fn changed_regions(changed: &[bool], width: u32, height: u32, cell: u32) -> Vec<DiffRegion> {
+    let cols = width.div_ceil(cell) as usize;
+    let rows = height.div_ceil(cell) as usize;
+
+    let mut cells: Vec<Option<Bounds>> = vec![None; cols * rows];
+    for y in 0..height {
+        for x in 0..width {
+            if !changed[(y * width + x) as usize] {
+                continue;
+            }
+            let point = Bounds::point(x, y);
+            match &mut cells[(y / cell) as usize * cols + (x / cell) as usize] {
+                Some(bounds) => bounds.merge(&point),
+                empty => *empty = Some(point),
+            }
+        }
+    }
+
+    let mut visited = vec![false; cells.len()];
+    let mut regions = Vec::new();
+    for start in 0..cells.len() {
+        let Some(mut bounds) = cells[start].filter(|_| !visited[start]) else {
+            continue;
+        };
+        bounds.count = 0;
+
+        let mut stack = vec![start];
+        visited[start] = true;
+        while let Some(index) = stack.pop() {
+            if let Some(cell_bounds) = &cells[index] {
+                bounds.merge(cell_bounds);
+            }
+
+            let (col, row) = (index % cols, index / cols);
+            for ny in row.saturating_sub(1)..=(row + 1).min(rows - 1) {
+                for nx in col.saturating_sub(1)..=(col + 1).min(cols - 1) {
+                    let neighbour = ny * cols + nx;
+                    if !visited[neighbour] && cells[neighbour].is_some() {
+                        visited[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+        }
+
+        regions.push(DiffRegion {
+            x: bounds.min_x,
+            y: bounds.min_y,
+            width: bounds.max_x - bounds.min_x + 1,
+            height: bounds.max_y - bounds.min_y + 1,
+            changed_pixels: bounds.count,
+        });
+    }
+
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pattern with enough texture to align against
+    fn pattern(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let v = ((x * 7 + y * 13) ^ (x * y)) as u8;
+            Rgba([v, v.wrapping_mul(3), 255 - v, 255])
+        })
+    }
+
+    fn fill(img: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32) {
+        for py in y..y + height {
+            for px in x..x + width {
+                img.put_pixel(px, py, Rgba([0, 255, 0, 255]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_identical_images() {
+        let img = DynamicImage::ImageRgba8(pattern(64, 48));
+
+        let (_, report) = diff_images(&img, &img, &DiffOptions::default()).unwrap();
+
+        assert_eq!(report.changed_pixels, 0);
+        assert_eq!(report.pixel_similarity, 100.0);
+        assert!((report.ssim - 1.0).abs() < 1e-9);
+        assert!(report.regions.is_empty());
+        assert_eq!((report.offset_x, report.offset_y), (0, 0));
+    }
+
+    #[test]
+    fn test_changed_regions_are_boxed() {
+        let before = pattern(120, 80);
+        let mut after = before.clone();
+        fill(&mut after, 10, 10, 5, 4);
+        fill(&mut after, 90, 60, 8, 8);
+
+        let (diff, report) = diff_images(
+            &DynamicImage::ImageRgba8(before),
+            &DynamicImage::ImageRgba8(after),
+            &DiffOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(report.changed_pixels, 20 + 64);
+        assert_eq!(
+            report.regions,
+            vec![
+                DiffRegion {
+                    x: 10,
+                    y: 10,
+                    width: 5,
+                    height: 4,
+                    changed_pixels: 20
+                },
+                DiffRegion {
+                    x: 90,
+                    y: 60,
+                    width: 8,
+                    height: 8,
+                    changed_pixels: 64
+                },
+            ]
+        );
+        assert_eq!(diff.get_pixel(12, 12), &HIGHLIGHT);
+        assert_ne!(diff.get_pixel(50, 40), &HIGHLIGHT);
+        assert!(report.ssim < 1.0);
+    }
+
+    #[test]
+    fn test_tolerance_ignores_small_differences() {
+        let before = pattern(32, 32);
+        let after = RgbaImage::from_fn(32, 32, |x, y| {
+            let Rgba([r, g, b, a]) = *before.get_pixel(x, y);
+            Rgba([r.saturating_add(3), g, b.saturating_sub(3), a])
+        });
+
+        let (_, report) = diff_images(
+            &DynamicImage::ImageRgba8(before),
+            &DynamicImage::ImageRgba8(after),
+            &DiffOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(report.changed_pixels, 0);
+    }
+
+    #[test]
+    fn test_aligns_shifted_content() {
+        let source = pattern(140, 100);
+        let before = imageops::crop_imm(&source, 10, 10, 120, 80).to_image();
+        let after = imageops::crop_imm(&source, 13, 8, 120, 80).to_image();
+
+        let (_, report) = diff_images(
+            &DynamicImage::ImageRgba8(before),
+            &DynamicImage::ImageRgba8(after),
+            &DiffOptions::default(),
+        )
+        .unwrap();
+
+        // Content moved left by 3 and down by 2
+        assert_eq!((report.offset_x, report.offset_y), (-3, 2));
+        // Only the uncovered strips along the edges differ
+        assert_eq!(report.changed_pixels, 120 * 80 - 117 * 78);
+    }
+
+    #[test]
+    fn test_different_sizes_count_missing_pixels() {
+        let before = DynamicImage::ImageRgba8(pattern(40, 40));
+        let after = DynamicImage::ImageRgba8(pattern(50, 40));
+        let options = DiffOptions {
+            max_offset: 0,
+            ..Default::default()
+        };
+
+        let (_, report) = diff_images(&before, &after, &options).unwrap();
+
+        assert_eq!(report.changed_pixels, 10 * 40);
+        assert_eq!(report.regions.len(), 1);
+        assert_eq!(report.regions[0].x, 40);
+    }
+}
@@ -0,0 +1,357 @@
+//! Drawing module
+//!
+//! Antialiased shape primitives for renderers that draw on top of, or
+//! around, a screenshot.
+
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::AppResult;
+
+/// Rectangle in floating-point pixel coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+}
+
+/// Colour written as `#RRGGBB` or `#RRGGBBAA` in JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub Rgba<u8>);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse_color(&value).map(Color)
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        let [r, g, b, a] = color.0 .0;
+        match a {
+            255 => format!("#{:02X}{:02X}{:02X}", r, g, b),
+            _ => format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a),
+        }
+    }
+}
+
+/// Parse a `#RRGGBB` or `#RRGGBBAA` hex colour
+pub fn parse_color(value: &str) -> AppResult<Rgba<u8>> {
+    let hex = value
+        .strip_prefix('#')
+        .filter(|hex| (hex.len() == 6 || hex.len() == 8) && hex.is_ascii())
+        .ok_or_else(|| format!("Invalid colour: {}", value))?;
+
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("Invalid colour: {}", value))
+    };
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+
+    Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, alpha]))
+}
+
+/// Which corners of a rectangle are rounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Corners {
+    pub top_left: bool,
+    pub top_right: bool,
+    pub bottom_left: bool,
+    pub bottom_right: bool,
+}
+
+impl Corners {
+    pub const ALL: Corners = Corners {
+        top_left: true,
+        top_right: true,
+        bottom_left: true,
+        bottom_right: true,
+    };
+}
+
+/// Composite a colour over a pixel, scaled by coverage from 0 to 1
+pub fn blend(pixel: &mut Rgba<u8>, color: Rgba<u8>, coverage: f32) {
+    let src_alpha = color[3] as f32 / 255.0 * coverage.clamp(0.0, 1.0);
+    if src_alpha <= 0.0 {
+        return;
+    }
+
+    let dst_alpha = pixel[3] as f32 / 255.0;
+    let out_alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+    for c in 0..3 {
+        let value = (color[c] as f32 * src_alpha + pixel[c] as f32 * dst_alpha * (1.0 - src_alpha))
+            / out_alpha;
+        pixel[c] = value.round() as u8;
+    }
+    pixel[3] = (out_alpha * 255.0).round() as u8;
+}
+
+/// Coverage of the pixel centred at (px, py) by a rounded rectangle
+///
+/// Uses the signed distance to the shape, which antialiases over one pixel.
+pub fn rounded_rect_coverage(px: f32, py: f32, rect: &Rect, radius: f32, corners: Corners) -> f32 {
+    let half_w = rect.width / 2.0;
+    let half_h = rect.height / 2.0;
+    let dx = px - (rect.x + half_w);
+    let dy = py - (rect.y + half_h);
+
+    let rounded = match (dx < 0.0, dy < 0.0) {
+        (true, true) => corners.top_left,
+        (false, true) => corners.top_right,
+        (true, false) => corners.bottom_left,
+        (false, false) => corners.bottom_right,
+    };
+    let radius = if rounded {
+        radius.clamp(0.0, half_w.min(half_h))
+    } else {
+        0.0
+    };
+
+    let qx = dx.abs() - half_w + radius;
+    let qy = dy.abs() - half_h + radius;
+    let outside = qx.max(0.0).hypot(qy.max(0.0));
+    let distance = outside + qx.max(qy).min(0.0) - radius;
+
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+/// Fill a rounded rectangle
+pub fn fill_rounded_rect(img: &mut RgbaImage, rect: &Rect, radius: f32, color: Rgba<u8>) {
+    fill_rounded_rect_corners(img, rect, radius, Corners::ALL, color);
+}
+
+/// Fill a rectangle with only some corners rounded
+pub fn fill_rounded_rect_corners(
+    img: &mut RgbaImage,
+    rect: &Rect,
+    radius: f32,
+    corners: Corners,
+    color: Rgba<u8>,
+) {
+    let Some((x0, y0, x1, y1)) = pixel_bounds(img, rect.x, rect.y, rect.right(), rect.bottom())
+    else {
+        return;
+    };
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let coverage =
+                rounded_rect_coverage(x as f32 + 0.5, y as f32 + 0.5, rect, radius, corners);
+            if coverage > 0.0 {
+                blend(img.get_pixel_mut(x, y), color, coverage);
+            }
+        }
+    }
+}
+
+/// Fill a circle
+pub fn fill_circle(img: &mut RgbaImage, cx: f32, cy: f32, radius: f32, color: Rgba<u8>) {
+    let rect = Rect::new(cx - radius, cy - radius, radius * 2.0, radius * 2.0);
+    fill_rounded_rect(img, &rect, radius, color);
+}
+
+/// Stroke the inside edge of a rounded rectangle
+pub fn stroke_rounded_rect(
+    img: &mut RgbaImage,
+    rect: &Rect,
+    radius: f32,
+    width: f32,
+    color: Rgba<u8>,
+) {
+    let Some((x0, y0, x1, y1)) = pixel_bounds(img, rect.x, rect.y, rect.right(), rect.bottom())
+    else {
+        return;
+    };
+
+    let inner = Rect::new(
+        rect.x + width,
+        rect.y + width,
+        rect.width - width * 2.0,
+        rect.height - width * 2.0,
+    );
+    let inner_radius = (radius - width).max(0.0);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let outer = rounded_rect_coverage(px, py, rect, radius, Corners::ALL);
+            let hole = match inner.width > 0.0 && inner.height > 0.0 {
+                true => rounded_rect_coverage(px, py, &inner, inner_radius, Corners::ALL),
+                false => 0.0,
+            };
+            let coverage = outer - hole;
+            if coverage > 0.0 {
+                blend(img.get_pixel_mut(x, y), color, coverage);
+            }
+        }
+    }
+}
+
+/// Draw a line with round caps
+pub fn draw_line(
+    img: &mut RgbaImage,
+    from: (f32, f32),
+    to: (f32, f32),
+    width: f32,
+    color: Rgba<u8>,
+) {
+    let half = width / 2.0;
+    let Some((x0, y0, x1, y1)) = pixel_bounds(
+        img,
+        from.0.min(to.0) - half - 1.0,
+        from.1.min(to.1) - half - 1.0,
+        from.0.max(to.0) + half + 1.0,
+        from.1.max(to.1) + half + 1.0,
+    ) else {
+        return;
+    };
+
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length_sq = (dx * dx + dy * dy).max(f32::EPSILON);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            // Distance from the pixel centre to the nearest point on the segment
+            let (px, py) = (x as f32 + 0.5 - from.0, y as f32 + 0.5 - from.1);
+            let t = ((px * dx + py * dy) / length_sq).clamp(0.0, 1.0);
+            let distance = (px - t * dx).hypot(py - t * dy);
+            let coverage = (half + 0.5 - distance).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                blend(img.get_pixel_mut(x, y), color, coverage);
+            }
+        }
+    }
+}
+
+/// Scale the alpha of an image by a rounded-corner mask covering all of it
+pub fn mask_corners(img: &mut RgbaImage, radius: f32, corners: Corners) {
+    if radius <= 0.0 {
+        return;
+    }
+
+    let rect = Rect::new(0.0, 0.0, img.width() as f32, img.height() as f32);
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let coverage =
+            rounded_rect_coverage(x as f32 + 0.5, y as f32 + 0.5, &rect, radius, corners);
+        if coverage < 1.0 {
+            pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+        }
+    }
+}
+
+/// Integer pixel range touched by a float rectangle, clipped to the image
+fn pixel_bounds(
+    img: &RgbaImage,
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+) -> Option<(u32, u32, u32, u32)> {
+    let clip = |v: f32, max: u32| v.clamp(0.0, max as f32) as u32;
+    let bounds = (
+        clip(x0.floor(), img.width()),
+        clip(y0.floor(), img.height()),
+        clip(x1.ceil(), img.width()),
+        clip(y1.ceil(), img.height()),
+    );
+    (bounds.0 < bounds.2 && bounds.1 < bounds.3).then_some(bounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#FF8000").unwrap(), Rgba([255, 128, 0, 255]));
+        assert_eq!(parse_color("#00000080").unwrap(), Rgba([0, 0, 0, 128]));
+        assert!(parse_color("FF8000").is_err());
+        assert!(parse_color("#FF80").is_err());
+        assert!(parse_color("#GG0000").is_err());
+
+        assert_eq!(String::from(Color(Rgba([255, 128, 0, 255]))), "#FF8000");
+        assert_eq!(String::from(Color(Rgba([0, 0, 0, 128]))), "#00000080");
+    }
+
+    #[test]
+    fn test_blend_over_transparent_and_opaque() {
+        let mut transparent = Rgba([0, 0, 0, 0]);
+        blend(&mut transparent, Rgba([200, 100, 50, 255]), 0.5);
+        assert_eq!(transparent, Rgba([200, 100, 50, 128]));
+
+        let mut white = Rgba([255, 255, 255, 255]);
+        blend(&mut white, Rgba([0, 0, 0, 255]), 0.5);
+        assert_eq!(white, Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn test_rounded_rect_corners() {
+        let mut img = RgbaImage::new(20, 20);
+        let rect = Rect::new(0.0, 0.0, 20.0, 20.0);
+        let corners = Corners {
+            top_left: false,
+            ..Corners::ALL
+        };
+
+        fill_rounded_rect_corners(&mut img, &rect, 8.0, corners, Rgba([255, 0, 0, 255]));
+
+        assert_eq!(img.get_pixel(0, 0)[3], 255);
+        assert_eq!(img.get_pixel(19, 0)[3], 0);
+        assert_eq!(img.get_pixel(10, 10)[3], 255);
+        // Edge of the arc is partially covered
+        let edge = img.get_pixel(17, 2)[3];
+        assert!(edge > 0 && edge < 255, "{}", edge);
+    }
+
+    #[test]
+    fn test_mask_corners() {
+        let mut img = RgbaImage::from_pixel(16, 16, Rgba([10, 20, 30, 255]));
+
+        mask_corners(&mut img, 6.0, Corners::ALL);
+
+        assert_eq!(img.get_pixel(0, 0)[3], 0);
+        assert_eq!(img.get_pixel(15, 15)[3], 0);
+        assert_eq!(img.get_pixel(8, 0)[3], 255);
+    }
+
+    #[test]
+    fn test_stroke_and_line() {
+        let mut img = RgbaImage::new(20, 20);
+        let rect = Rect::new(2.0, 2.0, 16.0, 16.0);
+
+        stroke_rounded_rect(&mut img, &rect, 0.0, 2.0, Rgba([255, 0, 0, 255]));
+        draw_line(
+            &mut img,
+            (0.0, 10.0),
+            (20.0, 10.0),
+            2.0,
+            Rgba([0, 0, 255, 255]),
+        );
+
+        assert_eq!(img.get_pixel(2, 5), &Rgba([255, 0, 0, 255]));
+        assert_eq!(img.get_pixel(4, 5)[3], 0);
+        assert_eq!(img.get_pixel(10, 9), &Rgba([0, 0, 255, 255]));
+        assert_eq!(img.get_pixel(10, 12)[3], 0);
+    }
+}
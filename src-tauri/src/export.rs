@@ -0,0 +1,338 @@
+//! Size-constrained export module
+//!
+//! Searches over format, quality and scale until the encoded image fits a
+//! byte budget, using the same encoders as `save_image`.
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs;
+
+use crate::image::{
+    encode_image, export_scale, render_export, write_export, ExportFormat, ExportOptions,
+};
+use crate::optimize::{optimize_png, OptimizeOptions};
+use crate::utils::AppResult;
+
+/// Highest quality tried for lossy formats
+const MAX_QUALITY: u8 = 92;
+
+/// Bytes kept free for the density and metadata chunks written after encoding
+const TAG_ALLOWANCE: u64 = 4096;
+
+/// Options for exporting under a byte budget
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SizeLimitOptions {
+    pub max_bytes: u64,
+    /// Formats to try, in order of preference
+    pub formats: Vec<ExportFormat>,
+    /// Downscale when no format fits at full resolution
+    pub allow_downscale: bool,
+    /// Lowest quality tried before downscaling
+    pub min_quality: u8,
+    /// Smallest scale factor the search may reach
+    pub min_scale: f32,
+}
+
+impl Default for SizeLimitOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes: 5 * 1024 * 1024,
+            formats: vec![ExportFormat::Png, ExportFormat::Jpeg],
+            allow_downscale: true,
+            min_quality: 50,
+            min_scale: 0.25,
+        }
+    }
+}
+
+/// Encoding parameters chosen by the search
+#[derive(Serialize, Clone, Debug)]
+pub struct ChosenEncoding {
+    pub format: ExportFormat,
+    /// Quality used for lossy formats, 100 for lossless ones
+    pub quality: u8,
+    pub scale: f32,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: u64,
+}
+
+/// Saved file along with the parameters that made it fit
+#[derive(Serialize, Clone, Debug)]
+pub struct SizeLimitedExport {
+    pub path: String,
+    #[serde(flatten)]
+    pub encoding: ChosenEncoding,
+}
+
+/// Outcome of trying a single format at a single scale
+enum Fit {
+    Fits(Vec<u8>, u8),
+    /// Smallest size reached, in bytes
+    TooLarge(u64),
+}
+
+/// Encode an image so that it fits within `options.max_bytes`
+///
+/// Formats are tried in order at full resolution first, picking the highest
+/// quality that fits. If nothing fits, the image is downscaled in steps
+/// estimated from the smallest encoding seen so far.
+pub fn encode_within_size(
+    img: &DynamicImage,
+    options: &SizeLimitOptions,
+    png_optimization: &OptimizeOptions,
+) -> AppResult<(Vec<u8>, ChosenEncoding)> {
+    if options.max_bytes == 0 {
+        return Err("Maximum size must be greater than zero".to_string());
+    }
+    if options.formats.is_empty() {
+        return Err("At least one export format is required".to_string());
+    }
+
+    let min_scale = options.min_scale.clamp(0.01, 1.0);
+    let mut scale = 1.0f32;
+
+    loop {
+        let scaled = if scale < 1.0 {
+            let width = ((img.width() as f32 * scale).round() as u32).max(1);
+            let height = ((img.height() as f32 * scale).round() as u32).max(1);
+            Cow::Owned(img.resize_exact(width, height, FilterType::Lanczos3))
+        } else {
+            Cow::Borrowed(img)
+        };
+
+        let mut smallest = u64::MAX;
+        for &format in &options.formats {
+            match fit_format(&scaled, format, options, png_optimization)? {
+                Fit::Fits(bytes, quality) => {
+                    let encoding = ChosenEncoding {
+                        format,
+                        quality,
+                        scale,
+                        width: scaled.width(),
+                        height: scaled.height(),
+                        bytes: bytes.len() as u64,
+                    };
+                    return Ok((bytes, encoding));
+                }
+                Fit::TooLarge(size) => smallest = smallest.min(size),
+            }
+        }
+
+        if !options.allow_downscale || scale <= min_scale {
+            return Err(format!(
+                "Could not fit image under {} bytes (smallest encoding was {} bytes)",
+                options.max_bytes, smallest
+            ));
+        }
+
+        // Encoded size scales roughly with pixel count, so step by the square root
+        let ratio = (options.max_bytes as f32 / smallest as f32).sqrt();
+        scale = (scale * (ratio * 0.95).min(0.9)).max(min_scale);
+    }
+}
+
+/// Run an image through the export stages, then save it under a byte budget
+///
+/// Format and quality come from the search; canvas, palette strip, resize,
+/// watermark, colour profile and density come from `export_options`.
+pub fn save_within_size(
+    img: &DynamicImage,
+    save_dir: &str,
+    prefix: &str,
+    export_options: &ExportOptions,
+    icc_profile: Option<&[u8]>,
+    options: &SizeLimitOptions,
+    png_optimization: &OptimizeOptions,
+) -> AppResult<SizeLimitedExport> {
+    let (img, source_width) = render_export(img, export_options, icc_profile)?;
+
+    // Leave room for the tags, but never more than half the budget
+    let allowance = TAG_ALLOWANCE + icc_profile.map_or(0, |profile| profile.len() as u64);
+    let budget = SizeLimitOptions {
+        max_bytes: options.max_bytes - allowance.min(options.max_bytes / 2),
+        ..options.clone()
+    };
+    let (bytes, mut encoding) = encode_within_size(&img, &budget, png_optimization)?;
+
+    let output_options = ExportOptions {
+        format: encoding.format,
+        ..export_options.clone()
+    };
+    let scale = export_scale(export_options, encoding.width, source_width);
+    let path = write_export(bytes, save_dir, prefix, &output_options, scale, icc_profile)?;
+    if let Ok(file) = fs::metadata(&path) {
+        encoding.bytes = file.len();
+    }
+
+    Ok(SizeLimitedExport { path, encoding })
+}
+
+/// Find the highest quality at which a format fits the budget
+fn fit_format(
+    img: &DynamicImage,
+    format: ExportFormat,
+    options: &SizeLimitOptions,
+    png_optimization: &OptimizeOptions,
+) -> AppResult<Fit> {
+    let max_bytes = options.max_bytes as usize;
+
+    if !format.is_lossy() {
+        let mut bytes = encode_image(img, format, 100)?;
+        if format == ExportFormat::Png {
+            bytes = optimize_png(&bytes, png_optimization)?;
+        }
+        return Ok(if bytes.len() <= max_bytes {
+            Fit::Fits(bytes, 100)
+        } else {
+            Fit::TooLarge(bytes.len() as u64)
+        });
+    }
+
+    let best = encode_image(img, format, MAX_QUALITY)?;
+    if best.len() <= max_bytes {
+        return Ok(Fit::Fits(best, MAX_QUALITY));
+    }
+
+    let mut low = options.min_quality.clamp(1, MAX_QUALITY - 1);
+    let mut low_bytes = encode_image(img, format, low)?;
+    if low_bytes.len() > max_bytes {
+        return Ok(Fit::TooLarge(low_bytes.len() as u64));
+    }
+
+    // Binary search: `low` always fits, `high` never does
+    let mut high = MAX_QUALITY;
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        let bytes = encode_image(img, format, mid)?;
+        if bytes.len() <= max_bytes {
+            low = mid;
+            low_bytes = bytes;
+        } else {
+            high = mid;
+        }
+    }
+
+    Ok(Fit::Fits(low_bytes, low))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::PaletteStripOptions;
+    use image::{Rgba, RgbaImage};
+
+    /// Noise defeats compression, so encoded sizes are predictable
+    fn noise_image(width: u32, height: u32) -> DynamicImage {
+        let mut seed = 0x9e37_79b9_u32;
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |_, _| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let [r, g, b, _] = seed.to_le_bytes();
+            Rgba([r, g, b, 255])
+        }))
+    }
+
+    #[test]
+    fn test_fits_lossless_when_budget_allows() {
+        let img = noise_image(32, 32);
+        let options = SizeLimitOptions {
+            max_bytes: 1024 * 1024,
+            ..Default::default()
+        };
+
+        let (bytes, encoding) =
+            encode_within_size(&img, &options, &OptimizeOptions::default()).unwrap();
+
+        assert_eq!(encoding.format, ExportFormat::Png);
+        assert_eq!(encoding.scale, 1.0);
+        assert_eq!(encoding.bytes, bytes.len() as u64);
+    }
+
+    #[test]
+    fn test_falls_back_to_lossy_format() {
+        let img = noise_image(200, 200);
+        let options = SizeLimitOptions {
+            max_bytes: 40_000,
+            ..Default::default()
+        };
+
+        let (bytes, encoding) =
+            encode_within_size(&img, &options, &OptimizeOptions::default()).unwrap();
+
+        assert_eq!(encoding.format, ExportFormat::Jpeg);
+        assert!(bytes.len() as u64 <= options.max_bytes);
+        assert_eq!(encoding.width, 200);
+    }
+
+    #[test]
+    fn test_downscales_when_nothing_fits() {
+        let img = noise_image(200, 200);
+        let options = SizeLimitOptions {
+            max_bytes: 30_000,
+            formats: vec![ExportFormat::Png],
+            ..Default::default()
+        };
+
+        let (bytes, encoding) =
+            encode_within_size(&img, &options, &OptimizeOptions::default()).unwrap();
+
+        assert!(bytes.len() as u64 <= options.max_bytes);
+        assert!(encoding.scale < 1.0);
+        assert!(encoding.width < 200);
+    }
+
+    #[test]
+    fn test_errors_without_downscale() {
+        let img = noise_image(200, 200);
+        let options = SizeLimitOptions {
+            max_bytes: 1_000,
+            formats: vec![ExportFormat::Png],
+            allow_downscale: false,
+            ..Default::default()
+        };
+
+        assert!(encode_within_size(&img, &options, &OptimizeOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_saved_export_applies_stages_and_fits() {
+        let dir = std::env::temp_dir().join(format!("size_export_{}", std::process::id()));
+        let export_options = ExportOptions {
+            palette_strip: Some(PaletteStripOptions {
+                height: 0.25,
+                labels: false,
+                ..Default::default()
+            }),
+            display_scale: Some(2.0),
+            hidpi_suffix: true,
+            ..Default::default()
+        };
+        let options = SizeLimitOptions {
+            max_bytes: 60_000,
+            ..Default::default()
+        };
+
+        let saved = save_within_size(
+            &noise_image(200, 160),
+            &dir.to_string_lossy(),
+            "limited",
+            &export_options,
+            None,
+            &options,
+            &OptimizeOptions::default(),
+        )
+        .unwrap();
+        let file_len = fs::metadata(&saved.path).unwrap().len();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(saved.encoding.height * 200, saved.encoding.width * 210);
+        assert!(saved.path.ends_with("@2x.jpg"));
+        assert_eq!(saved.encoding.bytes, file_len);
+        assert!(file_len <= options.max_bytes);
+    }
+}
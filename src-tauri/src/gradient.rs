@@ -0,0 +1,426 @@
+//! Gradient module
+//!
+//! Renders linear, radial, conic and mesh gradients from a small JSON spec
+//! at any resolution, so backgrounds don't have to ship as images.
+
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::draw::Color;
+use crate::image::MAX_EXPORT_DIMENSION;
+use crate::utils::AppResult;
+
+/// Colour at a position along the gradient, from 0 to 1
+///
+/// Stops without a position are spaced evenly.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ColorStop {
+    pub color: Color,
+    #[serde(default)]
+    pub position: Option<f32>,
+}
+
+/// Point as a fraction of the canvas size, (0, 0) being the top-left corner
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    const CENTER: Point = Point { x: 0.5, y: 0.5 };
+}
+
+/// Coloured control point of a mesh gradient
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MeshPoint {
+    pub x: f32,
+    pub y: f32,
+    pub color: Color,
+}
+
+/// Gradient style used for random gradients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GradientKind {
+    Linear,
+    Radial,
+    Conic,
+    #[default]
+    Mesh,
+}
+
+/// Gradient description, tagged by `type` in JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GradientSpec {
+    /// Angle in degrees as in CSS: 0 runs bottom to top, 90 left to right
+    Linear {
+        #[serde(default = "default_angle")]
+        angle: f32,
+        stops: Vec<ColorStop>,
+    },
+    /// Radius as a fraction of the distance to the farthest corner
+    Radial {
+        #[serde(default = "default_center")]
+        center: Point,
+        #[serde(default = "default_radius")]
+        radius: f32,
+        stops: Vec<ColorStop>,
+    },
+    /// Sweeps clockwise from `angle` degrees, 0 pointing up
+    Conic {
+        #[serde(default = "default_center")]
+        center: Point,
+        #[serde(default)]
+        angle: f32,
+        stops: Vec<ColorStop>,
+    },
+    /// Blend of colours spread around control points; higher smoothness
+    /// gives each point a larger area of its own colour
+    Mesh {
+        points: Vec<MeshPoint>,
+        #[serde(default = "default_smoothness")]
+        smoothness: f32,
+    },
+    /// Palette and layout picked from a seed, so the same seed gives the same gradient
+    Random {
+        seed: u64,
+        #[serde(default)]
+        kind: GradientKind,
+    },
+}
+
+fn default_angle() -> f32 {
+    180.0
+}
+
+fn default_center() -> Point {
+    Point::CENTER
+}
+
+fn default_radius() -> f32 {
+    1.0
+}
+
+fn default_smoothness() -> f32 {
+    2.0
+}
+
+/// Render a gradient to a new image
+pub fn render_gradient(spec: &GradientSpec, width: u32, height: u32) -> AppResult<RgbaImage> {
+    if width == 0 || height == 0 || width > MAX_EXPORT_DIMENSION || height > MAX_EXPORT_DIMENSION {
+        return Err(format!("Invalid gradient size: {}x{}", width, height));
+    }
+    let (w, h) = (width as f32, height as f32);
+
+    let img = match spec {
+        GradientSpec::Linear { angle, stops } => {
+            let stops = resolve_stops(stops)?;
+            let (sin, cos) = angle.to_radians().sin_cos();
+            // Length of the gradient line so the corners get the end colours
+            let length = (w * sin).abs() + (h * cos).abs();
+            RgbaImage::from_fn(width, height, |x, y| {
+                let (dx, dy) = (x as f32 + 0.5 - w / 2.0, y as f32 + 0.5 - h / 2.0);
+                sample(&stops, (dx * sin - dy * cos) / length.max(1.0) + 0.5)
+            })
+        }
+        GradientSpec::Radial {
+            center,
+            radius,
+            stops,
+        } => {
+            let stops = resolve_stops(stops)?;
+            let (cx, cy) = (center.x * w, center.y * h);
+            let farthest = cx.max(w - cx).hypot(cy.max(h - cy));
+            let radius = (farthest * radius).max(1.0);
+            RgbaImage::from_fn(width, height, |x, y| {
+                let distance = (x as f32 + 0.5 - cx).hypot(y as f32 + 0.5 - cy);
+                sample(&stops, distance / radius)
+            })
+        }
+        GradientSpec::Conic {
+            center,
+            angle,
+            stops,
+        } => {
+            let stops = resolve_stops(stops)?;
+            let (cx, cy) = (center.x * w, center.y * h);
+            RgbaImage::from_fn(width, height, |x, y| {
+                let bearing = (x as f32 + 0.5 - cx)
+                    .atan2(cy - y as f32 - 0.5)
+                    .to_degrees();
+                sample(&stops, (bearing - angle).rem_euclid(360.0) / 360.0)
+            })
+        }
+        GradientSpec::Mesh { points, smoothness } => {
+            if points.is_empty() {
+                return Err("Mesh gradient needs at least one point".to_string());
+            }
+            let diagonal = w.hypot(h);
+            let power = smoothness.max(0.5);
+            RgbaImage::from_fn(width, height, |x, y| {
+                let (px, py) = ((x as f32 + 0.5) / diagonal, (y as f32 + 0.5) / diagonal);
+                let mut total = 0.0;
+                let mut sum = [0.0f32; 4];
+                for point in points {
+                    let distance = (px - point.x * w / diagonal).hypot(py - point.y * h / diagonal);
+                    let weight = 1.0 / (distance.powf(power) + 1e-6);
+                    total += weight;
+                    for (c, value) in sum.iter_mut().enumerate() {
+                        *value += point.color.0[c] as f32 * weight;
+                    }
+                }
+                Rgba(sum.map(|value| (value / total).round() as u8))
+            })
+        }
+        GradientSpec::Random { seed, kind } => {
+            return render_gradient(&random_gradient(*seed, *kind), width, height)
+        }
+    };
+
+    Ok(img)
+}
+
+/// Build a gradient with a harmonious random palette from a seed
+pub fn random_gradient(seed: u64, kind: GradientKind) -> GradientSpec {
+    let mut rng = SplitMix64(seed);
+    let base_hue = rng.next_f32() * 360.0;
+    // Analogous hues with the odd complementary accent
+    let mut colors: Vec<Color> = (0..4)
+        .map(|i| {
+            let hue = match i == 3 && rng.next_f32() < 0.5 {
+                true => base_hue + 180.0,
+                false => base_hue + (i as f32 - 1.5) * (20.0 + rng.next_f32() * 25.0),
+            };
+            let saturation = 0.55 + rng.next_f32() * 0.35;
+            let lightness = 0.45 + rng.next_f32() * 0.25;
+            Color(hsl_to_rgb(hue, saturation, lightness))
+        })
+        .collect();
+
+    let stops = |colors: &[Color]| {
+        colors
+            .iter()
+            .map(|&color| ColorStop {
+                color,
+                position: None,
+            })
+            .collect()
+    };
+    match kind {
+        GradientKind::Linear => GradientSpec::Linear {
+            angle: (rng.next_f32() * 8.0).floor() * 45.0,
+            stops: stops(&colors[..3]),
+        },
+        GradientKind::Radial => GradientSpec::Radial {
+            center: Point {
+                x: 0.2 + rng.next_f32() * 0.6,
+                y: 0.2 + rng.next_f32() * 0.6,
+            },
+            radius: 1.0,
+            stops: stops(&colors[..3]),
+        },
+        GradientKind::Conic => {
+            // Repeat the first colour so the sweep has no seam
+            colors.push(colors[0]);
+            GradientSpec::Conic {
+                center: Point::CENTER,
+                angle: rng.next_f32() * 360.0,
+                stops: stops(&colors),
+            }
+        }
+        GradientKind::Mesh => GradientSpec::Mesh {
+            points: colors
+                .iter()
+                .map(|&color| MeshPoint {
+                    x: rng.next_f32(),
+                    y: rng.next_f32(),
+                    color,
+                })
+                .collect(),
+            smoothness: default_smoothness(),
+        },
+    }
+}
+
+/// Fill in missing stop positions and sort by position
+fn resolve_stops(stops: &[ColorStop]) -> AppResult<Vec<(f32, Rgba<u8>)>> {
+    if stops.is_empty() {
+        return Err("Gradient needs at least one colour stop".to_string());
+    }
+
+    let last = (stops.len() - 1).max(1) as f32;
+    let mut resolved: Vec<(f32, Rgba<u8>)> = stops
+        .iter()
+        .enumerate()
+        .map(|(i, stop)| (stop.position.unwrap_or(i as f32 / last), stop.color.0))
+        .collect();
+    resolved.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(resolved)
+}
+
+/// Colour at `t` between the surrounding stops
+fn sample(stops: &[(f32, Rgba<u8>)], t: f32) -> Rgba<u8> {
+    let next = stops.partition_point(|(position, _)| *position <= t);
+    if next == 0 {
+        return stops[0].1;
+    }
+    if next == stops.len() {
+        return stops[next - 1].1;
+    }
+
+    let (start, from) = stops[next - 1];
+    let (end, to) = stops[next];
+    let amount = (t - start) / (end - start).max(f32::EPSILON);
+    let mut pixel = [0u8; 4];
+    for (c, value) in pixel.iter_mut().enumerate() {
+        *value = (from[c] as f32 + (to[c] as f32 - from[c] as f32) * amount).round() as u8;
+    }
+    Rgba(pixel)
+}
+
+/// Convert a hue in degrees, saturation and lightness (0-1) to an opaque colour
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> Rgba<u8> {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let second = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |v: f32| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    Rgba([channel(r), channel(g), channel(b), 255])
+}
+
+/// Small deterministic generator, so seeds give the same gradient on every platform
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in [0, 1)
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    fn parse(json: &str) -> GradientSpec {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_linear_default_runs_top_to_bottom() {
+        let spec =
+            parse(r##"{"type": "linear", "stops": [{"color": "#FF0000"}, {"color": "#0000FF"}]}"##);
+
+        let img = render_gradient(&spec, 10, 101).unwrap();
+
+        assert_eq!(img.get_pixel(5, 0)[0], 254);
+        assert_eq!(img.get_pixel(5, 50), &Rgba([128, 0, 128, 255]));
+        assert_eq!(img.get_pixel(5, 100)[2], 254);
+        // Constant across each row
+        assert_eq!(img.get_pixel(0, 30), img.get_pixel(9, 30));
+    }
+
+    #[test]
+    fn test_linear_angle_and_stop_positions() {
+        let spec = parse(
+            r##"{"type": "linear", "angle": 90, "stops": [
+                {"color": "#FF0000", "position": 0.4}, {"color": "#0000FF", "position": 0.6}]}"##,
+        );
+
+        let img = render_gradient(&spec, 100, 10).unwrap();
+
+        assert_eq!(img.get_pixel(10, 5), &RED);
+        assert_eq!(img.get_pixel(90, 5), &BLUE);
+        assert_eq!(img.get_pixel(0, 0), img.get_pixel(0, 9));
+    }
+
+    #[test]
+    fn test_radial_and_conic() {
+        let radial = parse(
+            r##"{"type": "radial", "radius": 0.5, "stops": [{"color": "#FF0000"}, {"color": "#0000FF"}]}"##,
+        );
+        let img = render_gradient(&radial, 100, 100).unwrap();
+        assert!(img.get_pixel(50, 50)[0] > 245);
+        assert_eq!(img.get_pixel(0, 0), &BLUE);
+        assert_eq!(img.get_pixel(50, 20), img.get_pixel(20, 50));
+
+        let conic =
+            parse(r##"{"type": "conic", "stops": [{"color": "#FF0000"}, {"color": "#0000FF"}]}"##);
+        let img = render_gradient(&conic, 100, 100).unwrap();
+        // Just clockwise of 12 o'clock is red, just anticlockwise is blue
+        assert!(img.get_pixel(52, 5)[0] > 240);
+        assert!(img.get_pixel(47, 5)[2] > 240);
+        // Half way round at 6 o'clock
+        let bottom = img.get_pixel(50, 95);
+        assert!((bottom[0] as i32 - 128).abs() < 10, "{:?}", bottom);
+    }
+
+    #[test]
+    fn test_mesh_points_dominate_their_area() {
+        let spec = parse(
+            r##"{"type": "mesh", "points": [
+                {"x": 0, "y": 0, "color": "#FF0000"}, {"x": 1, "y": 1, "color": "#0000FF"}]}"##,
+        );
+
+        let img = render_gradient(&spec, 64, 64).unwrap();
+
+        assert!(img.get_pixel(0, 0)[0] > 250);
+        assert!(img.get_pixel(63, 63)[2] > 250);
+        let middle = img.get_pixel(31, 32);
+        assert!(
+            (middle[0] as i32 - middle[2] as i32).abs() < 12,
+            "{:?}",
+            middle
+        );
+    }
+
+    #[test]
+    fn test_random_is_reproducible() {
+        for kind in [
+            GradientKind::Linear,
+            GradientKind::Radial,
+            GradientKind::Conic,
+            GradientKind::Mesh,
+        ] {
+            assert_eq!(random_gradient(7, kind), random_gradient(7, kind));
+        }
+        assert_ne!(
+            random_gradient(7, GradientKind::Mesh),
+            random_gradient(8, GradientKind::Mesh)
+        );
+
+        let spec = parse(r#"{"type": "random", "seed": 42}"#);
+        let first = render_gradient(&spec, 32, 32).unwrap();
+        assert_eq!(first, render_gradient(&spec, 32, 32).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        let empty = parse(r#"{"type": "linear", "stops": []}"#);
+        assert!(render_gradient(&empty, 10, 10).is_err());
+
+        let spec = random_gradient(1, GradientKind::Linear);
+        assert!(render_gradient(&spec, 0, 10).is_err());
+    }
+}
@@ -1,14 +1,33 @@
 //! Image processing module
 
 use base64::{engine::general_purpose, Engine as _};
-use image::DynamicImage;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::io::{Limits, Reader};
+use image::{DynamicImage, ImageEncoder, ImageFormat, ImageOutputFormat, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fs;
+use std::io::Cursor;
 use std::path::PathBuf;
 
-use crate::utils::{ensure_dir, generate_filename, AppResult};
+use crate::canvas::{fit_to_canvas, CanvasOptions};
+use crate::color::{
+    convert_to_srgb, embed_icc_profile, icc_profile, mark_srgb, read_icc_profile, ColorProfileMode,
+};
+use crate::draw::Color;
+use crate::metadata::jpeg_segments;
+use crate::optimize::{insert_chunks, preserved_chunks, read_chunks, remove_chunks};
+use crate::palette::{append_palette_strip, PaletteStripOptions};
+use crate::stream::{read_png_header, stream_png};
+use crate::utils::{
+    ensure_dir, generate_filename, generate_filename_with_suffix, write_file_atomic, AppResult,
+};
+use crate::watermark::{apply_watermark, WatermarkOptions};
 
 /// Region coordinates for cropping
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CropRegion {
     pub x: u32,
     pub y: u32,
@@ -45,9 +64,240 @@ impl CropRegion {
     }
 }
 
+/// Encoded output format for saved images
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Png,
+    Jpeg,
+    /// Lossless WebP
+    Webp,
+}
+
+impl ExportFormat {
+    /// File extension used for saved files
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::Webp => "webp",
+        }
+    }
+
+    /// Check if the format takes a quality setting
+    pub fn is_lossy(self) -> bool {
+        matches!(self, ExportFormat::Jpeg)
+    }
+}
+
+/// Largest width or height an export may be resized to, or decoded from
+pub const MAX_EXPORT_DIMENSION: u32 = 32_768;
+
+/// Largest decoded image or base64 payload accepted from the frontend
+const MAX_DECODE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Dots per inch of a display with a scale factor of 1
+const BASE_DPI: f32 = 72.0;
+
+/// Resampling filter used when resizing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResampleFilter {
+    #[default]
+    Lanczos3,
+    CatmullRom,
+    Triangle,
+    /// Keeps hard pixel edges, for pixel art and UI zooms
+    Nearest,
+}
+
+impl From<ResampleFilter> for FilterType {
+    fn from(filter: ResampleFilter) -> Self {
+        match filter {
+            ResampleFilter::Lanczos3 => FilterType::Lanczos3,
+            ResampleFilter::CatmullRom => FilterType::CatmullRom,
+            ResampleFilter::Triangle => FilterType::Triangle,
+            ResampleFilter::Nearest => FilterType::Nearest,
+        }
+    }
+}
+
+/// How the output size is derived from the source size
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResizeMode {
+    /// Exact output size, ignoring aspect ratio
+    Exact { width: u32, height: u32 },
+    /// Largest size within the bounds that keeps aspect ratio; never upscales
+    Fit {
+        max_width: Option<u32>,
+        max_height: Option<u32>,
+    },
+    /// Multiply both dimensions by a factor (0.5 for 50%)
+    Scale { factor: f32 },
+    /// Undo display scaling, e.g. turn a Retina 2x capture into 1x
+    Logical { scale_factor: Option<f32> },
+}
+
+/// Resize step applied before encoding
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ResizeOptions {
+    #[serde(flatten)]
+    pub mode: ResizeMode,
+    #[serde(default)]
+    pub filter: ResampleFilter,
+}
+
+impl ResizeOptions {
+    /// Compute the output dimensions for a source image
+    pub fn target_size(&self, width: u32, height: u32) -> AppResult<(u32, u32)> {
+        let scaled = |factor: f64| -> AppResult<(u32, u32)> {
+            if !factor.is_finite() || factor <= 0.0 {
+                return Err(format!("Invalid resize factor: {}", factor));
+            }
+            Ok((
+                ((width as f64 * factor).round() as u32).max(1),
+                ((height as f64 * factor).round() as u32).max(1),
+            ))
+        };
+
+        let (target_width, target_height) = match self.mode {
+            ResizeMode::Exact {
+                width: target_width,
+                height: target_height,
+            } => {
+                if target_width == 0 || target_height == 0 {
+                    return Err("Resize dimensions must be greater than zero".to_string());
+                }
+                (target_width, target_height)
+            }
+            ResizeMode::Fit {
+                max_width,
+                max_height,
+            } => {
+                let ratio_x = max_width.map_or(1.0, |w| w as f64 / width as f64);
+                let ratio_y = max_height.map_or(1.0, |h| h as f64 / height as f64);
+                scaled(ratio_x.min(ratio_y).min(1.0))?
+            }
+            ResizeMode::Scale { factor } => scaled(factor as f64)?,
+            ResizeMode::Logical { scale_factor } => {
+                let scale_factor = scale_factor.ok_or("Display scale factor is unknown")?;
+                scaled(1.0 / scale_factor as f64)?
+            }
+        };
+
+        if target_width > MAX_EXPORT_DIMENSION || target_height > MAX_EXPORT_DIMENSION {
+            return Err(format!(
+                "Resized image too large: {}x{} (max {} px per side)",
+                target_width, target_height, MAX_EXPORT_DIMENSION
+            ));
+        }
+
+        Ok((target_width, target_height))
+    }
+}
+
+/// Options applied when saving an image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Quality for lossy formats (1-100)
+    pub quality: u8,
+    pub resize: Option<ResizeOptions>,
+    /// Name files like `shot_123@2x.png` when the output is HiDPI
+    pub hidpi_suffix: bool,
+    /// Embed the source display's colour profile or convert to sRGB
+    pub color_profile: ColorProfileMode,
+    /// Place the image on a fixed-size canvas before resizing
+    pub canvas: Option<CanvasOptions>,
+    /// Add a strip of the image's main colours underneath
+    pub palette_strip: Option<PaletteStripOptions>,
+    /// Scale factor of the display the source was captured on, if known
+    #[serde(skip)]
+    pub display_scale: Option<f32>,
+    /// Watermark to stamp on the output, taken from settings
+    #[serde(skip)]
+    pub watermark: Option<WatermarkOptions>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Png,
+            quality: 90,
+            resize: None,
+            hidpi_suffix: false,
+            color_profile: ColorProfileMode::Embed,
+            canvas: None,
+            palette_strip: None,
+            display_scale: None,
+            watermark: None,
+        }
+    }
+}
+
+/// Resize an image according to the resize options
+pub fn resize_image(img: &DynamicImage, options: &ResizeOptions) -> AppResult<DynamicImage> {
+    let (width, height) = options.target_size(img.width(), img.height())?;
+
+    if (width, height) == (img.width(), img.height()) {
+        return Ok(img.clone());
+    }
+
+    Ok(img.resize_exact(width, height, options.filter.into()))
+}
+
+/// Gaussian blur on a reduced copy, for large radii where fine detail is lost anyway
+pub fn blur_image(img: &RgbaImage, sigma: f32) -> RgbaImage {
+    let factor = ((sigma / 4.0) as u32).clamp(1, 8);
+    let (width, height) = img.dimensions();
+    let small = image::imageops::resize(
+        img,
+        (width / factor).max(1),
+        (height / factor).max(1),
+        FilterType::Triangle,
+    );
+    let blurred = image::imageops::blur(&small, sigma / factor as f32);
+    image::imageops::resize(&blurred, width, height, FilterType::Triangle)
+}
+
+/// A saved export, and whether it was streamed rather than fully decoded
+#[derive(Debug, Clone)]
+pub struct SavedExport {
+    pub path: String,
+    pub streamed: bool,
+}
+
 /// Crop an image file and save to a new location
-pub fn crop_image(source_path: &str, region: CropRegion, save_dir: &str) -> AppResult<String> {
-    let img = image::open(source_path).map_err(|e| format!("Failed to open screenshot: {}", e))?;
+///
+/// Plain PNG crops are streamed row by row, so huge captures are never
+/// fully decoded; anything else is decoded within the memory limits.
+pub fn crop_image(
+    source_path: &str,
+    region: CropRegion,
+    save_dir: &str,
+    options: &ExportOptions,
+) -> AppResult<SavedExport> {
+    if can_stream(options) {
+        let bytes =
+            fs::read(source_path).map_err(|e| format!("Failed to read screenshot: {}", e))?;
+
+        // Upright, non-interlaced PNGs are cropped without a full decode
+        let streamable = exif_orientation(&bytes).unwrap_or(1) == 1
+            && read_png_header(Cursor::new(&bytes)).is_ok_and(|header| !header.interlaced);
+        if streamable {
+            let (path, scale) = stream_export_path(save_dir, "region", options)?;
+            stream_png(Cursor::new(&bytes), Some(region), &path, scale)?;
+            return Ok(SavedExport {
+                path: path.to_string_lossy().into_owned(),
+                streamed: true,
+            });
+        }
+    }
+
+    let img = load_image(source_path)?;
 
     let img_width = img.width();
     let img_height = img.height();
@@ -71,42 +321,611 @@ pub fn crop_image(source_path: &str, region: CropRegion, save_dir: &str) -> AppR
 
     let cropped = img.crop_imm(region.x, region.y, region.width, region.height);
 
-    save_image(&cropped, save_dir, "region")
+    // screencapture embeds the display's profile in its output
+    let profile = read_icc_profile(source_path)?;
+    let path = save_image_with(&cropped, save_dir, "region", options, profile.as_deref())?;
+    Ok(SavedExport {
+        path,
+        streamed: false,
+    })
+}
+
+/// Whether an export only re-encodes PNG pixels, so it can be streamed
+fn can_stream(options: &ExportOptions) -> bool {
+    options.format == ExportFormat::Png
+        && options.color_profile == ColorProfileMode::Embed
+        && options.resize.is_none()
+        && options.canvas.is_none()
+        && options.palette_strip.is_none()
+        && options.watermark.is_none()
+}
+
+/// Output path and pixel density for a streamed PNG export
+fn stream_export_path(
+    save_dir: &str,
+    prefix: &str,
+    options: &ExportOptions,
+) -> AppResult<(PathBuf, Option<f32>)> {
+    let suffix = match (options.hidpi_suffix, options.display_scale) {
+        (true, Some(scale)) => hidpi_suffix(scale).unwrap_or_default(),
+        _ => String::new(),
+    };
+    let path = export_path(save_dir, prefix, &suffix, ExportFormat::Png)?;
+    Ok((path, options.display_scale))
 }
 
 /// Save a DynamicImage to a directory with a generated filename
+/// The pixels are taken as sRGB, so no colour profile is attached
 pub fn save_image(img: &DynamicImage, save_dir: &str, prefix: &str) -> AppResult<String> {
+    save_image_with(img, save_dir, prefix, &ExportOptions::default(), None)
+}
+
+/// Save a DynamicImage, applying colour, resize, watermark and encoding options
+/// `icc_profile` is the colour profile of the display the pixels came from
+pub fn save_image_with(
+    img: &DynamicImage,
+    save_dir: &str,
+    prefix: &str,
+    options: &ExportOptions,
+    icc_profile: Option<&[u8]>,
+) -> AppResult<String> {
+    let (img, source_width) = render_export(img, options, icc_profile)?;
+    let bytes = encode_image(&img, options.format, options.quality)?;
+    let scale = export_scale(options, img.width(), source_width);
+
+    write_export(bytes, save_dir, prefix, options, scale, icc_profile)
+}
+
+/// Apply the pixel stages of an export: colour conversion, canvas, palette
+/// strip, resize and watermark
+/// Also returns the width before resizing, for `export_scale`
+pub fn render_export<'a>(
+    img: &'a DynamicImage,
+    options: &ExportOptions,
+    icc_profile: Option<&[u8]>,
+) -> AppResult<(Cow<'a, DynamicImage>, u32)> {
+    let mut img = Cow::Borrowed(img);
+
+    if let (Some(profile), ColorProfileMode::Convert) = (icc_profile, options.color_profile) {
+        img = Cow::Owned(convert_to_srgb(&img, profile)?);
+    }
+    if let Some(canvas) = &options.canvas {
+        img = Cow::Owned(DynamicImage::ImageRgba8(fit_to_canvas(&img, canvas)?));
+    }
+    if let Some(strip) = &options.palette_strip {
+        img = Cow::Owned(append_palette_strip(&img, strip)?);
+    }
+
+    let source_width = img.width();
+    if let Some(resize) = &options.resize {
+        img = Cow::Owned(resize_image(&img, resize)?);
+    }
+
+    // Stamped after resizing so the watermark keeps its size relative to the output
+    if let Some(watermark) = &options.watermark {
+        img = Cow::Owned(apply_watermark(&img, watermark)?);
+    }
+
+    Ok((img, source_width))
+}
+
+/// Display scale of an export `width` pixels wide, rendered from `source_width`
+///
+/// Resizing changes how many pixels map to each display point, and a
+/// canvas has a fixed size unrelated to the display.
+pub fn export_scale(options: &ExportOptions, width: u32, source_width: u32) -> Option<f32> {
+    options
+        .display_scale
+        .filter(|_| options.canvas.is_none())
+        .map(|scale| scale * width as f32 / source_width as f32)
+}
+
+/// Tag colour profile and pixel density, then write the file,
+/// adding a HiDPI suffix if enabled
+pub fn write_export(
+    bytes: Vec<u8>,
+    save_dir: &str,
+    prefix: &str,
+    options: &ExportOptions,
+    scale: Option<f32>,
+    icc_profile: Option<&[u8]>,
+) -> AppResult<String> {
+    // Converted pixels are sRGB; without a profile there is nothing to record
+    let bytes = match (icc_profile, options.color_profile) {
+        (Some(profile), ColorProfileMode::Embed) => {
+            embed_icc_profile(bytes, options.format, profile)?
+        }
+        (Some(_), ColorProfileMode::Convert) => mark_srgb(bytes, options.format)?,
+        (None, _) => bytes,
+    };
+
+    let Some(scale) = scale else {
+        return write_encoded(&bytes, options.format, save_dir, prefix, "");
+    };
+
+    let bytes = set_pixel_density(bytes, options.format, scale)?;
+    let suffix = match options.hidpi_suffix {
+        true => hidpi_suffix(scale).unwrap_or_default(),
+        false => String::new(),
+    };
+    write_encoded(&bytes, options.format, save_dir, prefix, &suffix)
+}
+
+fn write_encoded(
+    bytes: &[u8],
+    format: ExportFormat,
+    save_dir: &str,
+    prefix: &str,
+    suffix: &str,
+) -> AppResult<String> {
+    let file_path = export_path(save_dir, prefix, suffix, format)?;
+    fs::write(&file_path, bytes).map_err(|e| format!("Failed to save image: {}", e))?;
+
+    Ok(file_path.to_string_lossy().into_owned())
+}
+
+/// Generated path for a new export, creating the directory if needed
+fn export_path(
+    save_dir: &str,
+    prefix: &str,
+    suffix: &str,
+    format: ExportFormat,
+) -> AppResult<PathBuf> {
     let dest_path = PathBuf::from(save_dir);
     ensure_dir(&dest_path)?;
 
-    let filename = generate_filename(prefix, "png")?;
-    let file_path = dest_path.join(&filename);
+    let filename = generate_filename_with_suffix(prefix, suffix, format.extension())?;
+    Ok(dest_path.join(&filename))
+}
 
-    img.save(&file_path)
-        .map_err(|e| format!("Failed to save image: {}", e))?;
+/// Encode an image as a PNG data URL, for previews in the webview
+pub fn encode_data_url(img: &DynamicImage) -> AppResult<String> {
+    let bytes = encode_image(img, ExportFormat::Png, 100)?;
+    Ok(format!(
+        "data:image/png;base64,{}",
+        general_purpose::STANDARD.encode(bytes)
+    ))
+}
 
-    Ok(file_path.to_string_lossy().into_owned())
+/// Encode an image in memory; `quality` (1-100) only applies to lossy formats
+pub fn encode_image(img: &DynamicImage, format: ExportFormat, quality: u8) -> AppResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    let result = match format {
+        ExportFormat::Png => img.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png),
+        ExportFormat::Jpeg => {
+            // JPEG has no alpha channel, so composite onto white first
+            let rgb = flatten_alpha(img, Rgba([255, 255, 255, 255]));
+            JpegEncoder::new_with_quality(&mut bytes, quality.clamp(1, 100)).write_image(
+                rgb.as_raw(),
+                rgb.width(),
+                rgb.height(),
+                image::ColorType::Rgb8,
+            )
+        }
+        ExportFormat::Webp => {
+            let rgba = img.to_rgba8();
+            WebPEncoder::new_lossless(&mut bytes).write_image(
+                rgba.as_raw(),
+                rgba.width(),
+                rgba.height(),
+                image::ColorType::Rgba8,
+            )
+        }
+    };
+
+    result.map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(bytes)
 }
 
-/// Save base64-encoded image data to a file
-pub fn save_base64_image(image_data: &str, save_dir: &str, prefix: &str) -> AppResult<String> {
-    let base64_data = image_data
-        .strip_prefix("data:image/png;base64,")
-        .ok_or("Invalid image data format: expected data:image/png;base64, prefix")?;
+/// Filename suffix for a HiDPI scale, e.g. `@2x`; none for 1x or fractional scales
+pub fn hidpi_suffix(scale: f32) -> Option<String> {
+    let rounded = scale.round();
+    if rounded >= 2.0 && (scale - rounded).abs() < 0.05 {
+        Some(format!("@{}x", rounded as u32))
+    } else {
+        None
+    }
+}
+
+/// Record the display scale as pixel density, so documents and wikis
+/// render the image at its logical size
+///
+/// PNG gets a pHYs chunk and JPEG its JFIF density; WebP is left unchanged.
+pub fn set_pixel_density(bytes: Vec<u8>, format: ExportFormat, scale: f32) -> AppResult<Vec<u8>> {
+    if !scale.is_finite() || scale <= 0.0 {
+        return Ok(bytes);
+    }
+    let dpi = BASE_DPI * scale;
+
+    match format {
+        ExportFormat::Png => {
+            let ppm = pixels_per_metre(scale);
+            let mut phys = Vec::with_capacity(9);
+            phys.extend_from_slice(&ppm.to_be_bytes());
+            phys.extend_from_slice(&ppm.to_be_bytes());
+            phys.push(1);
+
+            let without_phys = remove_chunks(&bytes, &[b"pHYs"])?;
+            insert_chunks(&without_phys, &[(png::chunk::pHYs, &phys)])
+        }
+        ExportFormat::Jpeg => Ok(set_jfif_density(bytes, dpi.round() as u16)),
+        ExportFormat::Webp => Ok(bytes),
+    }
+}
+
+/// PNG pixel density (pHYs stores pixels per metre) for a display scale
+pub fn pixels_per_metre(scale: f32) -> u32 {
+    (BASE_DPI * scale / 0.0254).round() as u32
+}
+
+/// Set the density fields of a JFIF APP0 header, if the JPEG has one
+fn set_jfif_density(mut bytes: Vec<u8>, dpi: u16) -> Vec<u8> {
+    // SOI, APP0 marker and length, then "JFIF\0", version, units, X and Y density
+    if bytes.len() >= 18 && bytes[2..4] == [0xFF, 0xE0] && &bytes[6..11] == b"JFIF\0" {
+        bytes[13] = 1;
+        bytes[14..16].copy_from_slice(&dpi.to_be_bytes());
+        bytes[16..18].copy_from_slice(&dpi.to_be_bytes());
+    }
+    bytes
+}
+
+/// Composite an image onto a solid background colour, dropping alpha
+fn flatten_alpha(img: &DynamicImage, background: Rgba<u8>) -> image::RgbImage {
+    let mut canvas = RgbaImage::from_pixel(img.width(), img.height(), background);
+    image::imageops::overlay(&mut canvas, &img.to_rgba8(), 0, 0);
+    DynamicImage::ImageRgba8(canvas).to_rgb8()
+}
+
+/// Split an image data URL into its format and decoded bytes
+fn decode_data_url(image_data: &str) -> AppResult<(ImageFormat, Vec<u8>)> {
+    let (header, base64_data) = image_data
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .ok_or("Invalid image data format: expected a base64 data URL")?;
 
-    let image_bytes = general_purpose::STANDARD
+    let mime = header
+        .strip_suffix(";base64")
+        .ok_or("Invalid image data format: expected base64 encoding")?;
+    let format = match mime {
+        "image/png" => ImageFormat::Png,
+        "image/jpeg" | "image/jpg" => ImageFormat::Jpeg,
+        "image/webp" => ImageFormat::WebP,
+        "image/gif" => ImageFormat::Gif,
+        _ => return Err(format!("Unsupported image data type: {}", mime)),
+    };
+
+    // Base64 expands data by 4/3, so reject oversized payloads before decoding
+    if base64_data.len() as u64 / 4 * 3 > MAX_DECODE_BYTES {
+        return Err("Image data is too large".to_string());
+    }
+
+    let bytes = general_purpose::STANDARD
         .decode(base64_data)
         .map_err(|e| format!("Failed to decode base64: {}", e))?;
 
-    let dest_path = PathBuf::from(save_dir);
-    ensure_dir(&dest_path)?;
+    Ok((format, bytes))
+}
 
-    let filename = generate_filename(prefix, "png")?;
-    let file_path = dest_path.join(&filename);
+/// Decode image bytes, rejecting images over the dimension and memory limits
+pub fn decode_with_limits(bytes: &[u8], format: ImageFormat) -> AppResult<DynamicImage> {
+    // Check the header first, since some decoders ignore the allocation limit
+    let (width, height) = Reader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(|e| format!("Failed to read image header: {}", e))?;
+    if width == 0 || height == 0 {
+        return Err("Image has no pixels".to_string());
+    }
+    if width > MAX_EXPORT_DIMENSION || height > MAX_EXPORT_DIMENSION {
+        return Err(format!(
+            "Image is too large: {}x{} exceeds {} pixels per side",
+            width, height, MAX_EXPORT_DIMENSION
+        ));
+    }
+    if width as u64 * height as u64 * 4 > MAX_DECODE_BYTES {
+        return Err(format!("Image is too large: {}x{}", width, height));
+    }
 
-    fs::write(&file_path, image_bytes).map_err(|e| format!("Failed to save image: {}", e))?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_EXPORT_DIMENSION);
+    limits.max_image_height = Some(MAX_EXPORT_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_BYTES);
 
-    Ok(file_path.to_string_lossy().into_owned())
+    let mut reader = Reader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    reader
+        .decode()
+        .map_err(|e| format!("Failed to decode image: {}", e))
+}
+
+/// Decode image bytes within the limits, turned upright by their EXIF orientation
+fn decode_upright(bytes: &[u8], format: ImageFormat) -> AppResult<DynamicImage> {
+    let img = decode_with_limits(bytes, format)?;
+    Ok(match exif_orientation(bytes) {
+        Some(orientation) => apply_orientation(img, orientation),
+        None => img,
+    })
+}
+
+/// Load an image file, with the same limits as data from the frontend
+pub fn load_image(path: &str) -> AppResult<DynamicImage> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read image {}: {}", path, e))?;
+    let format = image::guess_format(&bytes)
+        .map_err(|e| format!("Unrecognised image format {}: {}", path, e))?;
+    decode_upright(&bytes, format)
+}
+
+/// Decode a base64 png, jpeg, webp or gif data URL into an image
+pub fn decode_base64_image(image_data: &str) -> AppResult<DynamicImage> {
+    let (format, bytes) = decode_data_url(image_data)?;
+    decode_upright(&bytes, format)
+}
+
+/// Decode a base64 data URL along with the colour profile it carries, if any
+pub fn decode_base64_image_with_profile(
+    image_data: &str,
+) -> AppResult<(DynamicImage, Option<Vec<u8>>)> {
+    let (format, bytes) = decode_data_url(image_data)?;
+    Ok((decode_upright(&bytes, format)?, icc_profile(&bytes)?))
+}
+
+/// Save base64-encoded image data to a file
+///
+/// The data is always decoded and re-encoded, so malformed payloads never
+/// reach disk. Webview canvases export sRGB pixels, so only a profile
+/// carried in the data itself is embedded or converted.
+pub fn save_base64_image(
+    image_data: &str,
+    save_dir: &str,
+    prefix: &str,
+    options: &ExportOptions,
+) -> AppResult<SavedExport> {
+    let (format, bytes) = decode_data_url(image_data)?;
+
+    // Upright, non-interlaced PNGs go straight to disk without a full decode
+    let streamable = format == ImageFormat::Png
+        && can_stream(options)
+        && exif_orientation(&bytes).unwrap_or(1) == 1
+        && read_png_header(Cursor::new(&bytes)).is_ok_and(|header| !header.interlaced);
+    if streamable {
+        let (path, scale) = stream_export_path(save_dir, prefix, options)?;
+        stream_png(Cursor::new(&bytes), None, &path, scale)?;
+        return Ok(SavedExport {
+            path: path.to_string_lossy().into_owned(),
+            streamed: true,
+        });
+    }
+
+    let img = decode_upright(&bytes, format)?;
+    let profile = icc_profile(&bytes)?;
+
+    let path = save_image_with(&img, save_dir, prefix, options, profile.as_deref())?;
+    Ok(SavedExport {
+        path,
+        streamed: false,
+    })
+}
+
+/// Direction of a mirror flip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlipDirection {
+    /// Mirror left to right
+    Horizontal,
+    /// Mirror top to bottom
+    Vertical,
+}
+
+/// Geometric edit applied to a whole image
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImageTransform {
+    /// Rotate clockwise; corners uncovered by other angles get the fill colour,
+    /// transparent by default
+    Rotate {
+        degrees: f32,
+        fill: Option<Color>,
+    },
+    Flip {
+        direction: FlipDirection,
+    },
+}
+
+/// Apply a rotation or flip
+pub fn transform_image(img: &DynamicImage, transform: &ImageTransform) -> AppResult<DynamicImage> {
+    match *transform {
+        ImageTransform::Rotate { degrees, fill } => {
+            let fill = fill.map_or(Rgba([0, 0, 0, 0]), |color| color.0);
+            rotate_image(img, degrees, fill)
+        }
+        ImageTransform::Flip { direction } => Ok(flip_image(img, direction)),
+    }
+}
+
+/// Mirror an image horizontally or vertically
+pub fn flip_image(img: &DynamicImage, direction: FlipDirection) -> DynamicImage {
+    match direction {
+        FlipDirection::Horizontal => img.fliph(),
+        FlipDirection::Vertical => img.flipv(),
+    }
+}
+
+/// Rotate an image clockwise by any angle
+///
+/// Multiples of 90 degrees are exact. Other angles grow the canvas to fit
+/// the rotated image, resample bilinearly and fill the corners.
+pub fn rotate_image(img: &DynamicImage, degrees: f32, fill: Rgba<u8>) -> AppResult<DynamicImage> {
+    if !degrees.is_finite() {
+        return Err(format!("Invalid rotation angle: {}", degrees));
+    }
+
+    let degrees = degrees.rem_euclid(360.0);
+    let quarter = (degrees / 90.0).round();
+    if (degrees - quarter * 90.0).abs() < 0.01 {
+        return Ok(match quarter as u32 % 4 {
+            1 => img.rotate90(),
+            2 => img.rotate180(),
+            3 => img.rotate270(),
+            _ => img.clone(),
+        });
+    }
+
+    let (width, height) = (img.width() as f32, img.height() as f32);
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let out_width = (width * cos.abs() + height * sin.abs()).ceil();
+    let out_height = (width * sin.abs() + height * cos.abs()).ceil();
+    if out_width > MAX_EXPORT_DIMENSION as f32 || out_height > MAX_EXPORT_DIMENSION as f32 {
+        return Err(format!(
+            "Rotated image of {}x{} exceeds the maximum of {} pixels per side",
+            out_width, out_height, MAX_EXPORT_DIMENSION
+        ));
+    }
+    let (out_width, out_height) = (out_width as u32, out_height as u32);
+
+    let source = img.to_rgba8();
+
+    let rotated = RgbaImage::from_fn(out_width, out_height, |x, y| {
+        // Map each output pixel centre back into the source
+        let dx = x as f32 + 0.5 - out_width as f32 / 2.0;
+        let dy = y as f32 + 0.5 - out_height as f32 / 2.0;
+        let sx = dx * cos + dy * sin + width / 2.0 - 0.5;
+        let sy = -dx * sin + dy * cos + height / 2.0 - 0.5;
+        sample_bilinear(&source, sx, sy, fill)
+    });
+
+    Ok(DynamicImage::ImageRgba8(rotated))
+}
+
+/// Bilinear sample with premultiplied alpha; pixels outside the image are `fill`
+fn sample_bilinear(img: &RgbaImage, x: f32, y: f32, fill: Rgba<u8>) -> Rgba<u8> {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+
+    let mut sum = [0.0f32; 4];
+    for (ox, oy, weight) in [
+        (0, 0, (1.0 - fx) * (1.0 - fy)),
+        (1, 0, fx * (1.0 - fy)),
+        (0, 1, (1.0 - fx) * fy),
+        (1, 1, fx * fy),
+    ] {
+        let (px, py) = (x0 as i64 + ox, y0 as i64 + oy);
+        let inside = px >= 0 && py >= 0 && px < img.width() as i64 && py < img.height() as i64;
+        let pixel = match inside {
+            true => *img.get_pixel(px as u32, py as u32),
+            false => fill,
+        };
+        let alpha = pixel[3] as f32 * weight;
+        for c in 0..3 {
+            sum[c] += pixel[c] as f32 * alpha;
+        }
+        sum[3] += alpha;
+    }
+
+    if sum[3] <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    Rgba([
+        (sum[0] / sum[3]).round() as u8,
+        (sum[1] / sum[3]).round() as u8,
+        (sum[2] / sum[3]).round() as u8,
+        sum[3].round() as u8,
+    ])
+}
+
+/// Turn an image upright according to an EXIF orientation value (1-8)
+pub fn apply_orientation(img: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// Read the EXIF orientation of a PNG, JPEG or WebP file, if it has one
+pub fn exif_orientation(data: &[u8]) -> Option<u16> {
+    if let Ok(chunks) = read_chunks(data) {
+        return chunks
+            .iter()
+            .find(|(kind, _)| &kind.0 == b"eXIf")
+            .and_then(|(_, tiff)| tiff_orientation(tiff));
+    }
+
+    if let Ok((segments, _)) = jpeg_segments(data) {
+        return segments
+            .iter()
+            .find(|(marker, segment)| *marker == 0xE1 && segment[4..].starts_with(b"Exif\0\0"))
+            .and_then(|(_, segment)| tiff_orientation(&segment[10..]));
+    }
+
+    // WebP keeps EXIF in a RIFF chunk
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        let mut offset = 12;
+        while offset + 8 <= data.len() {
+            let size = u32::from_le_bytes(data[offset + 4..offset + 8].try_into().ok()?) as usize;
+            let body = data.get(offset + 8..offset + 8 + size)?;
+            if &data[offset..offset + 4] == b"EXIF" {
+                return tiff_orientation(body.strip_prefix(b"Exif\0\0").unwrap_or(body));
+            }
+            offset += 8 + size + size % 2;
+        }
+    }
+
+    None
+}
+
+/// Find the orientation tag (0x0112) in the first IFD of a TIFF-format EXIF block
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let little_endian = match tiff.get(..4)? {
+        [b'I', b'I', 42, 0] => true,
+        [b'M', b'M', 0, 42] => false,
+        _ => return None,
+    };
+    let u16_at = |offset: usize| -> Option<u16> {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(match little_endian {
+            true => u16::from_le_bytes(bytes),
+            false => u16::from_be_bytes(bytes),
+        })
+    };
+    let u32_at = |offset: usize| -> Option<u32> {
+        let bytes: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(match little_endian {
+            true => u32::from_le_bytes(bytes),
+            false => u32::from_be_bytes(bytes),
+        })
+    };
+
+    let ifd = u32_at(4)? as usize;
+    let count = u16_at(ifd)? as usize;
+    (0..count)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| u16_at(entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// Replace a PNG file with new pixels in place
+///
+/// Colour profile, density, EXIF and text chunks are carried over to the new file.
+pub fn replace_png_file(path: &str, img: &DynamicImage) -> AppResult<()> {
+    let original = fs::read(path).map_err(|e| format!("Failed to read image: {}", e))?;
+    let chunks = read_chunks(&original)?;
+
+    // Pixels were turned upright when loaded, so a rotating orientation no longer applies
+    let chunks: Vec<_> = preserved_chunks(chunks)
+        .into_iter()
+        .filter(|(kind, data)| &kind.0 != b"eXIf" || tiff_orientation(data).unwrap_or(1) == 1)
+        .collect();
+
+    let encoded = encode_image(img, ExportFormat::Png, 100)?;
+    let output = insert_chunks(&encoded, &chunks)?;
+
+    write_file_atomic(path, &output)
 }
 
 /// Copy a screenshot file to a destination directory
@@ -216,23 +1035,479 @@ mod tests {
         }
     }
 
+    mod resize {
+        use super::*;
+
+        fn options(mode: ResizeMode) -> ResizeOptions {
+            ResizeOptions {
+                mode,
+                filter: ResampleFilter::default(),
+            }
+        }
+
+        #[test]
+        fn test_exact_size() {
+            let resize = options(ResizeMode::Exact {
+                width: 300,
+                height: 100,
+            });
+            assert_eq!(resize.target_size(1920, 1080).unwrap(), (300, 100));
+        }
+
+        #[test]
+        fn test_fit_width_keeps_aspect_ratio() {
+            let resize = options(ResizeMode::Fit {
+                max_width: Some(1200),
+                max_height: None,
+            });
+            assert_eq!(resize.target_size(2400, 1600).unwrap(), (1200, 800));
+        }
+
+        #[test]
+        fn test_fit_never_upscales() {
+            let resize = options(ResizeMode::Fit {
+                max_width: Some(1200),
+                max_height: Some(1200),
+            });
+            assert_eq!(resize.target_size(800, 600).unwrap(), (800, 600));
+        }
+
+        #[test]
+        fn test_scale_factor() {
+            let resize = options(ResizeMode::Scale { factor: 0.5 });
+            assert_eq!(resize.target_size(1921, 1080).unwrap(), (961, 540));
+        }
+
+        #[test]
+        fn test_logical_resolution() {
+            let resize = options(ResizeMode::Logical {
+                scale_factor: Some(2.0),
+            });
+            assert_eq!(resize.target_size(2880, 1800).unwrap(), (1440, 900));
+
+            let unknown = options(ResizeMode::Logical { scale_factor: None });
+            assert!(unknown.target_size(2880, 1800).is_err());
+        }
+
+        #[test]
+        fn test_invalid_sizes_rejected() {
+            assert!(options(ResizeMode::Scale { factor: 0.0 })
+                .target_size(100, 100)
+                .is_err());
+            assert!(options(ResizeMode::Exact {
+                width: 0,
+                height: 10
+            })
+            .target_size(100, 100)
+            .is_err());
+            assert!(options(ResizeMode::Scale { factor: 1000.0 })
+                .target_size(100, 100)
+                .is_err());
+        }
+
+        #[test]
+        fn test_deserialize_resize_options() {
+            let resize: ResizeOptions =
+                serde_json::from_str(r#"{"type": "fit", "max_width": 1200, "filter": "nearest"}"#)
+                    .unwrap();
+
+            assert_eq!(
+                resize.mode,
+                ResizeMode::Fit {
+                    max_width: Some(1200),
+                    max_height: None
+                }
+            );
+            assert_eq!(resize.filter, ResampleFilter::Nearest);
+        }
+
+        #[test]
+        fn test_resize_image_applies_filter() {
+            let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 4, |x, _| {
+                if x < 2 {
+                    Rgba([0, 0, 0, 255])
+                } else {
+                    Rgba([255, 255, 255, 255])
+                }
+            }));
+            let resize = ResizeOptions {
+                mode: ResizeMode::Scale { factor: 2.0 },
+                filter: ResampleFilter::Nearest,
+            };
+
+            let resized = resize_image(&img, &resize).unwrap().to_rgba8();
+
+            assert_eq!(resized.dimensions(), (8, 8));
+            assert_eq!(resized.get_pixel(3, 0), &Rgba([0, 0, 0, 255]));
+            assert_eq!(resized.get_pixel(4, 0), &Rgba([255, 255, 255, 255]));
+        }
+
+        #[test]
+        fn test_watermark_applied_after_resize() {
+            let dir = std::env::temp_dir().join(format!("wm_export_{}", std::process::id()));
+            let logo = dir.join("logo.png");
+            fs::create_dir_all(&dir).unwrap();
+            RgbaImage::from_pixel(10, 10, Rgba([255, 0, 0, 255]))
+                .save(&logo)
+                .unwrap();
+
+            let img =
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 100, Rgba([0, 0, 0, 255])));
+            let options = ExportOptions {
+                resize: Some(options(ResizeMode::Scale { factor: 2.0 })),
+                watermark: Some(WatermarkOptions {
+                    enabled: true,
+                    kind: crate::watermark::WatermarkKind::Image,
+                    image_path: Some(logo.to_string_lossy().into_owned()),
+                    margin: 0.0,
+                    opacity: 1.0,
+                    scale: 0.5,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+
+            let path = save_image_with(&img, &dir.to_string_lossy(), "wm", &options, None).unwrap();
+            let saved = image::open(&path).unwrap().to_rgba8();
+            fs::remove_dir_all(&dir).unwrap();
+
+            // Half of the 200px output, in the bottom-right corner
+            assert_eq!(saved.dimensions(), (200, 200));
+            assert_eq!(saved.get_pixel(100, 100), &Rgba([255, 0, 0, 255]));
+            assert_eq!(saved.get_pixel(99, 99), &Rgba([0, 0, 0, 255]));
+        }
+
+        #[test]
+        fn test_canvas_fixes_output_size() {
+            let dir = std::env::temp_dir().join(format!("canvas_export_{}", std::process::id()));
+            let img =
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(400, 300, Rgba([0, 0, 0, 255])));
+            let options = ExportOptions {
+                canvas: Some(CanvasOptions {
+                    width: 160,
+                    height: 90,
+                    ..Default::default()
+                }),
+                hidpi_suffix: true,
+                display_scale: Some(2.0),
+                ..Default::default()
+            };
+
+            let path =
+                save_image_with(&img, &dir.to_string_lossy(), "canvas", &options, None).unwrap();
+            let saved = image::open(&path).unwrap().to_rgba8();
+            fs::remove_dir_all(&dir).unwrap();
+
+            // A fixed-size canvas isn't tied to the display, so no @2x suffix
+            assert!(!path.contains('@'), "{}", path);
+            assert_eq!(saved.dimensions(), (160, 90));
+            assert_eq!(saved.get_pixel(80, 45), &Rgba([0, 0, 0, 255]));
+            assert_eq!(saved.get_pixel(2, 2)[3], 0);
+        }
+
+        #[test]
+        fn test_palette_strip_resized_with_image() {
+            let dir = std::env::temp_dir().join(format!("strip_export_{}", std::process::id()));
+            let img =
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 100, Rgba([200, 0, 0, 255])));
+            let options = ExportOptions {
+                resize: Some(options(ResizeMode::Scale { factor: 2.0 })),
+                palette_strip: Some(PaletteStripOptions {
+                    height: 0.1,
+                    labels: false,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+
+            let path =
+                save_image_with(&img, &dir.to_string_lossy(), "strip", &options, None).unwrap();
+            let saved = image::open(&path).unwrap().to_rgba8();
+            fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(saved.dimensions(), (200, 220));
+            assert_eq!(saved.get_pixel(100, 215), &Rgba([200, 0, 0, 255]));
+        }
+    }
+
     mod base64_validation {
+        use super::*;
+
+        fn data_url(img: &DynamicImage, format: ImageOutputFormat, mime: &str) -> String {
+            let mut bytes = Vec::new();
+            img.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+            format!(
+                "data:{};base64,{}",
+                mime,
+                general_purpose::STANDARD.encode(bytes)
+            )
+        }
+
+        fn sample() -> DynamicImage {
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 2, Rgba([10, 20, 30, 255])))
+        }
+
+        #[test]
+        fn test_decodes_supported_formats() {
+            let urls = [
+                data_url(&sample(), ImageOutputFormat::Png, "image/png"),
+                data_url(&sample(), ImageOutputFormat::Jpeg(90), "image/jpeg"),
+                data_url(&sample(), ImageOutputFormat::Gif, "image/gif"),
+                data_url(&sample(), ImageOutputFormat::WebP, "image/webp"),
+            ];
+
+            for url in urls {
+                let img = decode_base64_image(&url).unwrap();
+                assert_eq!((img.width(), img.height()), (3, 2));
+            }
+        }
+
+        #[test]
+        fn test_rejects_unsupported_type() {
+            let url = data_url(&sample(), ImageOutputFormat::Bmp, "image/bmp");
+
+            assert!(decode_base64_image(&url).is_err());
+        }
+
+        #[test]
+        fn test_rejects_malformed_payloads() {
+            assert!(decode_base64_image("iVBORw0KGgo=").is_err());
+            assert!(decode_base64_image("data:image/png,iVBORw0KGgo=").is_err());
+            assert!(decode_base64_image("data:image/png;base64,not base64!").is_err());
+
+            // Valid base64, but a truncated PNG
+            let url = data_url(&sample(), ImageOutputFormat::Png, "image/png");
+            assert!(decode_base64_image(&url[..url.len() - 24]).is_err());
+        }
+
+        #[test]
+        fn test_rejects_mismatched_type() {
+            let url = data_url(&sample(), ImageOutputFormat::Png, "image/jpeg");
+
+            assert!(decode_base64_image(&url).is_err());
+        }
+
+        #[test]
+        fn test_rejects_oversized_dimensions() {
+            let wide = DynamicImage::ImageLuma8(image::GrayImage::new(MAX_EXPORT_DIMENSION + 1, 1));
+            let url = data_url(&wide, ImageOutputFormat::Png, "image/png");
+
+            let err = decode_base64_image(&url).unwrap_err();
+            assert!(err.contains("too large"), "{}", err);
+        }
+    }
+
+    mod pixel_density {
+        use super::*;
+        use image::RgbaImage;
+
+        fn encoded(format: ExportFormat) -> Vec<u8> {
+            let img = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+            encode_image(&img, format, 90).unwrap()
+        }
+
+        #[test]
+        fn test_png_phys_matches_scale() {
+            let bytes =
+                set_pixel_density(encoded(ExportFormat::Png), ExportFormat::Png, 2.0).unwrap();
+
+            let decoder = png::Decoder::new(bytes.as_slice());
+            let reader = decoder.read_info().unwrap();
+            let dims = reader.info().pixel_dims.unwrap();
+
+            // 144 dpi is 5669 pixels per metre
+            assert_eq!(dims.xppu, 5669);
+            assert_eq!(dims.yppu, 5669);
+            assert_eq!(dims.unit, png::Unit::Meter);
+        }
+
+        #[test]
+        fn test_png_phys_replaced_not_duplicated() {
+            let once =
+                set_pixel_density(encoded(ExportFormat::Png), ExportFormat::Png, 2.0).unwrap();
+            let twice = set_pixel_density(once, ExportFormat::Png, 1.0).unwrap();
+
+            let phys: Vec<_> = read_chunks(&twice)
+                .unwrap()
+                .into_iter()
+                .filter(|(kind, _)| *kind == png::chunk::pHYs)
+                .collect();
+            assert_eq!(phys.len(), 1);
+            assert_eq!(&phys[0].1[..4], &2835u32.to_be_bytes());
+        }
+
+        #[test]
+        fn test_jpeg_jfif_density() {
+            let bytes =
+                set_pixel_density(encoded(ExportFormat::Jpeg), ExportFormat::Jpeg, 2.0).unwrap();
+
+            assert_eq!(bytes[13], 1);
+            assert_eq!(&bytes[14..18], &[0, 144, 0, 144]);
+            assert!(image::load_from_memory(&bytes).is_ok());
+        }
+
+        #[test]
+        fn test_hidpi_suffix() {
+            assert_eq!(hidpi_suffix(2.0).as_deref(), Some("@2x"));
+            assert_eq!(hidpi_suffix(3.0).as_deref(), Some("@3x"));
+            assert_eq!(hidpi_suffix(1.0), None);
+            assert_eq!(hidpi_suffix(1.5), None);
+        }
+    }
+
+    mod transform {
+        use super::*;
+        use png::chunk::ChunkType;
+
+        /// 3x2 image with a distinct colour in every pixel
+        fn labelled() -> DynamicImage {
+            DynamicImage::ImageRgba8(RgbaImage::from_fn(3, 2, |x, y| {
+                Rgba([x as u8 * 80, y as u8 * 120, 50, 255])
+            }))
+        }
+
+        /// Little-endian TIFF block with only an orientation tag
+        fn exif(orientation: u16) -> Vec<u8> {
+            let mut tiff =
+                b"II\x2A\x00\x08\x00\x00\x00\x01\x00\x12\x01\x03\x00\x01\x00\x00\x00".to_vec();
+            tiff.extend_from_slice(&orientation.to_le_bytes());
+            tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+            tiff
+        }
+
+        #[test]
+        fn test_quarter_turns_are_exact() {
+            let img = labelled();
+
+            assert_eq!(
+                rotate_image(&img, 90.0, Rgba([0, 0, 0, 0])).unwrap(),
+                img.rotate90()
+            );
+            assert_eq!(
+                rotate_image(&img, 450.0, Rgba([0, 0, 0, 0])).unwrap(),
+                img.rotate90()
+            );
+            assert_eq!(
+                rotate_image(&img, -90.0, Rgba([0, 0, 0, 0])).unwrap(),
+                img.rotate270()
+            );
+            assert_eq!(
+                rotate_image(&img, 180.0, Rgba([0, 0, 0, 0])).unwrap(),
+                img.rotate180()
+            );
+        }
+
+        #[test]
+        fn test_arbitrary_angle_expands_and_fills() {
+            let img =
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(20, 20, Rgba([0, 0, 255, 255])));
+
+            let transparent = rotate_image(&img, 45.0, Rgba([0, 0, 0, 0]))
+                .unwrap()
+                .to_rgba8();
+            let filled = rotate_image(&img, 45.0, Rgba([255, 255, 255, 255]))
+                .unwrap()
+                .to_rgba8();
+
+            assert_eq!(transparent.dimensions(), (29, 29));
+            assert_eq!(transparent.get_pixel(0, 0), &Rgba([0, 0, 0, 0]));
+            assert_eq!(transparent.get_pixel(14, 14), &Rgba([0, 0, 255, 255]));
+            // Edges blend without darkening towards the transparent fill
+            assert_eq!(&transparent.get_pixel(14, 0).0[..3], &[0, 0, 255]);
+            assert_eq!(filled.get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+        }
+
         #[test]
-        fn test_base64_prefix_validation() {
-            let valid_prefix = "data:image/png;base64,";
-            let test_data = format!("{}iVBORw0KGgo=", valid_prefix);
+        fn test_rejects_non_finite_angles() {
+            let img = labelled();
 
-            let result = test_data.strip_prefix("data:image/png;base64,");
-            assert!(result.is_some());
-            assert_eq!(result.unwrap(), "iVBORw0KGgo=");
+            assert!(rotate_image(&img, f32::NAN, Rgba([0, 0, 0, 0])).is_err());
+            assert!(rotate_image(&img, f32::INFINITY, Rgba([0, 0, 0, 0])).is_err());
         }
 
         #[test]
-        fn test_base64_invalid_prefix() {
-            let invalid_data = "data:image/jpeg;base64,/9j/4AAQSkZJRg==";
+        fn test_flip_and_transform() {
+            let img = labelled();
+
+            let flipped = flip_image(&img, FlipDirection::Horizontal).to_rgba8();
+            assert_eq!(flipped.get_pixel(0, 0), img.to_rgba8().get_pixel(2, 0));
+
+            let transform: ImageTransform =
+                serde_json::from_str(r#"{"type": "flip", "direction": "vertical"}"#).unwrap();
+            assert_eq!(transform_image(&img, &transform).unwrap(), img.flipv());
+        }
+
+        #[test]
+        fn test_orientation_from_jpeg_and_png() {
+            let img = DynamicImage::ImageRgba8(RgbaImage::new(4, 2));
+
+            let jpeg = encode_image(&img, ExportFormat::Jpeg, 90).unwrap();
+            let tiff = exif(6);
+            let mut app1 = vec![0xFF, 0xE1];
+            app1.extend_from_slice(&(tiff.len() as u16 + 8).to_be_bytes());
+            app1.extend_from_slice(b"Exif\0\0");
+            app1.extend_from_slice(&tiff);
+            let mut tagged = jpeg[..2].to_vec();
+            tagged.extend_from_slice(&app1);
+            tagged.extend_from_slice(&jpeg[2..]);
+
+            assert_eq!(exif_orientation(&tagged), Some(6));
+            let upright = decode_upright(&tagged, ImageFormat::Jpeg).unwrap();
+            assert_eq!((upright.width(), upright.height()), (2, 4));
+
+            let png = encode_image(&img, ExportFormat::Png, 100).unwrap();
+            let tiff = exif(8);
+            let tagged = insert_chunks(&png, &[(ChunkType(*b"eXIf"), &tiff)]).unwrap();
+            assert_eq!(exif_orientation(&tagged), Some(8));
+            assert_eq!(exif_orientation(&png), None);
+        }
+
+        #[test]
+        fn test_replaced_png_drops_applied_orientation() {
+            let png = encode_image(&labelled(), ExportFormat::Png, 100).unwrap();
+            let tiff = exif(6);
+            let tagged = insert_chunks(&png, &[(ChunkType(*b"eXIf"), &tiff)]).unwrap();
+            let path = std::env::temp_dir().join(format!("oriented_{}.png", std::process::id()));
+            fs::write(&path, tagged).unwrap();
+            let path = path.to_string_lossy().into_owned();
+
+            let upright = load_image(&path).unwrap();
+            replace_png_file(&path, &upright).unwrap();
+            let reloaded = load_image(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!((reloaded.width(), reloaded.height()), (2, 3));
+            assert_eq!(reloaded, upright);
+        }
+
+        #[test]
+        fn test_crop_of_oriented_png_is_upright() {
+            let png = encode_image(&labelled(), ExportFormat::Png, 100).unwrap();
+            let tiff = exif(6);
+            let tagged = insert_chunks(&png, &[(ChunkType(*b"eXIf"), &tiff)]).unwrap();
+            let dir = std::env::temp_dir().join(format!("oriented_crop_{}", std::process::id()));
+            ensure_dir(&dir).unwrap();
+            let source = dir.join("source.png");
+            fs::write(&source, tagged).unwrap();
+            let region = CropRegion {
+                x: 0,
+                y: 0,
+                width: 2,
+                height: 3,
+            };
+
+            let saved = crop_image(
+                &source.to_string_lossy(),
+                region,
+                &dir.to_string_lossy(),
+                &ExportOptions::default(),
+            )
+            .unwrap();
+            let cropped = load_image(&saved.path).unwrap();
+            fs::remove_dir_all(&dir).unwrap();
 
-            let result = invalid_data.strip_prefix("data:image/png;base64,");
-            assert!(result.is_none());
+            assert!(!saved.streamed);
+            assert_eq!(cropped, labelled().rotate90());
         }
     }
 }
@@ -0,0 +1,452 @@
+//! Jobs module
+//!
+//! Runs long operations such as batch exports in the background, with
+//! progress reporting, cancellation and at most one running job per CPU.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crate::dedupe::DuplicateOptions;
+use crate::image::ExportOptions;
+use crate::operations::ImageOperation;
+use crate::optimize::OptimizeOptions;
+use crate::utils::AppResult;
+
+/// Number of finished jobs kept for status queries
+const MAX_FINISHED_JOBS: usize = 50;
+
+/// Error returned by cancelled work, and recognised when the job ends
+const CANCELLED: &str = "Job cancelled";
+
+static QUEUE: Mutex<Queue> = Mutex::new(Queue {
+    jobs: Vec::new(),
+    running: 0,
+});
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Work that can be submitted as a background job
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobRequest {
+    /// Optimise PNG files in place
+    Optimize {
+        paths: Vec<String>,
+        options: Option<OptimizeOptions>,
+    },
+    /// Apply editor operations to several images and save each result
+    Process {
+        paths: Vec<String>,
+        operations: Vec<ImageOperation>,
+        save_dir: String,
+        export_options: Option<Box<ExportOptions>>,
+    },
+    /// Find duplicate images in a directory
+    FindDuplicates {
+        dir: String,
+        options: Option<DuplicateOptions>,
+    },
+}
+
+impl JobRequest {
+    /// Short name shown in job listings
+    pub fn kind(&self) -> &'static str {
+        match self {
+            JobRequest::Optimize { .. } => "optimize",
+            JobRequest::Process { .. } => "process",
+            JobRequest::FindDuplicates { .. } => "find_duplicates",
+        }
+    }
+}
+
+/// Lifecycle of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobState::Completed | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+/// Snapshot of a job, returned by queries and sent with every progress event
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub kind: String,
+    pub state: JobState,
+    /// From 0 to 100
+    pub percent: f32,
+    /// What the job is doing right now
+    pub stage: String,
+    /// Estimated seconds left, once some progress has been made
+    pub eta_secs: Option<f32>,
+    /// Output of completed jobs
+    pub result: Option<Value>,
+    pub error: Option<String>,
+}
+
+/// Receives a job's status whenever it changes
+pub type ProgressSink = Arc<dyn Fn(&JobStatus) + Send + Sync>;
+
+type Work = Box<dyn FnOnce(&JobContext) -> AppResult<Value> + Send>;
+
+struct Job {
+    status: JobStatus,
+    cancelled: Arc<AtomicBool>,
+    work: Option<Work>,
+    sink: ProgressSink,
+}
+
+struct Queue {
+    jobs: Vec<Job>,
+    running: usize,
+}
+
+/// Handle given to running work for reporting progress
+pub struct JobContext {
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+    started: Instant,
+}
+
+impl JobContext {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Report that `done` of `total` steps are finished and what comes next
+    ///
+    /// Fails once the job is cancelled, so `?` on it stops the work.
+    pub fn progress(&self, done: usize, total: usize, stage: &str) -> AppResult<()> {
+        if self.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
+
+        let fraction = match total {
+            0 => 0.0,
+            _ => (done as f32 / total as f32).clamp(0.0, 1.0),
+        };
+        let eta_secs = match fraction > 0.0 {
+            true => Some(self.started.elapsed().as_secs_f32() * (1.0 - fraction) / fraction),
+            false => None,
+        };
+
+        update(self.id, |status| {
+            status.percent = fraction * 100.0;
+            status.stage = stage.to_string();
+            status.eta_secs = eta_secs;
+        });
+        Ok(())
+    }
+}
+
+/// Jobs allowed to run at the same time
+pub fn max_concurrency() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Queue work to run in the background and return its initial status
+pub fn submit_job<T, F>(kind: &str, sink: ProgressSink, work: F) -> JobStatus
+where
+    T: Serialize,
+    F: FnOnce(&JobContext) -> AppResult<T> + Send + 'static,
+{
+    let status = JobStatus {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        kind: kind.to_string(),
+        state: JobState::Queued,
+        percent: 0.0,
+        stage: "Queued".to_string(),
+        eta_secs: None,
+        result: None,
+        error: None,
+    };
+    let work: Work = Box::new(move |ctx| {
+        let output = work(ctx)?;
+        serde_json::to_value(output).map_err(|e| format!("Failed to serialize job result: {}", e))
+    });
+
+    if let Ok(mut queue) = QUEUE.lock() {
+        queue.jobs.push(Job {
+            status: status.clone(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            work: Some(work),
+            sink: sink.clone(),
+        });
+    }
+    sink(&status);
+    schedule();
+
+    status
+}
+
+/// Current status of a job
+pub fn job_status(id: u64) -> AppResult<JobStatus> {
+    let queue = QUEUE
+        .lock()
+        .map_err(|e| format!("Failed to read jobs: {}", e))?;
+    queue
+        .jobs
+        .iter()
+        .find(|job| job.status.id == id)
+        .map(|job| job.status.clone())
+        .ok_or_else(|| format!("Unknown job: {}", id))
+}
+
+/// Status of every queued, running and recently finished job, oldest first
+pub fn list_jobs() -> Vec<JobStatus> {
+    QUEUE
+        .lock()
+        .map(|queue| queue.jobs.iter().map(|job| job.status.clone()).collect())
+        .unwrap_or_default()
+}
+
+/// Cancel a job; queued jobs stop at once, running ones at their next progress report
+pub fn cancel_job(id: u64) -> AppResult<JobStatus> {
+    let (status, sink) = {
+        let mut queue = QUEUE
+            .lock()
+            .map_err(|e| format!("Failed to read jobs: {}", e))?;
+        let job = queue
+            .jobs
+            .iter_mut()
+            .find(|job| job.status.id == id)
+            .ok_or_else(|| format!("Unknown job: {}", id))?;
+        if job.status.state.is_finished() {
+            return Err(format!("Job {} has already finished", id));
+        }
+
+        job.cancelled.store(true, Ordering::Relaxed);
+        if job.status.state != JobState::Queued {
+            return Ok(job.status.clone());
+        }
+        job.work = None;
+        job.status.state = JobState::Cancelled;
+        job.status.stage = "Cancelled".to_string();
+        (job.status.clone(), job.sink.clone())
+    };
+
+    sink(&status);
+    Ok(status)
+}
+
+/// Start queued jobs while there are free slots
+fn schedule() {
+    let mut started = Vec::new();
+    if let Ok(mut queue) = QUEUE.lock() {
+        let limit = max_concurrency();
+        while queue.running < limit {
+            let Some(job) = queue
+                .jobs
+                .iter_mut()
+                .find(|job| job.status.state == JobState::Queued)
+            else {
+                break;
+            };
+            let Some(work) = job.work.take() else {
+                break;
+            };
+            job.status.state = JobState::Running;
+            job.status.stage = "Starting".to_string();
+            let ctx = JobContext {
+                id: job.status.id,
+                cancelled: job.cancelled.clone(),
+                started: Instant::now(),
+            };
+            started.push((job.status.clone(), job.sink.clone(), ctx, work));
+            queue.running += 1;
+        }
+    }
+
+    for (status, sink, ctx, work) in started {
+        sink(&status);
+        thread::spawn(move || run(ctx, work));
+    }
+}
+
+/// Run a job's work on the current thread and record how it ended
+fn run(ctx: JobContext, work: Work) {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| work(&ctx)))
+        .unwrap_or_else(|_| Err("Job panicked".to_string()));
+
+    update(ctx.id, |status| {
+        status.eta_secs = None;
+        match result {
+            Ok(value) => {
+                status.state = JobState::Completed;
+                status.percent = 100.0;
+                status.stage = "Done".to_string();
+                status.result = Some(value);
+            }
+            Err(_) if ctx.is_cancelled() => {
+                status.state = JobState::Cancelled;
+                status.stage = "Cancelled".to_string();
+            }
+            Err(e) => {
+                status.state = JobState::Failed;
+                status.stage = "Failed".to_string();
+                status.error = Some(e);
+            }
+        }
+    });
+
+    if let Ok(mut queue) = QUEUE.lock() {
+        queue.running -= 1;
+        prune(&mut queue.jobs);
+    }
+    schedule();
+}
+
+/// Change a job's status and send the new status to its sink
+fn update(id: u64, change: impl FnOnce(&mut JobStatus)) {
+    let updated = QUEUE.lock().ok().and_then(|mut queue| {
+        let job = queue.jobs.iter_mut().find(|job| job.status.id == id)?;
+        change(&mut job.status);
+        Some((job.status.clone(), job.sink.clone()))
+    });
+
+    // Sent outside the lock, so sinks may query the queue
+    if let Some((status, sink)) = updated {
+        sink(&status);
+    }
+}
+
+/// Forget the oldest finished jobs beyond the limit
+fn prune(jobs: &mut Vec<Job>) {
+    let finished = jobs
+        .iter()
+        .filter(|job| job.status.state.is_finished())
+        .count();
+    let mut excess = finished.saturating_sub(MAX_FINISHED_JOBS);
+    jobs.retain(|job| match excess > 0 && job.status.state.is_finished() {
+        true => {
+            excess -= 1;
+            false
+        }
+        false => true,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    fn quiet() -> ProgressSink {
+        Arc::new(|_| {})
+    }
+
+    fn wait(id: u64) -> JobStatus {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let status = job_status(id).unwrap();
+            if status.state.is_finished() || Instant::now() > deadline {
+                return status;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_completes_with_progress_events() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let sink: ProgressSink = Arc::new(move |status: &JobStatus| {
+            recorded.lock().unwrap().push(status.clone());
+        });
+
+        let job = submit_job("count", sink, |ctx| {
+            for i in 0..4 {
+                ctx.progress(i, 4, "Counting")?;
+            }
+            Ok(vec![1, 2, 3])
+        });
+        let status = wait(job.id);
+
+        assert_eq!(status.state, JobState::Completed);
+        assert_eq!(status.percent, 100.0);
+        assert_eq!(status.result, Some(serde_json::json!([1, 2, 3])));
+        let events = events.lock().unwrap();
+        assert_eq!(events[0].state, JobState::Queued);
+        assert!(events
+            .iter()
+            .any(|e| e.stage == "Counting" && e.percent == 50.0 && e.eta_secs.is_some()));
+        assert_eq!(events.last().unwrap().state, JobState::Completed);
+    }
+
+    #[test]
+    fn test_cancel_stops_running_job() {
+        let job = submit_job("spin", quiet(), |ctx| -> AppResult<()> {
+            loop {
+                ctx.progress(0, 1, "Spinning")?;
+                thread::sleep(Duration::from_millis(2));
+            }
+        });
+        while job_status(job.id).unwrap().state == JobState::Queued {
+            thread::sleep(Duration::from_millis(2));
+        }
+
+        cancel_job(job.id).unwrap();
+
+        let status = wait(job.id);
+        assert_eq!(status.state, JobState::Cancelled);
+        assert!(status.error.is_none());
+        assert!(cancel_job(job.id).is_err());
+    }
+
+    #[test]
+    fn test_concurrency_is_capped() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let ids: Vec<u64> = (0..max_concurrency() * 2 + 1)
+            .map(|_| {
+                let (running, peak) = (running.clone(), peak.clone());
+                submit_job("sleep", quiet(), move |_| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(())
+                })
+                .id
+            })
+            .collect();
+
+        for id in ids {
+            assert_eq!(wait(id).state, JobState::Completed);
+        }
+        assert!(peak.load(Ordering::SeqCst) <= max_concurrency());
+    }
+
+    #[test]
+    fn test_failures_and_unknown_jobs() {
+        let job = submit_job("fail", quiet(), |_| -> AppResult<()> {
+            Err("Disk full".to_string())
+        });
+
+        let status = wait(job.id);
+        assert_eq!(status.state, JobState::Failed);
+        assert_eq!(status.error.as_deref(), Some("Disk full"));
+        assert!(job_status(u64::MAX).is_err());
+        assert!(list_jobs().iter().any(|j| j.id == job.id));
+    }
+}
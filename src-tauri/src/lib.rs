@@ -4,16 +4,45 @@
 //! and saving screenshots with various features like region selection
 //! and background customization.
 
+mod adjust;
+mod backdrop;
+mod canvas;
 mod clipboard;
+mod color;
 mod commands;
+mod compose;
+mod dedupe;
+mod diff;
+mod draw;
+mod export;
+mod gradient;
 mod image;
+mod jobs;
+mod magnifier;
+mod metadata;
+mod mockup;
+mod operations;
+mod optimize;
+mod palette;
+mod scan;
 mod screenshot;
+mod settings;
+mod shadow;
+mod spotlight;
+mod stream;
+mod text;
+mod trim;
 mod utils;
+mod watermark;
 
 use commands::{
-    capture_all_monitors, capture_once, capture_region, get_desktop_directory, get_mouse_position,
-    get_temp_directory, native_capture_fullscreen, native_capture_interactive,
-    native_capture_window, play_screenshot_sound, save_edited_image,
+    adjust_image, auto_trim, cancel_job, capture_all_monitors, capture_once, capture_region,
+    compose_images, diff_images, extract_palette, find_duplicates, get_desktop_directory,
+    get_mouse_position, get_temp_directory, job_status, list_canvas_presets, list_frames,
+    list_jobs, native_capture_fullscreen, native_capture_interactive, native_capture_window,
+    optimize_images, play_screenshot_sound, preview_operations, process_images, render_backdrop,
+    render_gradient, render_mockup, save_edited_image, save_edited_image_under_size, scan_codes,
+    submit_job, transform_image, window_shadow,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -53,6 +82,9 @@ pub fn run() {
             let capture_window_item =
                 MenuItemBuilder::with_id("capture_window", "Capture Window").build(app)?;
 
+            let scan_qr_item =
+                MenuItemBuilder::with_id("scan_qr", "Scan QR from Screen").build(app)?;
+
             let quit_item = MenuItemBuilder::with_id("quit", "Quit")
                 .accelerator("CommandOrControl+Q")
                 .build(app)?;
@@ -65,6 +97,8 @@ pub fn run() {
                     &capture_screen_item,
                     &capture_window_item,
                     &PredefinedMenuItem::separator(app)?,
+                    &scan_qr_item,
+                    &PredefinedMenuItem::separator(app)?,
                     &quit_item,
                 ])
                 .build()?;
@@ -91,6 +125,9 @@ pub fn run() {
                         "capture_window" => {
                             let _ = app.emit("capture-window", ());
                         }
+                        "scan_qr" => {
+                            let _ = app.emit("scan-qr-triggered", ());
+                        }
                         "quit" => {
                             app.exit(0);
                         }
@@ -112,7 +149,29 @@ pub fn run() {
             native_capture_fullscreen,
             native_capture_window,
             play_screenshot_sound,
-            get_mouse_position
+            get_mouse_position,
+            optimize_images,
+            save_edited_image_under_size,
+            diff_images,
+            find_duplicates,
+            auto_trim,
+            window_shadow,
+            list_frames,
+            render_mockup,
+            compose_images,
+            transform_image,
+            preview_operations,
+            process_images,
+            adjust_image,
+            render_gradient,
+            render_backdrop,
+            list_canvas_presets,
+            submit_job,
+            job_status,
+            cancel_job,
+            list_jobs,
+            scan_codes,
+            extract_palette
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
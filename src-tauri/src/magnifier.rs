@@ -0,0 +1,310 @@
+//! Magnifier module
+//!
+//! Draws an enlarged copy of a small region as an inset in the same image,
+//! with a connector line back to the region it shows.
+
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+use serde::Deserialize;
+
+use crate::draw::{
+    draw_line, fill_rounded_rect, mask_corners, stroke_rounded_rect, Color, Corners, Rect,
+};
+use crate::image::{ResampleFilter, MAX_EXPORT_DIMENSION};
+use crate::shadow::soften;
+use crate::utils::AppResult;
+
+/// Outline of the inset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MagnifierShape {
+    #[default]
+    Circle,
+    RoundedRect,
+}
+
+/// Region to enlarge, where to show it and how to style the inset
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct MagnifierOptions {
+    /// Region to enlarge, in image pixels
+    pub source: Rect,
+    /// Centre of the inset, in image pixels
+    pub inset_x: f32,
+    pub inset_y: f32,
+    pub zoom: f32,
+    pub shape: MagnifierShape,
+    /// Corner radius of rounded-rect insets
+    pub corner_radius: f32,
+    pub filter: ResampleFilter,
+    pub border_width: f32,
+    pub border_color: Color,
+    /// Softness of the inset's drop shadow; an opacity of 0 turns it off
+    pub shadow_blur: f32,
+    pub shadow_opacity: f32,
+    pub shadow_offset_y: f32,
+    /// Width of the source outline and connector line; 0 turns them off
+    pub connector_width: f32,
+    pub connector_color: Color,
+}
+
+impl Default for MagnifierOptions {
+    fn default() -> Self {
+        Self {
+            source: Rect::new(0.0, 0.0, 0.0, 0.0),
+            inset_x: 0.0,
+            inset_y: 0.0,
+            zoom: 2.0,
+            shape: MagnifierShape::Circle,
+            corner_radius: 12.0,
+            filter: ResampleFilter::Lanczos3,
+            border_width: 4.0,
+            border_color: Color(Rgba([255, 255, 255, 255])),
+            shadow_blur: 16.0,
+            shadow_opacity: 0.35,
+            shadow_offset_y: 6.0,
+            connector_width: 2.0,
+            connector_color: Color(Rgba([255, 255, 255, 255])),
+        }
+    }
+}
+
+/// Draw a magnifier inset onto a copy of the image
+pub fn magnify(img: &DynamicImage, options: &MagnifierOptions) -> AppResult<RgbaImage> {
+    if !options.zoom.is_finite() || options.zoom <= 0.0 {
+        return Err(format!("Invalid zoom factor: {}", options.zoom));
+    }
+
+    // Circles show a square region around the centre of the source
+    let mut source = options.source;
+    if options.shape == MagnifierShape::Circle {
+        let side = source.width.max(source.height);
+        source = Rect::new(
+            source.x + (source.width - side) / 2.0,
+            source.y + (source.height - side) / 2.0,
+            side,
+            side,
+        );
+    }
+    let left = source.x.max(0.0).round();
+    let top = source.y.max(0.0).round();
+    let right = source.right().min(img.width() as f32).round();
+    let bottom = source.bottom().min(img.height() as f32).round();
+    if right <= left || bottom <= top {
+        return Err("Magnifier source region is outside the image".to_string());
+    }
+    let source = Rect::new(left, top, right - left, bottom - top);
+
+    let zoomed_width = (source.width * options.zoom).round().max(1.0);
+    let zoomed_height = (source.height * options.zoom).round().max(1.0);
+    if zoomed_width > MAX_EXPORT_DIMENSION as f32 || zoomed_height > MAX_EXPORT_DIMENSION as f32 {
+        return Err("Magnified region is too large".to_string());
+    }
+    let mut zoomed = img
+        .crop_imm(
+            source.x as u32,
+            source.y as u32,
+            source.width as u32,
+            source.height as u32,
+        )
+        .resize_exact(
+            zoomed_width as u32,
+            zoomed_height as u32,
+            options.filter.into(),
+        )
+        .to_rgba8();
+
+    let inset = Rect::new(
+        (options.inset_x - zoomed_width / 2.0).round(),
+        (options.inset_y - zoomed_height / 2.0).round(),
+        zoomed_width,
+        zoomed_height,
+    );
+    let (radius, source_radius) = match options.shape {
+        MagnifierShape::Circle => (zoomed_width / 2.0, source.width / 2.0),
+        MagnifierShape::RoundedRect => {
+            (options.corner_radius, options.corner_radius / options.zoom)
+        }
+    };
+
+    let mut canvas = img.to_rgba8();
+
+    if options.connector_width > 0.0 {
+        let color = options.connector_color.0;
+        let width = options.connector_width;
+        let outline = Rect::new(
+            source.x - width,
+            source.y - width,
+            source.width + width * 2.0,
+            source.height + width * 2.0,
+        );
+        stroke_rounded_rect(&mut canvas, &outline, source_radius + width, width, color);
+        // Start at the edge of the source so the line never covers it
+        let from = centre(&source);
+        let to = centre(&inset);
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let exit = (source.width / 2.0 / dx.abs()).min(source.height / 2.0 / dy.abs());
+        if exit < 1.0 {
+            let start = (from.0 + dx * exit, from.1 + dy * exit);
+            draw_line(&mut canvas, start, to, options.connector_width, color);
+        }
+    }
+
+    if options.shadow_opacity > 0.0 {
+        draw_shadow(&mut canvas, &inset, radius, options);
+    }
+
+    mask_corners(&mut zoomed, radius, Corners::ALL);
+    image::imageops::overlay(&mut canvas, &zoomed, inset.x as i64, inset.y as i64);
+
+    if options.border_width > 0.0 {
+        stroke_rounded_rect(
+            &mut canvas,
+            &inset,
+            radius,
+            options.border_width,
+            options.border_color.0,
+        );
+    }
+
+    Ok(canvas)
+}
+
+fn centre(rect: &Rect) -> (f32, f32) {
+    (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0)
+}
+
+/// Soft shadow of the inset shape, drawn beneath it
+fn draw_shadow(canvas: &mut RgbaImage, inset: &Rect, radius: f32, options: &MagnifierOptions) {
+    let blur = options.shadow_blur.max(0.0);
+    let margin = (blur * 2.0).ceil();
+    let mut shape = RgbaImage::new(
+        (inset.width + margin * 2.0) as u32,
+        (inset.height + margin * 2.0) as u32,
+    );
+    let local = Rect::new(margin, margin, inset.width, inset.height);
+    fill_rounded_rect(&mut shape, &local, radius, Rgba([0, 0, 0, 255]));
+
+    let mask = GrayImage::from_fn(shape.width(), shape.height(), |x, y| {
+        Luma([shape.get_pixel(x, y)[3]])
+    });
+    let mask = soften(&mask, blur / 2.0);
+
+    let opacity = options.shadow_opacity.clamp(0.0, 1.0);
+    let shadow = RgbaImage::from_fn(mask.width(), mask.height(), |x, y| {
+        Rgba([
+            0,
+            0,
+            0,
+            (mask.get_pixel(x, y)[0] as f32 * opacity).round() as u8,
+        ])
+    });
+    image::imageops::overlay(
+        canvas,
+        &shadow,
+        (inset.x - margin) as i64,
+        (inset.y - margin + options.shadow_offset_y).round() as i64,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grey image with a small checkerboard detail at (10, 10)
+    fn detailed() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(200, 120, |x, y| {
+            if (10..20).contains(&x) && (10..20).contains(&y) {
+                match (x / 5 + y / 5) % 2 {
+                    0 => Rgba([0, 0, 0, 255]),
+                    _ => Rgba([255, 255, 0, 255]),
+                }
+            } else {
+                Rgba([128, 128, 128, 255])
+            }
+        }))
+    }
+
+    fn options(shape: MagnifierShape) -> MagnifierOptions {
+        MagnifierOptions {
+            source: Rect::new(10.0, 10.0, 10.0, 10.0),
+            inset_x: 120.0,
+            inset_y: 60.0,
+            zoom: 4.0,
+            shape,
+            filter: ResampleFilter::Nearest,
+            border_width: 0.0,
+            shadow_opacity: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rounded_rect_inset_shows_zoomed_source() {
+        let mut options = options(MagnifierShape::RoundedRect);
+        options.connector_width = 0.0;
+
+        let result = magnify(&detailed(), &options).unwrap();
+
+        // 40x40 inset centred on (120, 60); each source pixel becomes 4x4
+        assert_eq!(result.get_pixel(105, 45), &Rgba([0, 0, 0, 255]));
+        assert_eq!(result.get_pixel(125, 45), &Rgba([255, 255, 0, 255]));
+        assert_eq!(result.get_pixel(125, 65), &Rgba([0, 0, 0, 255]));
+        assert_eq!(result.get_pixel(150, 60), &Rgba([128, 128, 128, 255]));
+        // The source region is untouched
+        assert_eq!(result.get_pixel(12, 12), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_circle_clips_corners() {
+        let mut options = options(MagnifierShape::Circle);
+        options.connector_width = 0.0;
+
+        let result = magnify(&detailed(), &options).unwrap();
+
+        assert_eq!(result.get_pixel(101, 41), &Rgba([128, 128, 128, 255]));
+        assert_eq!(result.get_pixel(105, 52), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_connector_leaves_source_visible() {
+        let mut options = options(MagnifierShape::RoundedRect);
+        options.connector_color = Color(Rgba([255, 0, 0, 255]));
+
+        let result = magnify(&detailed(), &options).unwrap();
+
+        // Outline around the source, line towards the inset, source interior kept
+        assert_eq!(result.get_pixel(9, 15), &Rgba([255, 0, 0, 255]));
+        assert_eq!(result.get_pixel(10, 15), &Rgba([255, 255, 0, 255]));
+        let line = (21..99)
+            .filter(|&x| (10..70).any(|y| result.get_pixel(x, y) == &Rgba([255, 0, 0, 255])));
+        assert!(line.count() > 60);
+    }
+
+    #[test]
+    fn test_border_and_shadow() {
+        let options = MagnifierOptions {
+            border_width: 3.0,
+            border_color: Color(Rgba([0, 0, 255, 255])),
+            shadow_opacity: 0.8,
+            connector_width: 0.0,
+            ..options(MagnifierShape::RoundedRect)
+        };
+
+        let result = magnify(&detailed(), &options).unwrap();
+
+        assert_eq!(result.get_pixel(120, 41), &Rgba([0, 0, 255, 255]));
+        // Shadow darkens the background just below the inset
+        assert!(result.get_pixel(120, 84)[0] < 120);
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let mut options = options(MagnifierShape::Circle);
+        options.zoom = 0.0;
+        assert!(magnify(&detailed(), &options).is_err());
+
+        options.zoom = 2.0;
+        options.source = Rect::new(500.0, 500.0, 10.0, 10.0);
+        assert!(magnify(&detailed(), &options).is_err());
+    }
+}
@@ -0,0 +1,511 @@
+//! Capture metadata module
+//!
+//! Records where and how each capture was taken, then embeds it in saved
+//! files as PNG iTXt chunks or JPEG EXIF, with an optional JSON sidecar.
+
+use png::chunk::ChunkType;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::optimize::{insert_chunks, remove_chunks};
+use crate::utils::{write_file_atomic, AppResult};
+
+/// Number of recent captures whose metadata is kept in memory
+const MAX_TRACKED_CAPTURES: usize = 32;
+
+/// Software name written to saved files
+const SOFTWARE: &str = "Better Shot";
+
+/// PNG chunks removed when stripping metadata
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 5] = [b"tEXt", b"zTXt", b"iTXt", b"eXIf", b"tIME"];
+
+static CAPTURES: Mutex<Vec<(String, CaptureMetadata)>> = Mutex::new(Vec::new());
+
+/// Geometry of the monitor a capture was taken on
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MonitorInfo {
+    pub id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+}
+
+/// Window a capture was taken of
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WindowInfo {
+    pub title: String,
+    pub app_name: String,
+}
+
+/// Everything known about how a capture was taken
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CaptureMetadata {
+    /// RFC 3339 timestamp in local time
+    pub captured_at: String,
+    /// One of "region", "fullscreen", "window" or "monitor"
+    pub capture_mode: String,
+    pub monitor: Option<MonitorInfo>,
+    pub window: Option<WindowInfo>,
+}
+
+impl CaptureMetadata {
+    /// Create metadata for a capture taken now
+    pub fn now(capture_mode: &str) -> Self {
+        Self {
+            captured_at: chrono::Local::now().to_rfc3339(),
+            capture_mode: capture_mode.to_string(),
+            monitor: None,
+            window: None,
+        }
+    }
+
+    /// Human-readable key/value pairs written to PNG text chunks
+    fn text_entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![
+            ("Software", SOFTWARE.to_string()),
+            ("Creation Time", self.captured_at.clone()),
+            ("Capture Mode", self.capture_mode.clone()),
+        ];
+
+        if let Some(monitor) = &self.monitor {
+            entries.push((
+                "Monitor",
+                format!(
+                    "id={} x={} y={} {}x{}",
+                    monitor.id, monitor.x, monitor.y, monitor.width, monitor.height
+                ),
+            ));
+            entries.push(("Scale Factor", monitor.scale_factor.to_string()));
+        }
+
+        if let Some(window) = &self.window {
+            entries.push(("Title", window.title.clone()));
+            entries.push(("Application", window.app_name.clone()));
+        }
+
+        entries
+    }
+}
+
+/// Remember the metadata for a capture file so later saves can embed it
+pub fn track_capture(path: &str, metadata: CaptureMetadata) {
+    let Ok(mut captures) = CAPTURES.lock() else {
+        return;
+    };
+
+    captures.retain(|(p, _)| p != path);
+    if captures.len() >= MAX_TRACKED_CAPTURES {
+        captures.remove(0);
+    }
+    captures.push((path.to_string(), metadata));
+}
+
+/// Look up the metadata recorded for a capture file
+pub fn capture_metadata(path: &str) -> Option<CaptureMetadata> {
+    let captures = CAPTURES.lock().ok()?;
+    captures
+        .iter()
+        .find(|(p, _)| p == path)
+        .map(|(_, metadata)| metadata.clone())
+}
+
+/// Embed metadata into a saved PNG or JPEG file
+///
+/// Other formats are left untouched; the sidecar still covers them.
+pub fn embed_metadata_file(path: &str, metadata: &CaptureMetadata) -> AppResult<()> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read image: {}", e))?;
+
+    let updated = match extension(path).as_deref() {
+        Some("png") => embed_png_metadata(&data, metadata)?,
+        Some("jpg") | Some("jpeg") => embed_jpeg_metadata(&data, metadata)?,
+        _ => return Ok(()),
+    };
+
+    write_file_atomic(path, &updated)
+}
+
+/// Remove text, EXIF and timestamp metadata from a saved PNG or JPEG file
+///
+/// Colour profiles and pixel density are kept since they affect rendering.
+pub fn strip_metadata_file(path: &str) -> AppResult<()> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read image: {}", e))?;
+
+    let stripped = match extension(path).as_deref() {
+        Some("png") => strip_png_metadata(&data)?,
+        Some("jpg") | Some("jpeg") => strip_jpeg_metadata(&data)?,
+        _ => return Ok(()),
+    };
+
+    if stripped.len() != data.len() {
+        write_file_atomic(path, &stripped)?;
+    }
+    Ok(())
+}
+
+/// Write metadata as a `.json` file next to the image, returning its path
+pub fn write_sidecar(image_path: &str, metadata: &CaptureMetadata) -> AppResult<String> {
+    let sidecar_path = Path::new(image_path).with_extension("json");
+    let json = serde_json::to_string_pretty(metadata)
+        .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+
+    fs::write(&sidecar_path, json).map_err(|e| format!("Failed to write sidecar: {}", e))?;
+
+    Ok(sidecar_path.to_string_lossy().into_owned())
+}
+
+fn extension(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+}
+
+/// Add one iTXt chunk per metadata entry
+pub fn embed_png_metadata(data: &[u8], metadata: &CaptureMetadata) -> AppResult<Vec<u8>> {
+    let bodies: Vec<Vec<u8>> = metadata
+        .text_entries()
+        .into_iter()
+        .map(|(keyword, text)| itxt_body(keyword, &text))
+        .collect();
+    let chunks: Vec<(ChunkType, &[u8])> = bodies
+        .iter()
+        .map(|body| (ChunkType(*b"iTXt"), body.as_slice()))
+        .collect();
+
+    insert_chunks(&strip_png_metadata(data)?, &chunks)
+}
+
+/// Build an uncompressed iTXt chunk body
+fn itxt_body(keyword: &str, text: &str) -> Vec<u8> {
+    let mut body = Vec::with_capacity(keyword.len() + text.len() + 5);
+    body.extend_from_slice(keyword.as_bytes());
+    // Null separator, compression flag and method, then empty language and translated keyword
+    body.extend_from_slice(&[0, 0, 0, 0, 0]);
+    body.extend_from_slice(text.as_bytes());
+    body
+}
+
+/// Drop text, EXIF and timestamp chunks from a PNG
+pub fn strip_png_metadata(data: &[u8]) -> AppResult<Vec<u8>> {
+    remove_chunks(data, &PNG_METADATA_CHUNKS)
+}
+
+/// JPEG header segment: marker byte and the full segment including the marker
+pub type JpegSegment<'a> = (u8, &'a [u8]);
+
+/// Split a JPEG into its header segments and the offset where scan data starts
+pub fn jpeg_segments(data: &[u8]) -> AppResult<(Vec<JpegSegment<'_>>, usize)> {
+    if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+        return Err("Invalid JPEG: missing SOI marker".to_string());
+    }
+
+    let mut segments = Vec::new();
+    let mut offset = 2;
+
+    while offset + 4 <= data.len() && data[offset] == 0xFF {
+        let marker = data[offset + 1];
+        // Start of scan: everything after is entropy-coded data
+        if marker == 0xDA {
+            break;
+        }
+        let length = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let end = offset + 2 + length;
+        if length < 2 || end > data.len() {
+            return Err("Invalid JPEG: truncated segment".to_string());
+        }
+        segments.push((marker, &data[offset..end]));
+        offset = end;
+    }
+
+    Ok((segments, offset))
+}
+
+/// Insert an EXIF APP1 segment, replacing any existing one
+pub fn embed_jpeg_metadata(data: &[u8], metadata: &CaptureMetadata) -> AppResult<Vec<u8>> {
+    let exif = build_exif(metadata)?;
+    let (segments, scan_start) = jpeg_segments(data)?;
+
+    let mut out = Vec::with_capacity(data.len() + exif.len() + 10);
+    out.extend_from_slice(&[0xFF, 0xD8]);
+
+    let mut inserted = false;
+    for (marker, segment) in segments {
+        if marker == 0xE1 && segment[4..].starts_with(b"Exif\0\0") {
+            continue;
+        }
+        // Keep a JFIF APP0 header first, as readers expect
+        if !inserted && marker != 0xE0 {
+            write_app1(&mut out, &exif)?;
+            inserted = true;
+        }
+        out.extend_from_slice(segment);
+    }
+    if !inserted {
+        write_app1(&mut out, &exif)?;
+    }
+
+    out.extend_from_slice(&data[scan_start..]);
+    Ok(out)
+}
+
+fn write_app1(out: &mut Vec<u8>, exif: &[u8]) -> AppResult<()> {
+    let length = u16::try_from(exif.len() + 8).map_err(|_| "EXIF metadata too large")?;
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&length.to_be_bytes());
+    out.extend_from_slice(b"Exif\0\0");
+    out.extend_from_slice(exif);
+    Ok(())
+}
+
+/// Drop EXIF/XMP APP1 segments and comments from a JPEG
+pub fn strip_jpeg_metadata(data: &[u8]) -> AppResult<Vec<u8>> {
+    let (segments, scan_start) = jpeg_segments(data)?;
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&[0xFF, 0xD8]);
+    for (marker, segment) in segments {
+        if marker == 0xE1 || marker == 0xFE {
+            continue;
+        }
+        out.extend_from_slice(segment);
+    }
+    out.extend_from_slice(&data[scan_start..]);
+
+    Ok(out)
+}
+
+/// TIFF field types used in the EXIF block
+const TIFF_ASCII: u16 = 2;
+const TIFF_LONG: u16 = 4;
+const TIFF_UNDEFINED: u16 = 7;
+
+/// A single IFD entry: tag, field type and raw big-endian value bytes
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    value: Vec<u8>,
+}
+
+impl IfdEntry {
+    fn ascii(tag: u16, text: &str) -> Self {
+        let mut value = text.as_bytes().to_vec();
+        value.push(0);
+        Self {
+            tag,
+            field_type: TIFF_ASCII,
+            value,
+        }
+    }
+
+    fn count(&self) -> u32 {
+        // ASCII and UNDEFINED are byte arrays; LONG is four bytes per value
+        match self.field_type {
+            TIFF_LONG => (self.value.len() / 4) as u32,
+            _ => self.value.len() as u32,
+        }
+    }
+}
+
+/// Build a big-endian TIFF structure with IFD0 and an EXIF sub-IFD
+fn build_exif(metadata: &CaptureMetadata) -> AppResult<Vec<u8>> {
+    let date_time = chrono::DateTime::parse_from_rfc3339(&metadata.captured_at)
+        .map(|d| d.format("%Y:%m:%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+    let description = match &metadata.window {
+        Some(window) => format!("{} ({})", window.title, window.app_name),
+        None => format!("{} capture", metadata.capture_mode),
+    };
+
+    let json = serde_json::to_string(metadata)
+        .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+    let mut user_comment = b"ASCII\0\0\0".to_vec();
+    user_comment.extend_from_slice(json.as_bytes());
+
+    let mut ifd0 = vec![
+        IfdEntry::ascii(0x010E, &description),
+        IfdEntry::ascii(0x0131, SOFTWARE),
+    ];
+    if !date_time.is_empty() {
+        ifd0.push(IfdEntry::ascii(0x0132, &date_time));
+    }
+
+    let mut exif_ifd = Vec::new();
+    if !date_time.is_empty() {
+        exif_ifd.push(IfdEntry::ascii(0x9003, &date_time));
+    }
+    exif_ifd.push(IfdEntry {
+        tag: 0x9286,
+        field_type: TIFF_UNDEFINED,
+        value: user_comment,
+    });
+
+    // IFD0 ends with a pointer to the EXIF IFD, patched once its offset is known
+    ifd0.push(IfdEntry {
+        tag: 0x8769,
+        field_type: TIFF_LONG,
+        value: vec![0; 4],
+    });
+
+    let mut tiff = b"MM\x00\x2A\x00\x00\x00\x08".to_vec();
+    let pointer_at = write_ifd(&mut tiff, &ifd0)?;
+    let exif_offset = u32::try_from(tiff.len()).map_err(|_| "EXIF metadata too large")?;
+    tiff[pointer_at..pointer_at + 4].copy_from_slice(&exif_offset.to_be_bytes());
+    write_ifd(&mut tiff, &exif_ifd)?;
+
+    Ok(tiff)
+}
+
+/// Append an IFD and its out-of-line values
+///
+/// Returns the position of the last entry's value field so it can be patched.
+fn write_ifd(tiff: &mut Vec<u8>, entries: &[IfdEntry]) -> AppResult<usize> {
+    let ifd_start = tiff.len();
+    let mut data_offset = ifd_start + 2 + entries.len() * 12 + 4;
+    let mut data = Vec::new();
+    let mut last_value_at = 0;
+
+    tiff.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+    for entry in entries {
+        tiff.extend_from_slice(&entry.tag.to_be_bytes());
+        tiff.extend_from_slice(&entry.field_type.to_be_bytes());
+        tiff.extend_from_slice(&entry.count().to_be_bytes());
+        last_value_at = tiff.len();
+
+        if entry.value.len() <= 4 {
+            let mut inline = [0u8; 4];
+            inline[..entry.value.len()].copy_from_slice(&entry.value);
+            tiff.extend_from_slice(&inline);
+        } else {
+            let offset = u32::try_from(data_offset).map_err(|_| "EXIF metadata too large")?;
+            tiff.extend_from_slice(&offset.to_be_bytes());
+            data.extend_from_slice(&entry.value);
+            // Values must start on a word boundary
+            if entry.value.len() % 2 == 1 {
+                data.push(0);
+            }
+            data_offset = ifd_start + 2 + entries.len() * 12 + 4 + data.len();
+        }
+    }
+    // No further IFDs
+    tiff.extend_from_slice(&[0, 0, 0, 0]);
+    tiff.extend_from_slice(&data);
+
+    Ok(last_value_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize::read_chunks;
+    use image::{DynamicImage, ImageOutputFormat, RgbaImage};
+    use std::io::Cursor;
+
+    fn sample_metadata() -> CaptureMetadata {
+        CaptureMetadata {
+            captured_at: "2026-01-02T03:04:05+01:00".to_string(),
+            capture_mode: "window".to_string(),
+            monitor: Some(MonitorInfo {
+                id: 1,
+                x: 0,
+                y: 0,
+                width: 1440,
+                height: 900,
+                scale_factor: 2.0,
+            }),
+            window: Some(WindowInfo {
+                title: "Inbox — Mail".to_string(),
+                app_name: "Mail".to_string(),
+            }),
+        }
+    }
+
+    fn encode(format: ImageOutputFormat) -> Vec<u8> {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(8, 8));
+        let mut out = Cursor::new(Vec::new());
+        img.write_to(&mut out, format).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn test_png_metadata_round_trip() {
+        let png = embed_png_metadata(&encode(ImageOutputFormat::Png), &sample_metadata()).unwrap();
+
+        let decoder = png::Decoder::new(Cursor::new(&png));
+        let reader = decoder.read_info().unwrap();
+        let texts: Vec<(String, String)> = reader
+            .info()
+            .utf8_text
+            .iter()
+            .map(|t| (t.keyword.clone(), t.get_text().unwrap()))
+            .collect();
+
+        assert!(texts.contains(&("Capture Mode".to_string(), "window".to_string())));
+        assert!(texts.contains(&("Title".to_string(), "Inbox — Mail".to_string())));
+        assert!(image::load_from_memory(&png).is_ok());
+    }
+
+    #[test]
+    fn test_png_metadata_strip() {
+        let png = embed_png_metadata(&encode(ImageOutputFormat::Png), &sample_metadata()).unwrap();
+        let stripped = strip_png_metadata(&png).unwrap();
+
+        assert!(stripped.len() < png.len());
+        assert!(!read_chunks(&stripped)
+            .unwrap()
+            .iter()
+            .any(|(kind, _)| &kind.0 == b"iTXt"));
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn test_jpeg_exif_round_trip() {
+        let jpeg = encode(ImageOutputFormat::Jpeg(90));
+        let tagged = embed_jpeg_metadata(&jpeg, &sample_metadata()).unwrap();
+
+        assert!(tagged.windows(6).any(|w| w == b"Exif\0\0"));
+        assert!(tagged
+            .windows(b"2026:01:02 03:04:05".len())
+            .any(|w| w == b"2026:01:02 03:04:05"));
+        assert!(image::load_from_memory(&tagged).is_ok());
+
+        let stripped = strip_jpeg_metadata(&tagged).unwrap();
+        assert!(!stripped.windows(6).any(|w| w == b"Exif\0\0"));
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn test_exif_entries_are_well_formed() {
+        let tiff = build_exif(&sample_metadata()).unwrap();
+        let read_u16 = |at: usize| u16::from_be_bytes([tiff[at], tiff[at + 1]]);
+        let read_u32 =
+            |at: usize| u32::from_be_bytes([tiff[at], tiff[at + 1], tiff[at + 2], tiff[at + 3]]);
+
+        assert_eq!(&tiff[..4], b"MM\x00\x2A");
+        let ifd0 = read_u32(4) as usize;
+        let count = read_u16(ifd0) as usize;
+
+        // The last IFD0 entry points at the EXIF IFD
+        let last = ifd0 + 2 + (count - 1) * 12;
+        assert_eq!(read_u16(last), 0x8769);
+        let exif_ifd = read_u32(last + 8) as usize;
+        assert_eq!(read_u16(exif_ifd + 2), 0x9003);
+
+        // The Software tag points at a NUL-terminated string
+        let software = ifd0 + 2 + 12;
+        assert_eq!(read_u16(software), 0x0131);
+        let offset = read_u32(software + 8) as usize;
+        assert_eq!(&tiff[offset..offset + SOFTWARE.len()], SOFTWARE.as_bytes());
+    }
+
+    #[test]
+    fn test_track_capture_lookup() {
+        let path = "/tmp/bettershot_metadata_test.png";
+        track_capture(path, sample_metadata());
+
+        assert_eq!(capture_metadata(path), Some(sample_metadata()));
+        assert_eq!(capture_metadata("/tmp/unknown.png"), None);
+    }
+}
@@ -0,0 +1,592 @@
+//! Mockup frame module
+//!
+//! Wraps screenshots in browser, window and device frames. Frames are JSON
+//! definitions: a design canvas, the rectangle the screenshot fills, a
+//! corner mask for it and a list of shape, text and image layers. A few
+//! frames are bundled and users can add their own to the `frames` folder
+//! of the app's config directory.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::draw::{fill_circle, fill_rounded_rect_corners, mask_corners, Color, Corners, Rect};
+use crate::image::{load_image, MAX_EXPORT_DIMENSION};
+use crate::text::{draw_text, fit_text, system_font, TextAlign, TextStyle};
+use crate::utils::AppResult;
+
+/// Frames shipped with the app
+const BUNDLED_FRAMES: [&str; 5] = [
+    include_str!("../frames/browser-light.json"),
+    include_str!("../frames/browser-dark.json"),
+    include_str!("../frames/macos-window.json"),
+    include_str!("../frames/phone.json"),
+    include_str!("../frames/laptop.json"),
+];
+
+/// How a frame adapts to the size of the screenshot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameScaling {
+    /// Keep the screenshot at full size and stretch the frame parts that
+    /// span the content rectangle, like a nine-slice image
+    #[default]
+    Stretch,
+    /// Scale the whole frame to the screenshot's width, cropping the
+    /// screenshot to the content rectangle's aspect ratio
+    Fit,
+}
+
+/// A corner of a rectangle, as named in frame definitions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+fn all_corners() -> Vec<Corner> {
+    vec![
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ]
+}
+
+fn to_corners(corners: &[Corner]) -> Corners {
+    Corners {
+        top_left: corners.contains(&Corner::TopLeft),
+        top_right: corners.contains(&Corner::TopRight),
+        bottom_left: corners.contains(&Corner::BottomLeft),
+        bottom_right: corners.contains(&Corner::BottomRight),
+    }
+}
+
+/// Rounded corners cut from the screenshot
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ContentMask {
+    pub radius: f32,
+    #[serde(default = "all_corners")]
+    pub corners: Vec<Corner>,
+}
+
+/// What a layer draws
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum LayerShape {
+    Rect {
+        #[serde(flatten)]
+        rect: Rect,
+        #[serde(default)]
+        radius: f32,
+        #[serde(default = "all_corners")]
+        corners: Vec<Corner>,
+        color: Color,
+    },
+    Circle {
+        x: f32,
+        y: f32,
+        radius: f32,
+        color: Color,
+    },
+    /// Single line of text; `{url}` and `{title}` are replaced when rendering
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        color: Color,
+        text: String,
+        #[serde(default)]
+        align: TextAlign,
+        max_width: Option<f32>,
+    },
+    /// Image file, relative to the frame definition
+    Image {
+        #[serde(flatten)]
+        rect: Rect,
+        path: String,
+    },
+}
+
+/// One layer of a frame, drawn in order
+#[derive(Debug, Clone, Deserialize)]
+pub struct FrameLayer {
+    #[serde(flatten)]
+    pub shape: LayerShape,
+    /// Draw over the screenshot instead of under it, e.g. a camera notch
+    #[serde(default)]
+    pub over_content: bool,
+}
+
+/// Frame definition, in design units
+#[derive(Debug, Clone, Deserialize)]
+pub struct FrameDefinition {
+    pub id: String,
+    pub name: String,
+    pub width: f32,
+    pub height: f32,
+    /// Where the screenshot goes
+    pub content: Rect,
+    #[serde(default)]
+    pub content_mask: ContentMask,
+    #[serde(default)]
+    pub scaling: FrameScaling,
+    /// Part of the content rectangle that stretches with the screenshot,
+    /// so that frame details either side of it keep their size.
+    /// Defaults to the whole content rectangle
+    pub stretch_area: Option<Rect>,
+    #[serde(default)]
+    pub layers: Vec<FrameLayer>,
+}
+
+/// Frame definition and the folder its image layers are read from
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub definition: FrameDefinition,
+    pub base_dir: Option<PathBuf>,
+}
+
+/// Frame summary for the frontend
+#[derive(Serialize, Clone, Debug)]
+pub struct FrameInfo {
+    pub id: String,
+    pub name: String,
+    pub bundled: bool,
+}
+
+/// Text and sizing for a mockup render
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MockupOptions {
+    /// Replaces `{url}` in text layers
+    pub url: String,
+    /// Replaces `{title}` in text layers
+    pub title: String,
+    /// Size of stretched frames relative to design units, e.g. 2 for HiDPI captures
+    pub scale: Option<f32>,
+}
+
+/// Parse and check a frame definition
+pub fn parse_frame(json: &str, base_dir: Option<PathBuf>) -> AppResult<Frame> {
+    let definition: FrameDefinition =
+        serde_json::from_str(json).map_err(|e| format!("Invalid frame definition: {}", e))?;
+
+    let content = &definition.content;
+    if definition.width <= 0.0 || definition.height <= 0.0 {
+        return Err(format!("Frame '{}' has no size", definition.id));
+    }
+    if content.width <= 0.0
+        || content.height <= 0.0
+        || content.x < 0.0
+        || content.y < 0.0
+        || content.right() > definition.width
+        || content.bottom() > definition.height
+    {
+        return Err(format!(
+            "Content rectangle of frame '{}' must lie within the frame",
+            definition.id
+        ));
+    }
+
+    if let Some(band) = &definition.stretch_area {
+        if band.width <= 0.0
+            || band.height <= 0.0
+            || band.x < content.x
+            || band.y < content.y
+            || band.right() > content.right()
+            || band.bottom() > content.bottom()
+        {
+            return Err(format!(
+                "Stretch area of frame '{}' must lie within the content rectangle",
+                definition.id
+            ));
+        }
+    }
+
+    Ok(Frame {
+        definition,
+        base_dir,
+    })
+}
+
+/// Load bundled frames and any `.json` frames in a user folder
+/// User frames replace bundled frames with the same id
+pub fn load_frames(user_dir: Option<&Path>) -> Vec<Frame> {
+    let mut frames: Vec<Frame> = BUNDLED_FRAMES
+        .iter()
+        .filter_map(|json| parse_frame(json, None).ok())
+        .collect();
+
+    let Some(entries) = user_dir.and_then(|dir| fs::read_dir(dir).ok()) else {
+        return frames;
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("json"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let frame = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read frame: {}", e))
+            .and_then(|json| parse_frame(&json, path.parent().map(Path::to_path_buf)));
+        match frame {
+            Ok(frame) => {
+                frames.retain(|f| f.definition.id != frame.definition.id);
+                frames.push(frame);
+            }
+            Err(e) => eprintln!("Skipping frame {}: {}", path.display(), e),
+        }
+    }
+
+    frames
+}
+
+/// Summaries of the available frames
+pub fn frame_infos(frames: &[Frame]) -> Vec<FrameInfo> {
+    frames
+        .iter()
+        .map(|frame| FrameInfo {
+            id: frame.definition.id.clone(),
+            name: frame.definition.name.clone(),
+            bundled: frame.base_dir.is_none(),
+        })
+        .collect()
+}
+
+/// Maps design coordinates on one axis to output pixels
+///
+/// Coordinates before the stretch band are scaled, coordinates after it are
+/// also shifted by how much the band grew, and the band itself is stretched.
+#[derive(Debug, Clone, Copy)]
+struct Axis {
+    scale: f32,
+    band_start: f32,
+    band_length: f32,
+    growth: f32,
+}
+
+impl Axis {
+    fn map(&self, v: f32) -> f32 {
+        if v <= self.band_start {
+            v * self.scale
+        } else if v >= self.band_start + self.band_length {
+            v * self.scale + self.growth
+        } else {
+            self.band_start * self.scale
+                + (v - self.band_start) * (self.scale + self.growth / self.band_length)
+        }
+    }
+}
+
+/// Maps design coordinates to output pixels
+struct Layout {
+    scale: f32,
+    x: Axis,
+    y: Axis,
+    output: (u32, u32),
+    content_size: (u32, u32),
+}
+
+impl Layout {
+    fn new(frame: &FrameDefinition, screenshot: (u32, u32), scale: f32) -> AppResult<Self> {
+        let content = frame.content;
+        let band = frame.stretch_area.unwrap_or(content);
+        let (scale, content_size) = match frame.scaling {
+            FrameScaling::Stretch => (scale, screenshot),
+            FrameScaling::Fit => {
+                let scale = screenshot.0 as f32 / content.width;
+                let height = (content.height * scale).round().max(1.0) as u32;
+                (scale, (screenshot.0, height))
+            }
+        };
+
+        let axis = |band_start: f32, band_length: f32, design: f32, output: u32| {
+            let growth = output as f32 - design * scale;
+            if band_length * scale + growth <= 0.0 {
+                return Err(format!(
+                    "Screenshot is too small for frame '{}' at this scale",
+                    frame.id
+                ));
+            }
+            Ok(Axis {
+                scale,
+                band_start,
+                band_length,
+                growth,
+            })
+        };
+        let x = axis(band.x, band.width, content.width, content_size.0)?;
+        let y = axis(band.y, band.height, content.height, content_size.1)?;
+
+        let width = x.map(frame.width).round();
+        let height = y.map(frame.height).round();
+        if width > MAX_EXPORT_DIMENSION as f32 || height > MAX_EXPORT_DIMENSION as f32 {
+            return Err(format!(
+                "Mockup of {}x{} exceeds the maximum of {} pixels per side",
+                width, height, MAX_EXPORT_DIMENSION
+            ));
+        }
+
+        Ok(Self {
+            scale,
+            x,
+            y,
+            output: (width.max(1.0) as u32, height.max(1.0) as u32),
+            content_size,
+        })
+    }
+
+    fn rect(&self, rect: &Rect) -> Rect {
+        let x = self.x.map(rect.x);
+        let y = self.y.map(rect.y);
+        Rect::new(
+            x,
+            y,
+            self.x.map(rect.right()) - x,
+            self.y.map(rect.bottom()) - y,
+        )
+    }
+}
+
+/// Render a screenshot inside a frame
+pub fn render_mockup(
+    screenshot: &DynamicImage,
+    frame: &Frame,
+    options: &MockupOptions,
+) -> AppResult<RgbaImage> {
+    let definition = &frame.definition;
+    let scale = options.scale.unwrap_or(1.0).clamp(0.25, 8.0);
+    let layout = Layout::new(definition, (screenshot.width(), screenshot.height()), scale)?;
+
+    let mut canvas = RgbaImage::new(layout.output.0, layout.output.1);
+    for layer in definition.layers.iter().filter(|l| !l.over_content) {
+        draw_layer(&mut canvas, &layer.shape, &layout, frame, options)?;
+    }
+
+    let (content_width, content_height) = layout.content_size;
+    let mut content = if definition.scaling == FrameScaling::Fit {
+        screenshot
+            .resize_to_fill(content_width, content_height, FilterType::Lanczos3)
+            .to_rgba8()
+    } else {
+        screenshot.to_rgba8()
+    };
+    let mask = &definition.content_mask;
+    mask_corners(
+        &mut content,
+        mask.radius * layout.scale,
+        to_corners(&mask.corners),
+    );
+    imageops::overlay(
+        &mut canvas,
+        &content,
+        layout.x.map(definition.content.x).round() as i64,
+        layout.y.map(definition.content.y).round() as i64,
+    );
+
+    for layer in definition.layers.iter().filter(|l| l.over_content) {
+        draw_layer(&mut canvas, &layer.shape, &layout, frame, options)?;
+    }
+
+    Ok(canvas)
+}
+
+fn draw_layer(
+    canvas: &mut RgbaImage,
+    shape: &LayerShape,
+    layout: &Layout,
+    frame: &Frame,
+    options: &MockupOptions,
+) -> AppResult<()> {
+    match shape {
+        LayerShape::Rect {
+            rect,
+            radius,
+            corners,
+            color,
+        } => fill_rounded_rect_corners(
+            canvas,
+            &layout.rect(rect),
+            radius * layout.scale,
+            to_corners(corners),
+            color.0,
+        ),
+        LayerShape::Circle {
+            x,
+            y,
+            radius,
+            color,
+        } => fill_circle(
+            canvas,
+            layout.x.map(*x),
+            layout.y.map(*y),
+            radius * layout.scale,
+            color.0,
+        ),
+        LayerShape::Text {
+            x,
+            y,
+            size,
+            color,
+            text,
+            align,
+            max_width,
+        } => {
+            let Some(font) = system_font() else {
+                return Ok(());
+            };
+            let style = TextStyle {
+                size: size * layout.scale,
+                color: color.0,
+                align: *align,
+            };
+            let text = text
+                .replace("{url}", &options.url)
+                .replace("{title}", &options.title);
+            let text = match max_width {
+                Some(max_width) => {
+                    let width = layout.x.map(x + max_width) - layout.x.map(*x);
+                    fit_text(&font, style.size, &text, width)
+                }
+                None => text,
+            };
+            draw_text(
+                canvas,
+                &font,
+                &style,
+                layout.x.map(*x),
+                layout.y.map(*y),
+                &text,
+            );
+        }
+        LayerShape::Image { rect, path } => {
+            let base_dir = frame
+                .base_dir
+                .as_ref()
+                .ok_or("Image layers are only supported in frame files")?;
+            let image = load_image(&base_dir.join(path).to_string_lossy())?;
+            let rect = layout.rect(rect);
+            let image = image.resize_exact(
+                rect.width.round().max(1.0) as u32,
+                rect.height.round().max(1.0) as u32,
+                FilterType::Lanczos3,
+            );
+            imageops::overlay(
+                canvas,
+                &image.to_rgba8(),
+                rect.x.round() as i64,
+                rect.y.round() as i64,
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    fn screenshot(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, RED))
+    }
+
+    fn bundled(id: &str) -> Frame {
+        load_frames(None)
+            .into_iter()
+            .find(|f| f.definition.id == id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_bundled_frames_parse() {
+        let frames = load_frames(None);
+
+        assert_eq!(frames.len(), BUNDLED_FRAMES.len());
+        assert!(frame_infos(&frames).iter().all(|info| info.bundled));
+    }
+
+    #[test]
+    fn test_stretch_keeps_screenshot_size() {
+        let frame = bundled("browser-light");
+
+        let mockup =
+            render_mockup(&screenshot(300, 200), &frame, &MockupOptions::default()).unwrap();
+
+        // 52px toolbar above the screenshot, no other margins
+        assert_eq!(mockup.dimensions(), (300, 252));
+        assert_eq!(mockup.get_pixel(150, 150), &RED);
+        // Traffic lights keep their design size and position
+        assert_eq!(mockup.get_pixel(20, 26), &Rgba([0xFF, 0x5F, 0x57, 255]));
+        // Bottom corners are masked
+        assert_eq!(mockup.get_pixel(0, 251)[3], 0);
+    }
+
+    #[test]
+    fn test_stretch_scale_enlarges_chrome() {
+        let frame = bundled("browser-light");
+        let options = MockupOptions {
+            scale: Some(2.0),
+            ..Default::default()
+        };
+
+        let mockup = render_mockup(&screenshot(600, 400), &frame, &options).unwrap();
+
+        assert_eq!(mockup.dimensions(), (600, 504));
+        assert_eq!(mockup.get_pixel(40, 52), &Rgba([0xFF, 0x5F, 0x57, 255]));
+    }
+
+    #[test]
+    fn test_fit_scales_frame_and_crops_content() {
+        let frame = bundled("phone");
+
+        let mockup =
+            render_mockup(&screenshot(788, 1000), &frame, &MockupOptions::default()).unwrap();
+
+        // The frame is scaled by 788 / 394 = 2
+        assert_eq!(mockup.dimensions(), (860, 1800));
+        assert_eq!(mockup.get_pixel(430, 900), &RED);
+        // The notch is drawn over the screenshot
+        assert_eq!(mockup.get_pixel(430, 96), &Rgba([0, 0, 0, 255]));
+        assert_eq!(mockup.get_pixel(0, 0)[3], 0);
+    }
+
+    #[test]
+    fn test_user_frames_and_validation() {
+        let dir = std::env::temp_dir().join(format!("frames_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("border.json"),
+            r##"{"id": "border", "name": "Border", "width": 100, "height": 100,
+                "content": {"x": 10, "y": 10, "width": 80, "height": 80},
+                "layers": [{"type": "rect", "x": 0, "y": 0, "width": 100, "height": 100,
+                            "color": "#0000FF"}]}"##,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("broken.json"),
+            r#"{"id": "broken", "name": "Broken", "width": 10, "height": 10,
+                "content": {"x": 0, "y": 0, "width": 20, "height": 20}}"#,
+        )
+        .unwrap();
+
+        let frames = load_frames(Some(&dir));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let border = frames.iter().find(|f| f.definition.id == "border").unwrap();
+        assert!(frames.iter().all(|f| f.definition.id != "broken"));
+
+        let mockup = render_mockup(&screenshot(40, 30), border, &MockupOptions::default()).unwrap();
+        assert_eq!(mockup.dimensions(), (60, 50));
+        assert_eq!(mockup.get_pixel(5, 5), &Rgba([0, 0, 255, 255]));
+        assert_eq!(mockup.get_pixel(30, 25), &RED);
+    }
+}
@@ -0,0 +1,82 @@
+//! Operations module
+//!
+//! Edits described as JSON so the editor preview and headless exports
+//! run the same rendering code.
+
+use image::DynamicImage;
+use serde::Deserialize;
+
+use crate::adjust::{adjust_image, AdjustOptions};
+use crate::image::{transform_image, ImageTransform};
+use crate::magnifier::{magnify, MagnifierOptions};
+use crate::spotlight::{apply_spotlight, SpotlightOptions};
+use crate::utils::AppResult;
+
+/// One step of an edit, written as `{"op": "magnify", ...}` in JSON
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ImageOperation {
+    Transform(ImageTransform),
+    Magnify(MagnifierOptions),
+    Spotlight(SpotlightOptions),
+    Adjust(AdjustOptions),
+}
+
+/// Apply operations in order, each one seeing the result of the previous
+pub fn apply_operations(
+    img: &DynamicImage,
+    operations: &[ImageOperation],
+) -> AppResult<DynamicImage> {
+    let mut result = img.clone();
+    for operation in operations {
+        result = match operation {
+            ImageOperation::Transform(transform) => transform_image(&result, transform)?,
+            ImageOperation::Magnify(options) => {
+                DynamicImage::ImageRgba8(magnify(&result, options)?)
+            }
+            ImageOperation::Spotlight(options) => {
+                DynamicImage::ImageRgba8(apply_spotlight(&result, options)?)
+            }
+            ImageOperation::Adjust(options) => adjust_image(result, options),
+        };
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_operations_from_json_run_in_order() {
+        let json = r##"[
+            {"op": "magnify", "source": {"x": 0, "y": 0, "width": 4, "height": 4},
+             "inset_x": 30, "inset_y": 10, "zoom": 2, "shape": "rounded_rect",
+             "corner_radius": 0, "filter": "nearest", "border_width": 0,
+             "shadow_opacity": 0, "connector_width": 0},
+            {"op": "transform", "type": "rotate", "degrees": 90}
+        ]"##;
+        let operations: Vec<ImageOperation> = serde_json::from_str(json).unwrap();
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(40, 20, |x, y| {
+            if x < 4 && y < 4 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        }));
+
+        let result = apply_operations(&img, &operations).unwrap().to_rgba8();
+
+        // The 8x8 inset at (26, 6) ends up at (6, 26) after a clockwise quarter turn
+        assert_eq!(result.dimensions(), (20, 40));
+        assert_eq!(result.get_pixel(10, 30), &Rgba([255, 0, 0, 255]));
+        assert_eq!(result.get_pixel(10, 20), &Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_unknown_operation_is_rejected() {
+        let json = r#"[{"op": "sharpen"}]"#;
+        assert!(serde_json::from_str::<Vec<ImageOperation>>(json).is_err());
+    }
+}
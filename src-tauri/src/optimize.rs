@@ -0,0 +1,610 @@
+//! PNG optimisation module
+//!
+//! Re-encodes saved PNGs with the smallest colour type that represents the
+//! pixels exactly, tries several filter strategies and keeps the smallest
+//! result. Palette quantisation is available as an opt-in lossy step.
+
+use color_quant::NeuQuant;
+use image::{imageops, DynamicImage, RgbaImage};
+use png::chunk::ChunkType;
+use png::{AdaptiveFilterType, BitDepth, ColorType, Compression, FilterType};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::image::decode_with_limits;
+use crate::utils::{write_file_atomic, AppResult};
+
+/// PNG file signature
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Ancillary chunks that stay valid after the pixel data is re-encoded
+const PRESERVED_CHUNKS: [&[u8; 4]; 9] = [
+    b"tEXt", b"zTXt", b"iTXt", b"pHYs", b"iCCP", b"sRGB", b"gAMA", b"cHRM", b"eXIf",
+];
+
+/// How hard the optimiser searches for a smaller encoding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptimizationLevel {
+    /// Keep the encoder output as-is
+    #[default]
+    Off,
+    /// Colour type reduction with a single adaptive filter pass
+    Fast,
+    /// Adds an unfiltered pass, which usually wins for palette images
+    Balanced,
+    /// Tries every filter type
+    Max,
+}
+
+impl OptimizationLevel {
+    /// Filter strategies to try at this level
+    fn strategies(self) -> &'static [FilterStrategy] {
+        match self {
+            OptimizationLevel::Off => &[],
+            OptimizationLevel::Fast => &[FilterStrategy::Adaptive],
+            OptimizationLevel::Balanced => &[
+                FilterStrategy::Adaptive,
+                FilterStrategy::Fixed(FilterType::NoFilter),
+            ],
+            OptimizationLevel::Max => &[
+                FilterStrategy::Adaptive,
+                FilterStrategy::Fixed(FilterType::NoFilter),
+                FilterStrategy::Fixed(FilterType::Sub),
+                FilterStrategy::Fixed(FilterType::Up),
+                FilterStrategy::Fixed(FilterType::Avg),
+                FilterStrategy::Fixed(FilterType::Paeth),
+            ],
+        }
+    }
+}
+
+/// PNG optimisation options
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptimizeOptions {
+    pub level: OptimizationLevel,
+    /// Quantize images with more than 256 colours to a palette (lossy)
+    pub quantize: bool,
+    /// Apply Floyd-Steinberg dithering when quantizing
+    pub dither: bool,
+}
+
+impl OptimizeOptions {
+    /// Check if the optimisation stage should run at all
+    pub fn is_enabled(&self) -> bool {
+        self.level != OptimizationLevel::Off
+    }
+}
+
+/// Result of optimising a single file
+#[derive(Serialize, Clone, Debug)]
+pub struct OptimizeReport {
+    pub path: String,
+    pub original_bytes: u64,
+    pub optimized_bytes: u64,
+    pub bytes_saved: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FilterStrategy {
+    Adaptive,
+    Fixed(FilterType),
+}
+
+/// Raw pixel data laid out for a specific PNG colour type
+struct PngRaster {
+    color_type: ColorType,
+    bit_depth: BitDepth,
+    data: Vec<u8>,
+    palette: Option<Vec<u8>>,
+    trns: Option<Vec<u8>>,
+}
+
+/// Optimise an encoded PNG, returning the smallest encoding found
+///
+/// The original bytes are returned unchanged when no candidate is smaller.
+pub fn optimize_png(data: &[u8], options: &OptimizeOptions) -> AppResult<Vec<u8>> {
+    if !options.is_enabled() {
+        return Ok(data.to_vec());
+    }
+
+    let chunks = read_chunks(data)?;
+    let img = decode_with_limits(data, image::ImageFormat::Png)?;
+
+    // 16-bit and float images would lose precision when reduced to 8 bits
+    if !matches!(
+        img,
+        DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageRgb8(_)
+            | DynamicImage::ImageRgba8(_)
+    ) {
+        return Ok(data.to_vec());
+    }
+
+    let preserved = preserved_chunks(chunks);
+    // An RGB colour profile is invalid on a grayscale image
+    let allow_gray = !preserved.iter().any(|(kind, _)| &kind.0 == b"iCCP");
+
+    let rgba = img.to_rgba8();
+    let mut best = data.to_vec();
+
+    for raster in reduce_color_type(&rgba, allow_gray, options) {
+        for strategy in options.level.strategies() {
+            let encoded =
+                encode_raster(&raster, rgba.width(), rgba.height(), *strategy, &preserved)?;
+            if encoded.len() < best.len() {
+                best = encoded;
+            }
+        }
+    }
+
+    Ok(best)
+}
+
+/// Optimise a PNG file in place and report the bytes saved
+pub fn optimize_png_file(path: &str, options: &OptimizeOptions) -> AppResult<OptimizeReport> {
+    let original = fs::read(path).map_err(|e| format!("Failed to read image: {}", e))?;
+    let optimized = optimize_png(&original, options)?;
+
+    if optimized.len() < original.len() {
+        write_file_atomic(path, &optimized)?;
+    }
+
+    let original_bytes = original.len() as u64;
+    let optimized_bytes = original_bytes.min(optimized.len() as u64);
+
+    Ok(OptimizeReport {
+        path: path.to_string(),
+        original_bytes,
+        optimized_bytes,
+        bytes_saved: original_bytes - optimized_bytes,
+    })
+}
+
+/// Metadata and colour chunks that must survive re-encoding
+pub fn preserved_chunks(chunks: Vec<(ChunkType, &[u8])>) -> Vec<(ChunkType, &[u8])> {
+    chunks
+        .into_iter()
+        .filter(|(kind, _)| PRESERVED_CHUNKS.iter().any(|p| **p == kind.0))
+        .collect()
+}
+
+/// Split a PNG into its chunks, validating the signature
+pub fn read_chunks(data: &[u8]) -> AppResult<Vec<(ChunkType, &[u8])>> {
+    if data.len() < PNG_SIGNATURE.len() || data[..8] != PNG_SIGNATURE {
+        return Err("Invalid PNG signature".to_string());
+    }
+
+    let mut chunks = Vec::new();
+    let mut offset = PNG_SIGNATURE.len();
+
+    while offset + 12 <= data.len() {
+        let length = u32::from_be_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ]) as usize;
+        let kind = ChunkType([
+            data[offset + 4],
+            data[offset + 5],
+            data[offset + 6],
+            data[offset + 7],
+        ]);
+        let body_start = offset + 8;
+        let body_end = body_start
+            .checked_add(length)
+            .filter(|end| end + 4 <= data.len())
+            .ok_or("Truncated PNG chunk")?;
+
+        chunks.push((kind, &data[body_start..body_end]));
+        offset = body_end + 4;
+
+        if kind == png::chunk::IEND {
+            break;
+        }
+    }
+
+    Ok(chunks)
+}
+
+/// Build the candidate rasters worth encoding for this image
+fn reduce_color_type(
+    rgba: &RgbaImage,
+    allow_gray: bool,
+    options: &OptimizeOptions,
+) -> Vec<PngRaster> {
+    let pixels = rgba.as_raw();
+    let opaque = pixels.chunks_exact(4).all(|p| p[3] == 255);
+    let gray = allow_gray && pixels.chunks_exact(4).all(|p| p[0] == p[1] && p[1] == p[2]);
+
+    let truecolor = match (gray, opaque) {
+        (true, true) => PngRaster::plain(
+            ColorType::Grayscale,
+            pixels.chunks_exact(4).map(|p| p[0]).collect(),
+        ),
+        (true, false) => PngRaster::plain(
+            ColorType::GrayscaleAlpha,
+            pixels.chunks_exact(4).flat_map(|p| [p[0], p[3]]).collect(),
+        ),
+        (false, true) => PngRaster::plain(
+            ColorType::Rgb,
+            pixels
+                .chunks_exact(4)
+                .flat_map(|p| [p[0], p[1], p[2]])
+                .collect(),
+        ),
+        (false, false) => PngRaster::plain(ColorType::Rgba, pixels.to_vec()),
+    };
+
+    let mut candidates = vec![truecolor];
+
+    if let Some(indexed) = exact_palette(rgba) {
+        candidates.push(indexed);
+    } else if options.quantize {
+        candidates.push(quantized_palette(rgba, options.dither));
+    }
+
+    candidates
+}
+
+/// Build an indexed raster if the image has at most 256 distinct colours
+fn exact_palette(rgba: &RgbaImage) -> Option<PngRaster> {
+    let mut colors: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut order: Vec<[u8; 4]> = Vec::new();
+
+    for p in rgba.pixels() {
+        if let Entry::Vacant(entry) = colors.entry(p.0) {
+            if order.len() == 256 {
+                return None;
+            }
+            entry.insert(0);
+            order.push(p.0);
+        }
+    }
+
+    // Translucent entries first keeps the tRNS chunk as short as possible
+    order.sort_by_key(|c| c[3] == 255);
+    for (i, color) in order.iter().enumerate() {
+        colors.insert(*color, i as u8);
+    }
+
+    let indices: Vec<u8> = rgba.pixels().map(|p| colors[&p.0]).collect();
+    Some(PngRaster::indexed(rgba.width(), &indices, &order))
+}
+
+/// Quantize the image to a 256 colour palette
+fn quantized_palette(rgba: &RgbaImage, dither: bool) -> PngRaster {
+    let quantizer = NeuQuant::new(10, 256, rgba.as_raw());
+
+    let indices = if dither {
+        let mut dithered = rgba.clone();
+        imageops::dither(&mut dithered, &quantizer);
+        imageops::index_colors(&dithered, &quantizer)
+    } else {
+        imageops::index_colors(rgba, &quantizer)
+    };
+
+    let colors: Vec<[u8; 4]> = quantizer
+        .color_map_rgba()
+        .chunks_exact(4)
+        .map(|c| [c[0], c[1], c[2], c[3]])
+        .collect();
+
+    // Translucent entries first, as in exact_palette, so tRNS covers all of them
+    let mut order: Vec<usize> = (0..colors.len()).collect();
+    order.sort_by_key(|&i| colors[i][3] == 255);
+    let mut remap = vec![0u8; colors.len()];
+    for (new, &old) in order.iter().enumerate() {
+        remap[old] = new as u8;
+    }
+    let palette: Vec<[u8; 4]> = order.iter().map(|&i| colors[i]).collect();
+    let indices: Vec<u8> = indices
+        .as_raw()
+        .iter()
+        .map(|&i| remap[i as usize])
+        .collect();
+
+    PngRaster::indexed(rgba.width(), &indices, &palette)
+}
+
+impl PngRaster {
+    fn plain(color_type: ColorType, data: Vec<u8>) -> Self {
+        Self {
+            color_type,
+            bit_depth: BitDepth::Eight,
+            data,
+            palette: None,
+            trns: None,
+        }
+    }
+
+    /// Pack palette indices at the smallest bit depth that fits the palette
+    fn indexed(width: u32, indices: &[u8], palette: &[[u8; 4]]) -> Self {
+        let (bit_depth, bits) = match palette.len() {
+            0..=2 => (BitDepth::One, 1),
+            3..=4 => (BitDepth::Two, 2),
+            5..=16 => (BitDepth::Four, 4),
+            _ => (BitDepth::Eight, 8),
+        };
+
+        let data = if bits == 8 {
+            indices.to_vec()
+        } else {
+            let width = width as usize;
+            let per_byte = 8 / bits;
+            let row_bytes = width.div_ceil(per_byte);
+            let mut packed = Vec::with_capacity(row_bytes * (indices.len() / width.max(1)));
+            for row in indices.chunks(width.max(1)) {
+                for group in row.chunks(per_byte) {
+                    let mut byte = 0u8;
+                    for (i, index) in group.iter().enumerate() {
+                        byte |= index << (8 - bits * (i + 1));
+                    }
+                    packed.push(byte);
+                }
+            }
+            packed
+        };
+
+        let rgb: Vec<u8> = palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect();
+        let alpha: Vec<u8> = palette
+            .iter()
+            .map(|c| c[3])
+            .take_while(|a| *a != 255)
+            .collect();
+
+        Self {
+            color_type: ColorType::Indexed,
+            bit_depth,
+            data,
+            palette: Some(rgb),
+            trns: (!alpha.is_empty()).then_some(alpha),
+        }
+    }
+}
+
+/// Encode a raster with the given filter strategy
+fn encode_raster(
+    raster: &PngRaster,
+    width: u32,
+    height: u32,
+    strategy: FilterStrategy,
+    extra_chunks: &[(ChunkType, &[u8])],
+) -> AppResult<Vec<u8>> {
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(raster.color_type);
+        encoder.set_depth(raster.bit_depth);
+        encoder.set_compression(Compression::Best);
+        match strategy {
+            FilterStrategy::Adaptive => {
+                encoder.set_adaptive_filter(AdaptiveFilterType::Adaptive);
+            }
+            FilterStrategy::Fixed(filter) => {
+                encoder.set_adaptive_filter(AdaptiveFilterType::NonAdaptive);
+                encoder.set_filter(filter);
+            }
+        }
+        if let Some(palette) = &raster.palette {
+            encoder.set_palette(palette.clone());
+        }
+        if let Some(trns) = &raster.trns {
+            encoder.set_trns(trns.clone());
+        }
+
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| format!("Failed to write PNG header: {}", e))?;
+        writer
+            .write_image_data(&raster.data)
+            .map_err(|e| format!("Failed to write PNG data: {}", e))?;
+        writer
+            .finish()
+            .map_err(|e| format!("Failed to finish PNG: {}", e))?;
+    }
+    insert_chunks(&out, extra_chunks)
+}
+
+/// Insert ancillary chunks directly after the IHDR chunk
+///
+/// Every chunk we carry over is valid in that position, including the ones
+/// that must precede PLTE (iCCP, sRGB, gAMA, cHRM).
+pub fn insert_chunks(data: &[u8], chunks: &[(ChunkType, &[u8])]) -> AppResult<Vec<u8>> {
+    // Signature (8) + IHDR length, type, 13 byte body and CRC
+    const IHDR_END: usize = 8 + 4 + 4 + 13 + 4;
+
+    if data.len() < IHDR_END || data[12..16] != *b"IHDR" {
+        return Err("Invalid PNG: missing IHDR".to_string());
+    }
+
+    let extra: usize = chunks.iter().map(|(_, body)| body.len() + 12).sum();
+    let mut out = Vec::with_capacity(data.len() + extra);
+    out.extend_from_slice(&data[..IHDR_END]);
+
+    for (kind, body) in chunks {
+        let length = u32::try_from(body.len()).map_err(|_| "PNG chunk too large")?;
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(&kind.0);
+        crc.update(body);
+
+        out.extend_from_slice(&length.to_be_bytes());
+        out.extend_from_slice(&kind.0);
+        out.extend_from_slice(body);
+        out.extend_from_slice(&crc.finalize().to_be_bytes());
+    }
+
+    out.extend_from_slice(&data[IHDR_END..]);
+    Ok(out)
+}
+
+/// Drop every chunk of the given types from a PNG
+pub fn remove_chunks(data: &[u8], kinds: &[&[u8; 4]]) -> AppResult<Vec<u8>> {
+    let chunks = read_chunks(data)?;
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..PNG_SIGNATURE.len()]);
+
+    // Chunks are contiguous: length, type, body and CRC
+    let mut offset = PNG_SIGNATURE.len();
+    for (kind, body) in chunks {
+        let chunk_len = body.len() + 12;
+        if !kinds.iter().any(|k| **k == kind.0) {
+            out.extend_from_slice(&data[offset..offset + chunk_len]);
+        }
+        offset += chunk_len;
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageOutputFormat, Rgba};
+    use std::io::Cursor;
+
+    fn encode_default(img: &RgbaImage) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(img.clone())
+            .write_to(&mut out, ImageOutputFormat::Png)
+            .unwrap();
+        out.into_inner()
+    }
+
+    fn options(level: OptimizationLevel) -> OptimizeOptions {
+        OptimizeOptions {
+            level,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_optimize_is_lossless() {
+        let img = RgbaImage::from_fn(64, 48, |x, y| {
+            Rgba([(x * 4) as u8, (y * 5) as u8, ((x + y) * 2) as u8, 255])
+        });
+        let original = encode_default(&img);
+
+        let optimized = optimize_png(&original, &options(OptimizationLevel::Max)).unwrap();
+        let decoded = image::load_from_memory(&optimized).unwrap().to_rgba8();
+
+        assert!(optimized.len() <= original.len());
+        assert_eq!(decoded, img);
+    }
+
+    #[test]
+    fn test_optimize_reduces_few_colors_to_palette() {
+        let img = RgbaImage::from_fn(100, 100, |x, _| {
+            if x < 50 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 128])
+            }
+        });
+        let original = encode_default(&img);
+
+        let optimized = optimize_png(&original, &options(OptimizationLevel::Fast)).unwrap();
+        let decoded = image::load_from_memory(&optimized).unwrap().to_rgba8();
+
+        assert!(optimized.len() < original.len());
+        assert_eq!(decoded, img);
+    }
+
+    #[test]
+    fn test_optimize_off_returns_original() {
+        let img = RgbaImage::from_pixel(10, 10, Rgba([1, 2, 3, 255]));
+        let original = encode_default(&img);
+
+        let optimized = optimize_png(&original, &options(OptimizationLevel::Off)).unwrap();
+        assert_eq!(optimized, original);
+    }
+
+    #[test]
+    fn test_optimize_preserves_text_chunks() {
+        let mut original = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut original, 4, 4);
+            encoder.set_color(ColorType::Rgba);
+            encoder.set_depth(BitDepth::Eight);
+            encoder
+                .add_itxt_chunk("Software".to_string(), "BetterShot".to_string())
+                .unwrap();
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[200u8; 64]).unwrap();
+        }
+
+        let optimized = optimize_png(&original, &options(OptimizationLevel::Fast)).unwrap();
+        let chunks = read_chunks(&optimized).unwrap();
+
+        assert!(chunks.iter().any(|(kind, _)| &kind.0 == b"iTXt"));
+    }
+
+    #[test]
+    fn test_quantize_limits_palette() {
+        // Noisy pixels compress poorly as truecolor but fit a palette well
+        let mut seed = 0x2545_f491_u32;
+        let img = RgbaImage::from_fn(64, 64, |_, _| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let [r, g, b, _] = seed.to_le_bytes();
+            Rgba([r, g, b, 255])
+        });
+        let original = encode_default(&img);
+
+        let opts = OptimizeOptions {
+            level: OptimizationLevel::Fast,
+            quantize: true,
+            dither: true,
+        };
+        let optimized = optimize_png(&original, &opts).unwrap();
+        let chunks = read_chunks(&optimized).unwrap();
+
+        assert!(optimized.len() < original.len());
+        assert!(chunks.iter().any(|(kind, _)| &kind.0 == b"PLTE"));
+    }
+
+    #[test]
+    fn test_quantize_keeps_translucency() {
+        // Noisy colours, opaque on the left and fading out on the right
+        let mut seed = 0x1b87_3593_u32;
+        let img = RgbaImage::from_fn(128, 128, |x, _| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let [r, g, b, _] = seed.to_le_bytes();
+            Rgba([r, g, b, 255 - (x.saturating_sub(64) * 4) as u8])
+        });
+        let original = encode_default(&img);
+
+        let opts = OptimizeOptions {
+            level: OptimizationLevel::Fast,
+            quantize: true,
+            dither: false,
+        };
+        let optimized = optimize_png(&original, &opts).unwrap();
+        let decoded = image::load_from_memory(&optimized).unwrap().to_rgba8();
+
+        assert!(read_chunks(&optimized)
+            .unwrap()
+            .iter()
+            .any(|(kind, _)| &kind.0 == b"tRNS"));
+        // Quantizing shifts alpha a little, but translucent pixels never turn opaque
+        let opaque = img
+            .pixels()
+            .zip(decoded.pixels())
+            .filter(|(a, b)| a[3] < 160 && b[3] == 255)
+            .count();
+        assert_eq!(opaque, 0);
+    }
+
+    #[test]
+    fn test_read_chunks_rejects_non_png() {
+        assert!(read_chunks(b"GIF89a").is_err());
+    }
+}
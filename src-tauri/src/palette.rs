@@ -0,0 +1,332 @@
+//! Palette module
+//!
+//! Finds the main colours of an image with median cut, and draws them as a
+//! strip of swatches under an export.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::draw::Color;
+use crate::image::MAX_EXPORT_DIMENSION;
+use crate::text::{draw_text, system_font, text_width, TextAlign, TextStyle};
+use crate::utils::AppResult;
+
+/// Longest side of the copy the palette is computed from
+const SAMPLE_SIZE: u32 = 256;
+
+/// One palette colour and the share of the image it stands for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaletteColor {
+    pub color: Rgba<u8>,
+    /// Fraction of opaque pixels, from 0 to 1
+    pub coverage: f32,
+}
+
+/// Palette colour as returned to the webview
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PaletteEntry {
+    /// `#RRGGBB`
+    pub hex: String,
+    pub rgb: [u8; 3],
+    /// Share of opaque pixels, from 0 to 100
+    pub coverage: f32,
+}
+
+impl From<PaletteColor> for PaletteEntry {
+    fn from(entry: PaletteColor) -> Self {
+        Self {
+            hex: String::from(Color(entry.color)),
+            rgb: [entry.color[0], entry.color[1], entry.color[2]],
+            coverage: (entry.coverage * 1000.0).round() / 10.0,
+        }
+    }
+}
+
+/// Swatch strip added under exported images
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaletteStripOptions {
+    /// Number of swatches
+    pub count: usize,
+    /// Strip height as a fraction of the image width
+    pub height: f32,
+    /// Size swatches by coverage instead of equally
+    pub proportional: bool,
+    /// Write each swatch's hex value on it
+    pub labels: bool,
+}
+
+impl Default for PaletteStripOptions {
+    fn default() -> Self {
+        Self {
+            count: 5,
+            height: 0.08,
+            proportional: false,
+            labels: true,
+        }
+    }
+}
+
+/// Up to `count` main colours, most common first
+///
+/// Transparent pixels are ignored, so a fully transparent image has no palette.
+pub fn extract_palette(img: &DynamicImage, count: usize) -> Vec<PaletteColor> {
+    let sample = match img.width() > SAMPLE_SIZE || img.height() > SAMPLE_SIZE {
+        true => img.resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle),
+        false => img.clone(),
+    };
+    let pixels: Vec<[u8; 3]> = sample
+        .to_rgba8()
+        .pixels()
+        .filter(|p| p[3] >= 128)
+        .map(|p| [p[0], p[1], p[2]])
+        .collect();
+    if pixels.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    let total = pixels.len() as f32;
+    let mut boxes = vec![pixels];
+    while boxes.len() < count {
+        // Split the box whose widest channel spans the most, weighted by size
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, pixels)| pixels.len() > 1)
+            .map(|(i, pixels)| {
+                let (channel, range) = widest_channel(pixels);
+                (i, channel, range as usize * pixels.len())
+            })
+            .filter(|&(_, _, score)| score > 0)
+            .max_by_key(|&(_, _, score)| score)
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break;
+        };
+
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_unstable_by_key(|p| p[channel]);
+        let upper = pixels.split_off(pixels.len() / 2);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+
+    // Halves of a solid area end up with the same colour, so merge them
+    let mut palette: Vec<PaletteColor> = Vec::with_capacity(boxes.len());
+    for pixels in &boxes {
+        let entry = average(pixels, total);
+        match palette.iter_mut().find(|p| p.color == entry.color) {
+            Some(existing) => existing.coverage += entry.coverage,
+            None => palette.push(entry),
+        }
+    }
+    palette.sort_by(|a, b| b.coverage.total_cmp(&a.coverage));
+    palette
+}
+
+/// Return a copy of the image with a strip of its main colours underneath
+///
+/// Fully transparent images have no palette and are returned unchanged.
+pub fn append_palette_strip(
+    img: &DynamicImage,
+    options: &PaletteStripOptions,
+) -> AppResult<DynamicImage> {
+    let palette = extract_palette(img, options.count.max(1));
+    if palette.is_empty() {
+        return Ok(img.clone());
+    }
+
+    let (width, height) = (img.width(), img.height());
+    let strip_height = ((width as f32 * options.height.max(0.0)).round() as u32).max(1);
+    let total_height = height as u64 + strip_height as u64;
+    if total_height > MAX_EXPORT_DIMENSION as u64 {
+        return Err(format!(
+            "Palette strip makes the image {}x{}, over the maximum of {} pixels per side",
+            width, total_height, MAX_EXPORT_DIMENSION
+        ));
+    }
+    let mut canvas = RgbaImage::new(width, height + strip_height);
+    imageops::replace(&mut canvas, &img.to_rgba8(), 0, 0);
+
+    let mut start = 0.0;
+    let mut edges = Vec::with_capacity(palette.len());
+    for (i, entry) in palette.iter().enumerate() {
+        let end = match options.proportional {
+            true => start + entry.coverage,
+            false => (i + 1) as f32 / palette.len() as f32,
+        };
+        let x0 = (start * width as f32).round() as u32;
+        let x1 = match i + 1 == palette.len() {
+            true => width,
+            false => (end * width as f32).round() as u32,
+        };
+        edges.push((x0, x1));
+        for x in x0..x1 {
+            for y in height..height + strip_height {
+                canvas.put_pixel(x, y, entry.color);
+            }
+        }
+        start = end;
+    }
+
+    if options.labels {
+        if let Some(font) = system_font() {
+            let size = strip_height as f32 * 0.28;
+            for (entry, (x0, x1)) in palette.iter().zip(edges) {
+                let label = String::from(Color(entry.color));
+                // Skip labels that don't fit their swatch rather than overlap
+                if text_width(&font, size, &label) > (x1 - x0) as f32 * 0.9 {
+                    continue;
+                }
+                let style = TextStyle {
+                    size,
+                    color: label_color(entry.color),
+                    align: TextAlign::Center,
+                };
+                let y = height as f32 + (strip_height as f32 - size) / 2.0;
+                draw_text(
+                    &mut canvas,
+                    &font,
+                    &style,
+                    (x0 + x1) as f32 / 2.0,
+                    y,
+                    &label,
+                );
+            }
+        }
+    }
+
+    Ok(DynamicImage::ImageRgba8(canvas))
+}
+
+/// Black or white, whichever reads better on a colour
+fn label_color(color: Rgba<u8>) -> Rgba<u8> {
+    let luma = 0.2126 * color[0] as f32 + 0.7152 * color[1] as f32 + 0.0722 * color[2] as f32;
+    match luma > 140.0 {
+        true => Rgba([0, 0, 0, 255]),
+        false => Rgba([255, 255, 255, 255]),
+    }
+}
+
+/// Mean colour of a box of pixels
+fn average(pixels: &[[u8; 3]], total: f32) -> PaletteColor {
+    let mut sum = [0u64; 3];
+    for p in pixels {
+        for c in 0..3 {
+            sum[c] += p[c] as u64;
+        }
+    }
+    let n = pixels.len() as u64;
+    let mean = |c: usize| ((sum[c] + n / 2) / n) as u8;
+    PaletteColor {
+        color: Rgba([mean(0), mean(1), mean(2), 255]),
+        coverage: pixels.len() as f32 / total,
+    }
+}
+
+/// Channel with the largest spread and that spread
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let (min, max) = pixels.iter().fold((u8::MAX, u8::MIN), |(min, max), p| {
+                (min.min(p[c]), max.max(p[c]))
+            });
+            (c, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_finds_colours_by_coverage() {
+        let palette = extract_palette(&two_colours(), 4);
+
+        // Solid regions can't be split further
+        assert_eq!(palette.len(), 2);
+        assert_eq!(palette[0].color, Rgba([20, 40, 200, 255]));
+        assert!((palette[0].coverage - 0.75).abs() < 0.01);
+        assert_eq!(palette[1].color, Rgba([250, 140, 10, 255]));
+    }
+
+    fn two_colours() -> DynamicImage {
+        // Three quarters blue, one quarter orange
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(40, 40, |x, _| {
+            if x < 30 {
+                Rgba([20, 40, 200, 255])
+            } else {
+                Rgba([250, 140, 10, 255])
+            }
+        }))
+    }
+
+    #[test]
+    fn test_entries_use_hex_and_percent() {
+        let entry = PaletteEntry::from(PaletteColor {
+            color: Rgba([16, 32, 48, 255]),
+            coverage: 0.7543,
+        });
+
+        assert_eq!(entry.hex, "#102030");
+        assert_eq!(entry.rgb, [16, 32, 48]);
+        assert_eq!(entry.coverage, 75.4);
+    }
+
+    #[test]
+    fn test_palette_strip() {
+        let options = PaletteStripOptions {
+            height: 0.25,
+            labels: false,
+            ..Default::default()
+        };
+
+        let equal = append_palette_strip(&two_colours(), &options)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(equal.dimensions(), (40, 50));
+        assert_eq!(equal.get_pixel(35, 5), &Rgba([250, 140, 10, 255]));
+        assert_eq!(equal.get_pixel(19, 45), &Rgba([20, 40, 200, 255]));
+        assert_eq!(equal.get_pixel(20, 45), &Rgba([250, 140, 10, 255]));
+
+        let proportional = PaletteStripOptions {
+            proportional: true,
+            ..options
+        };
+        let sized = append_palette_strip(&two_colours(), &proportional)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(sized.get_pixel(29, 45), &Rgba([20, 40, 200, 255]));
+        assert_eq!(sized.get_pixel(30, 45), &Rgba([250, 140, 10, 255]));
+
+        let transparent = DynamicImage::ImageRgba8(RgbaImage::new(10, 10));
+        let unchanged = append_palette_strip(&transparent, &options).unwrap();
+        assert_eq!(unchanged, transparent);
+    }
+
+    #[test]
+    fn test_palette_strip_size_is_capped() {
+        let tall = PaletteStripOptions {
+            height: 1e9,
+            labels: false,
+            ..Default::default()
+        };
+
+        assert!(append_palette_strip(&two_colours(), &tall).is_err());
+    }
+
+    #[test]
+    fn test_ignores_transparent_pixels() {
+        let mut img = RgbaImage::new(10, 10);
+        assert!(extract_palette(&DynamicImage::ImageRgba8(img.clone()), 3).is_empty());
+
+        img.put_pixel(0, 0, Rgba([1, 2, 3, 255]));
+        let palette = extract_palette(&DynamicImage::ImageRgba8(img), 3);
+        assert_eq!(palette.len(), 1);
+        assert_eq!(palette[0].coverage, 1.0);
+    }
+}
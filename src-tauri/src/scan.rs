@@ -0,0 +1,216 @@
+//! Code scanning module
+//!
+//! Finds and decodes QR codes in screenshots, such as links shown on slides.
+
+use image::{imageops, DynamicImage, GrayImage};
+use serde::Serialize;
+
+use crate::image::CropRegion;
+use crate::utils::AppResult;
+
+/// Corner of a detected code, in image pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CodePoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A decoded code and where it was found
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScannedCode {
+    /// Symbology, currently always "qr"
+    pub kind: String,
+    pub payload: String,
+    /// The code's four corners
+    pub polygon: Vec<CodePoint>,
+}
+
+/// Decode every QR code in an image, or in a region of it
+///
+/// Codes are ordered top to bottom, then left to right.
+pub fn scan_codes(img: &DynamicImage, region: Option<CropRegion>) -> AppResult<Vec<ScannedCode>> {
+    let (offset_x, offset_y, mut grey) = match region {
+        Some(region) => {
+            let region = CropRegion::clamped(
+                region.x,
+                region.y,
+                region.width,
+                region.height,
+                img.width(),
+                img.height(),
+            );
+            if !region.is_valid() {
+                return Err(format!(
+                    "Invalid scan region: x={}, y={}, w={}, h={} (image: {}x{})",
+                    region.x,
+                    region.y,
+                    region.width,
+                    region.height,
+                    img.width(),
+                    img.height()
+                ));
+            }
+            let cropped = img.crop_imm(region.x, region.y, region.width, region.height);
+            (region.x as i32, region.y as i32, cropped.to_luma8())
+        }
+        None => (0, 0, img.to_luma8()),
+    };
+
+    let mut codes = decode_grids(&grey);
+    if codes.is_empty() {
+        // Dark themes draw light modules on a dark background
+        imageops::invert(&mut grey);
+        codes = decode_grids(&grey);
+    }
+
+    for point in codes.iter_mut().flat_map(|code| code.polygon.iter_mut()) {
+        point.x += offset_x;
+        point.y += offset_y;
+    }
+    codes.sort_by_key(|code| {
+        let top = code.polygon.iter().map(|p| p.y).min().unwrap_or(0);
+        let left = code.polygon.iter().map(|p| p.x).min().unwrap_or(0);
+        (top, left)
+    });
+
+    Ok(codes)
+}
+
+/// Find QR grids in a greyscale image and decode the readable ones
+fn decode_grids(grey: &GrayImage) -> Vec<ScannedCode> {
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+        grey.width() as usize,
+        grey.height() as usize,
+        |x, y| grey.get_pixel(x as u32, y as u32)[0],
+    );
+
+    // Grids that look like codes but don't decode are skipped
+    prepared
+        .detect_grids()
+        .into_iter()
+        .filter_map(|grid| {
+            let (_, payload) = grid.decode().ok()?;
+            Some(ScannedCode {
+                kind: "qr".to_string(),
+                payload,
+                polygon: grid
+                    .bounds
+                    .iter()
+                    .map(|p| CodePoint { x: p.x, y: p.y })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    /// Version 1 QR code for "https://qr.test", without its quiet zone
+    const FIXTURE: [&str; 21] = [
+        "#######..#..#.#######",
+        "#.....#.##.#..#.....#",
+        "#.###.#.##.##.#.###.#",
+        "#.###.#..#....#.###.#",
+        "#.###.#.#..#..#.###.#",
+        "#.....#.#..#..#.....#",
+        "#######.#.#.#.#######",
+        "........###.#........",
+        "##.#..##.####.###.##.",
+        ".###...##.#.#.###...#",
+        "..#...##.####.....#.#",
+        "#.##.#..##.#..#.##.##",
+        ".###.##....###.#.#...",
+        "........##.##..#....#",
+        "#######.#.#.##..####.",
+        "#.....#..#.#...##...#",
+        "#.###.#..#.##...##...",
+        "#.###.#.##.####.#..##",
+        "#.###.#..#.##...#.#.#",
+        "#.....#.#.###........",
+        "#######.##....#.#..#.",
+    ];
+
+    /// Draw the fixture with 4px modules, its top-left corner at (40, 24)
+    fn fixture_image(dark: Rgba<u8>, light: Rgba<u8>) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(180, 140, |x, y| {
+            let module = |v: u32, origin: u32| v.checked_sub(origin).map(|v| v / 4);
+            match (module(x, 40), module(y, 24)) {
+                (Some(mx), Some(my)) if mx < 21 && my < 21 => {
+                    match FIXTURE[my as usize].as_bytes()[mx as usize] {
+                        b'#' => dark,
+                        _ => light,
+                    }
+                }
+                _ => light,
+            }
+        }))
+    }
+
+    fn fixture_corners() -> Vec<CodePoint> {
+        [(40, 24), (128, 24), (128, 112), (40, 112)]
+            .into_iter()
+            .map(|(x, y)| CodePoint { x, y })
+            .collect()
+    }
+
+    #[test]
+    fn test_decodes_code_with_its_corners() {
+        let img = fixture_image(Rgba([0, 0, 0, 255]), Rgba([255; 4]));
+
+        let codes = scan_codes(&img, None).unwrap();
+
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].kind, "qr");
+        assert_eq!(codes[0].payload, "https://qr.test");
+        assert_eq!(codes[0].polygon, fixture_corners());
+    }
+
+    #[test]
+    fn test_decodes_light_code_on_dark_background() {
+        let img = fixture_image(Rgba([235, 235, 235, 255]), Rgba([24, 24, 28, 255]));
+
+        let codes = scan_codes(&img, None).unwrap();
+
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].payload, "https://qr.test");
+    }
+
+    #[test]
+    fn test_region_corners_are_in_image_coordinates() {
+        let img = fixture_image(Rgba([0, 0, 0, 255]), Rgba([255; 4]));
+        let region = CropRegion {
+            x: 20,
+            y: 10,
+            width: 140,
+            height: 120,
+        };
+
+        let codes = scan_codes(&img, Some(region)).unwrap();
+
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].polygon, fixture_corners());
+    }
+
+    #[test]
+    fn test_blank_image_has_no_codes() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, Rgba([255; 4])));
+
+        assert!(scan_codes(&img, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_empty_region_is_an_error() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(32, 32));
+        let region = CropRegion {
+            x: 8,
+            y: 8,
+            width: 0,
+            height: 10,
+        };
+
+        assert!(scan_codes(&img, Some(region)).is_err());
+    }
+}
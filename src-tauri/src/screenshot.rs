@@ -1,10 +1,15 @@
 //! Screenshot capture module
 
 use serde::Serialize;
+use std::fs;
 use std::path::PathBuf;
-use xcap::Monitor;
+use std::sync::Mutex;
+use xcap::{Monitor, Window};
 
-use crate::utils::{ensure_dir, generate_filename_with_id, AppResult};
+use crate::color::embed_icc_profile;
+use crate::image::ExportFormat;
+use crate::metadata::{track_capture, CaptureMetadata, MonitorInfo, WindowInfo};
+use crate::utils::{ensure_dir, generate_filename_with_id, write_file_atomic, AppResult};
 
 /// Represents a captured monitor screenshot with geometry info
 #[derive(Serialize, Clone, Debug)]
@@ -59,7 +64,32 @@ fn capture_single_monitor(monitor: &Monitor, save_path: &PathBuf) -> AppResult<M
         .save(&screenshot_path)
         .map_err(|e| format!("Failed to save screenshot: {}", e))?;
 
-    // Get monitor geometry
+    let info = monitor_info(monitor)?;
+    let path = screenshot_path.to_string_lossy().into_owned();
+    if let Err(e) = tag_display_profile(&path, info.x, info.y) {
+        eprintln!("Failed to attach display profile to {}: {}", path, e);
+    }
+
+    let mut metadata = CaptureMetadata::now("monitor");
+    metadata.monitor = Some(info.clone());
+    track_capture(&path, metadata);
+
+    Ok(MonitorShot {
+        id: info.id,
+        x: info.x,
+        y: info.y,
+        width: info.width,
+        height: info.height,
+        scale_factor: info.scale_factor,
+        path,
+    })
+}
+
+/// Get monitor geometry
+fn monitor_info(monitor: &Monitor) -> AppResult<MonitorInfo> {
+    let id = monitor
+        .id()
+        .map_err(|e| format!("Failed to get monitor id: {}", e))?;
     let x = monitor
         .x()
         .map_err(|e| format!("Failed to get monitor x: {}", e))?;
@@ -76,17 +106,145 @@ fn capture_single_monitor(monitor: &Monitor, save_path: &PathBuf) -> AppResult<M
         .scale_factor()
         .map_err(|e| format!("Failed to get monitor scale factor: {}", e))?;
 
-    Ok(MonitorShot {
-        id: monitor_id,
+    Ok(MonitorInfo {
+        id,
         x,
         y,
         width,
         height,
         scale_factor,
-        path: screenshot_path.to_string_lossy().into_owned(),
     })
 }
 
+/// Get geometry of the monitor containing a point
+pub fn monitor_info_at(x: i32, y: i32) -> AppResult<MonitorInfo> {
+    let monitor =
+        Monitor::from_point(x, y).map_err(|e| format!("Failed to find monitor: {}", e))?;
+    monitor_info(&monitor)
+}
+
+/// Get geometry of the primary monitor
+pub fn primary_monitor_info() -> AppResult<MonitorInfo> {
+    let monitors = Monitor::all().map_err(|e| format!("Failed to get monitors: {}", e))?;
+
+    let primary = monitors
+        .iter()
+        .find(|m| m.is_primary().unwrap_or(false))
+        .or_else(|| monitors.first())
+        .ok_or("No monitors available")?;
+
+    monitor_info(primary)
+}
+
+/// Find the topmost visible window containing a point
+pub fn window_info_at(x: i32, y: i32) -> AppResult<WindowInfo> {
+    let windows = Window::all().map_err(|e| format!("Failed to get windows: {}", e))?;
+
+    let contains = |w: &Window| -> bool {
+        let (Ok(wx), Ok(wy), Ok(width), Ok(height)) = (w.x(), w.y(), w.width(), w.height()) else {
+            return false;
+        };
+        !w.is_minimized().unwrap_or(true)
+            && x >= wx
+            && y >= wy
+            && x < wx + width as i32
+            && y < wy + height as i32
+    };
+
+    let window = windows
+        .iter()
+        .filter(|w| contains(w))
+        .max_by_key(|w| w.z().unwrap_or(i32::MIN))
+        .ok_or("No window found at point")?;
+
+    Ok(WindowInfo {
+        title: window.title().unwrap_or_default(),
+        app_name: window.app_name().unwrap_or_default(),
+    })
+}
+
+/// Get the scale factor of the primary monitor (e.g. 2.0 on Retina displays)
+pub fn primary_scale_factor() -> AppResult<f32> {
+    primary_monitor_info().map(|info| info.scale_factor)
+}
+
+/// Get the scale factor of the monitor containing a point
+pub fn scale_factor_at(x: i32, y: i32) -> AppResult<f32> {
+    let monitor =
+        Monitor::from_point(x, y).map_err(|e| format!("Failed to find monitor: {}", e))?;
+
+    monitor
+        .scale_factor()
+        .map_err(|e| format!("Failed to get monitor scale factor: {}", e))
+}
+
+/// Colour profiles already read, keyed by display origin
+static DISPLAY_PROFILES: Mutex<Vec<((i32, i32), Option<Vec<u8>>)>> = Mutex::new(Vec::new());
+
+/// Get the colour profile of the display whose origin is at a point, if it has one
+///
+/// Each display is only read once; later captures reuse the cached profile.
+pub fn display_icc_profile(x: i32, y: i32) -> AppResult<Option<Vec<u8>>> {
+    if let Ok(profiles) = DISPLAY_PROFILES.lock() {
+        if let Some((_, profile)) = profiles.iter().find(|(origin, _)| *origin == (x, y)) {
+            return Ok(profile.clone());
+        }
+    }
+
+    let profile = read_display_profile(x, y)?;
+    if let Ok(mut profiles) = DISPLAY_PROFILES.lock() {
+        profiles.push(((x, y), profile.clone()));
+    }
+    Ok(profile)
+}
+
+/// Read a display's colour profile from a one-pixel capture
+///
+/// screencapture tags its output with the display's profile, so this avoids
+/// linking ColorSync.
+#[cfg(target_os = "macos")]
+fn read_display_profile(x: i32, y: i32) -> AppResult<Option<Vec<u8>>> {
+    use crate::color::read_icc_profile;
+    use crate::utils::generate_filename;
+    use std::process::Command;
+
+    let path = std::env::temp_dir().join(generate_filename("display_profile", "png")?);
+    let status = Command::new("screencapture")
+        .arg("-x")
+        .arg("-R")
+        .arg(format!("{},{},1,1", x, y))
+        .arg(&path)
+        .status()
+        .map_err(|e| format!("Failed to run screencapture: {}", e))?;
+
+    if !status.success() {
+        return Err("Failed to read display colour profile".to_string());
+    }
+
+    let profile = read_icc_profile(&path.to_string_lossy());
+    let _ = fs::remove_file(&path);
+    profile
+}
+
+/// Display profiles are only read on macOS
+#[cfg(not(target_os = "macos"))]
+fn read_display_profile(_x: i32, _y: i32) -> AppResult<Option<Vec<u8>>> {
+    Ok(None)
+}
+
+/// Attach the colour profile of the display at a point to a saved capture
+///
+/// xcap and the screenshots plugin return the display's pixels untagged.
+fn tag_display_profile(path: &str, x: i32, y: i32) -> AppResult<()> {
+    let Some(profile) = display_icc_profile(x, y)? else {
+        return Ok(());
+    };
+
+    let data = fs::read(path).map_err(|e| format!("Failed to read screenshot: {}", e))?;
+    let tagged = embed_icc_profile(data, ExportFormat::Png, &profile)?;
+    write_file_atomic(path, &tagged)
+}
+
 /// Capture primary monitor using the screenshots plugin
 pub async fn capture_primary_monitor(app_handle: tauri::AppHandle) -> AppResult<PathBuf> {
     use tauri_plugin_screenshots::{get_monitor_screenshot, get_screenshotable_monitors};
@@ -105,5 +263,11 @@ pub async fn capture_primary_monitor(app_handle: tauri::AppHandle) -> AppResult<
         .await
         .map_err(|e| format!("Failed to capture screenshot: {}", e))?;
 
+    // The primary display's origin is always (0, 0)
+    let path = screenshot_path.to_string_lossy();
+    if let Err(e) = tag_display_profile(&path, 0, 0) {
+        eprintln!("Failed to attach display profile to {}: {}", path, e);
+    }
+
     Ok(screenshot_path)
 }
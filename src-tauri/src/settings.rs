@@ -0,0 +1,108 @@
+//! Persisted settings module
+//!
+//! The frontend owns `settings.json` through the store plugin. The backend
+//! only reads the keys that change how images are exported.
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+use crate::canvas::CanvasPreset;
+use crate::dedupe::DuplicateWarning;
+use crate::image::ExportOptions;
+use crate::optimize::OptimizeOptions;
+use crate::shadow::ShadowOptions;
+use crate::trim::AutoTrimOptions;
+use crate::watermark::WatermarkOptions;
+
+/// Store file shared with the frontend
+const SETTINGS_STORE: &str = "settings.json";
+
+/// Export settings persisted in the settings store
+#[derive(Debug, Clone, Default)]
+pub struct ExportSettings {
+    /// Optimisation applied to every PNG after it is saved
+    pub png_optimization: OptimizeOptions,
+    /// Default format, quality and resize step for saved images
+    pub export_options: ExportOptions,
+    /// Remove all capture metadata from saved files, for external sharing
+    pub strip_metadata: bool,
+    /// Write capture metadata to a `.json` file next to each saved image
+    pub metadata_sidecar: bool,
+    /// Warn when a save looks like one from the last few minutes
+    pub duplicate_warning: DuplicateWarning,
+    /// Trim uniform borders from region and window captures
+    pub auto_trim: AutoTrimOptions,
+    /// Remove or normalize the system shadow on window captures
+    pub window_shadow: ShadowOptions,
+    /// Text or logo stamped on every export
+    pub watermark: WatermarkOptions,
+    /// User-defined canvas sizes, added to the built-in presets
+    pub canvas_presets: Vec<CanvasPreset>,
+}
+
+impl ExportSettings {
+    /// Load export settings, falling back to defaults when the store is unavailable
+    pub fn load(app_handle: &AppHandle) -> Self {
+        let store = match app_handle.store(SETTINGS_STORE) {
+            Ok(store) => store,
+            Err(e) => {
+                eprintln!("Failed to load settings store: {}", e);
+                return Self::default();
+            }
+        };
+
+        let entries: Map<String, Value> = store.entries().into_iter().collect();
+        Self::from_entries(&entries)
+    }
+
+    /// Read each setting from its own key, so one malformed value only
+    /// resets that setting
+    fn from_entries(entries: &Map<String, Value>) -> Self {
+        Self {
+            png_optimization: field(entries, "pngOptimization"),
+            export_options: field(entries, "exportOptions"),
+            strip_metadata: field(entries, "stripMetadata"),
+            metadata_sidecar: field(entries, "metadataSidecar"),
+            duplicate_warning: field(entries, "duplicateWarning"),
+            auto_trim: field(entries, "autoTrim"),
+            window_shadow: field(entries, "windowShadow"),
+            watermark: field(entries, "watermark"),
+            canvas_presets: field(entries, "canvasPresets"),
+        }
+    }
+}
+
+/// Deserialize one setting, falling back to its default when missing or invalid
+fn field<T: DeserializeOwned + Default>(entries: &Map<String, Value>, key: &str) -> T {
+    let Some(value) = entries.get(key) else {
+        return T::default();
+    };
+
+    serde_json::from_value(value.clone()).unwrap_or_else(|e| {
+        eprintln!("Invalid setting {}, using its default: {}", key, e);
+        T::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_invalid_setting_only_resets_itself() {
+        let entries = json!({
+            "stripMetadata": true,
+            "watermark": { "enabled": "yes" },
+            "canvasPresets": "not a list",
+        });
+
+        let settings = ExportSettings::from_entries(entries.as_object().unwrap());
+
+        assert!(settings.strip_metadata);
+        assert!(!settings.watermark.enabled);
+        assert!(settings.canvas_presets.is_empty());
+    }
+}
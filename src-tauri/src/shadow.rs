@@ -0,0 +1,336 @@
+//! Window shadow module
+//!
+//! Native window captures include the system drop shadow as a wide margin
+//! of alpha-faded pixels. It can be removed, leaving only the window body,
+//! or replaced with a consistent shadow of our own.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::image::{load_image, replace_png_file, CropRegion};
+use crate::utils::AppResult;
+
+/// Alpha at or above which a pixel belongs to the window body
+const BODY_ALPHA: u8 = 250;
+
+/// What to do with the shadow of window captures
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShadowMode {
+    /// Leave the system shadow as captured
+    #[default]
+    Keep,
+    /// Crop tightly to the window body
+    Remove,
+    /// Replace the system shadow with the configured one
+    Normalize,
+}
+
+/// Shadow handling for window captures, persisted in settings
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct ShadowOptions {
+    pub mode: ShadowMode,
+    /// Softness of the normalized shadow, in pixels
+    pub blur_radius: f32,
+    /// Opacity of the normalized shadow, from 0 to 1
+    pub opacity: f32,
+    /// Vertical offset of the normalized shadow, in pixels
+    pub offset_y: i32,
+}
+
+impl Default for ShadowOptions {
+    fn default() -> Self {
+        Self {
+            mode: ShadowMode::Keep,
+            blur_radius: 24.0,
+            opacity: 0.35,
+            offset_y: 12,
+        }
+    }
+}
+
+/// Opaque window inside a capture's shadow margin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowBody {
+    pub region: CropRegion,
+    /// Radius of the window's rounded corners, in pixels
+    pub corner_radius: u32,
+}
+
+/// Find the opaque window body, if the capture has a transparent margin
+pub fn detect_window_body(img: &DynamicImage) -> Option<WindowBody> {
+    if !img.color().has_alpha() {
+        return None;
+    }
+
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+    for (x, y, pixel) in rgba.enumerate_pixels() {
+        if pixel[3] >= BODY_ALPHA {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    if min_x > max_x {
+        return None;
+    }
+
+    let region = CropRegion {
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x + 1,
+        height: max_y - min_y + 1,
+    };
+    if region.width == width && region.height == height {
+        return None;
+    }
+
+    let corner_radius = corner_radius(&rgba, &region);
+
+    Some(WindowBody {
+        region,
+        corner_radius,
+    })
+}
+
+/// Estimate the corner radius from the shadow showing through the top-left corner
+///
+/// The area outside a quarter circle of radius r is r² (1 - π/4). Pixels are
+/// counted as outside when nearer the shadow's alpha than the body's, which
+/// keeps antialiased edges from skewing the estimate.
+fn corner_radius(rgba: &RgbaImage, region: &CropRegion) -> u32 {
+    let max_radius = region.width.min(region.height) / 2;
+    let shadow_alpha = rgba.get_pixel(region.x, region.y)[3] as u32;
+    let threshold = (shadow_alpha + 255) / 2;
+
+    let mut outside = 0u32;
+    for y in region.y..region.y + max_radius {
+        for x in region.x..region.x + max_radius {
+            if (rgba.get_pixel(x, y)[3] as u32) < threshold {
+                outside += 1;
+            }
+        }
+    }
+
+    let radius = (outside as f32 / (1.0 - std::f32::consts::FRAC_PI_4)).sqrt();
+    (radius.round() as u32).min(max_radius)
+}
+
+/// Crop to the window body, clearing shadow left in the rounded corners
+pub fn remove_shadow(img: &DynamicImage, body: &WindowBody) -> RgbaImage {
+    let region = body.region;
+    let mut window = img
+        .crop_imm(region.x, region.y, region.width, region.height)
+        .to_rgba8();
+
+    let radius = body.corner_radius as f32;
+    if radius == 0.0 {
+        return window;
+    }
+
+    let (width, height) = window.dimensions();
+    for (x, y, pixel) in window.enumerate_pixels_mut() {
+        // Distance into the corner square, measured from the nearest edges
+        let dx = (radius - x.min(width - 1 - x) as f32 - 0.5).max(0.0);
+        let dy = (radius - y.min(height - 1 - y) as f32 - 0.5).max(0.0);
+        if dx == 0.0 || dy == 0.0 {
+            continue;
+        }
+
+        // Antialiased coverage of the corner arc
+        let coverage = (radius + 0.5 - (dx * dx + dy * dy).sqrt()).clamp(0.0, 1.0);
+        pixel[3] = pixel[3].min((coverage * 255.0).round() as u8);
+    }
+
+    window
+}
+
+/// Replace the system shadow with a soft shadow of consistent size
+pub fn normalize_shadow(
+    img: &DynamicImage,
+    body: &WindowBody,
+    options: &ShadowOptions,
+) -> RgbaImage {
+    let window = remove_shadow(img, body);
+    let (width, height) = window.dimensions();
+
+    let blur = options.blur_radius.max(0.0);
+    let margin = (blur * 2.0).ceil() as u32 + options.offset_y.unsigned_abs();
+    let canvas_width = width + margin * 2;
+    let canvas_height = height + margin * 2;
+
+    let mut mask = GrayImage::new(canvas_width, canvas_height);
+    let shadow_y = margin as i64 + options.offset_y as i64;
+    for (x, y, pixel) in window.enumerate_pixels() {
+        let target_y = shadow_y + y as i64;
+        if target_y >= 0 && target_y < canvas_height as i64 {
+            mask.put_pixel(margin + x, target_y as u32, Luma([pixel[3]]));
+        }
+    }
+    let mask = soften(&mask, blur / 2.0);
+
+    let opacity = options.opacity.clamp(0.0, 1.0);
+    let mut canvas = RgbaImage::from_fn(canvas_width, canvas_height, |x, y| {
+        let alpha = mask.get_pixel(x, y)[0] as f32 * opacity;
+        Rgba([0, 0, 0, alpha.round() as u8])
+    });
+    imageops::overlay(&mut canvas, &window, margin as i64, margin as i64);
+
+    canvas
+}
+
+/// Gaussian blur, run on a reduced mask since shadows have no fine detail
+pub fn soften(mask: &GrayImage, sigma: f32) -> GrayImage {
+    if sigma < 0.5 {
+        return mask.clone();
+    }
+
+    let factor = ((sigma / 2.0) as u32).clamp(1, 8);
+    let (width, height) = mask.dimensions();
+    let small = imageops::resize(
+        mask,
+        (width / factor).max(1),
+        (height / factor).max(1),
+        FilterType::Triangle,
+    );
+    let blurred = imageops::blur(&small, sigma / factor as f32);
+    imageops::resize(&blurred, width, height, FilterType::Triangle)
+}
+
+/// Apply shadow handling to a window capture
+///
+/// Returns the new image and the window body within the original capture,
+/// or `None` when there is nothing to do.
+pub fn apply_shadow_mode(
+    img: &DynamicImage,
+    options: &ShadowOptions,
+) -> Option<(RgbaImage, CropRegion)> {
+    if options.mode == ShadowMode::Keep {
+        return None;
+    }
+
+    let body = detect_window_body(img)?;
+    let processed = match options.mode {
+        ShadowMode::Remove => remove_shadow(img, &body),
+        _ => normalize_shadow(img, &body, options),
+    };
+    Some((processed, body.region))
+}
+
+/// Apply shadow handling to a PNG window capture in place
+pub fn process_shadow_file(path: &str, options: &ShadowOptions) -> AppResult<Option<CropRegion>> {
+    if options.mode == ShadowMode::Keep {
+        return Ok(None);
+    }
+
+    let img = load_image(path)?;
+    let Some((processed, region)) = apply_shadow_mode(&img, options) else {
+        return Ok(None);
+    };
+    replace_png_file(path, &DynamicImage::ImageRgba8(processed))?;
+
+    Ok(Some(region))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: CropRegion = CropRegion {
+        x: 20,
+        y: 16,
+        width: 60,
+        height: 40,
+    };
+
+    /// Window body with rounded corners inside a fading shadow margin
+    fn window_capture(radius: u32) -> DynamicImage {
+        let mut img = RgbaImage::from_fn(100, 80, |x, y| {
+            let dx = (BODY.x as i32 - x as i32).max(x as i32 - (BODY.x + BODY.width - 1) as i32);
+            let dy = (BODY.y as i32 - y as i32).max(y as i32 - (BODY.y + BODY.height - 1) as i32);
+            let distance = dx.max(dy).max(0) as u32;
+            let alpha = 120u32.saturating_sub(distance * 8) as u8;
+            Rgba([0, 0, 0, alpha])
+        });
+
+        let window = RgbaImage::from_pixel(BODY.width, BODY.height, Rgba([240, 240, 240, 255]));
+        let window = remove_shadow(
+            &DynamicImage::ImageRgba8(window),
+            &WindowBody {
+                region: CropRegion { x: 0, y: 0, ..BODY },
+                corner_radius: radius,
+            },
+        );
+        imageops::overlay(&mut img, &window, BODY.x as i64, BODY.y as i64);
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn test_detects_window_body() {
+        for radius in [0, 4, 6, 12] {
+            let body = detect_window_body(&window_capture(radius)).unwrap();
+
+            assert_eq!(body.region, BODY);
+            assert_eq!(body.corner_radius, radius);
+        }
+    }
+
+    #[test]
+    fn test_opaque_capture_has_no_shadow() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(40, 30));
+
+        assert!(detect_window_body(&img).is_none());
+    }
+
+    #[test]
+    fn test_remove_crops_and_clears_corners() {
+        let img = window_capture(6);
+        let body = detect_window_body(&img).unwrap();
+
+        let window = remove_shadow(&img, &body);
+
+        assert_eq!(window.dimensions(), (BODY.width, BODY.height));
+        assert_eq!(window.get_pixel(0, 0)[3], 0);
+        assert_eq!(window.get_pixel(BODY.width - 1, BODY.height - 1)[3], 0);
+        assert_eq!(window.get_pixel(30, 20), &Rgba([240, 240, 240, 255]));
+        assert_eq!(window.get_pixel(6, 0)[3], 255);
+    }
+
+    #[test]
+    fn test_normalize_pads_evenly_with_soft_shadow() {
+        let img = window_capture(4);
+        let options = ShadowOptions {
+            mode: ShadowMode::Normalize,
+            blur_radius: 8.0,
+            opacity: 0.5,
+            offset_y: 4,
+        };
+
+        let (normalized, region) = apply_shadow_mode(&img, &options).unwrap();
+        let margin = 16 + 4;
+
+        assert_eq!(region, BODY);
+        assert_eq!(
+            normalized.dimensions(),
+            (BODY.width + margin * 2, BODY.height + margin * 2)
+        );
+        assert_eq!(
+            normalized.get_pixel(margin + 30, margin + 20),
+            &Rgba([240, 240, 240, 255])
+        );
+        // Shadow shows below the window and fades out towards the edge
+        let below = normalized.get_pixel(margin + 30, margin + BODY.height + 2)[3];
+        assert!(below > 0 && below <= 128, "{}", below);
+        assert_eq!(normalized.get_pixel(0, 0)[3], 0);
+    }
+
+    #[test]
+    fn test_keep_does_nothing() {
+        assert!(apply_shadow_mode(&window_capture(4), &ShadowOptions::default()).is_none());
+    }
+}
@@ -0,0 +1,228 @@
+//! Spotlight module
+//!
+//! Draws attention to parts of an image by dimming, and optionally
+//! blurring, everything outside them.
+
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+use serde::Deserialize;
+
+use crate::draw::{blend, rounded_rect_coverage, Color, Corners, Rect};
+use crate::image::blur_image;
+use crate::shadow::soften;
+use crate::utils::AppResult;
+
+/// Outline of a highlighted region
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpotlightShape {
+    #[default]
+    Rect,
+    Ellipse,
+}
+
+/// Area left untouched by the spotlight, in image pixels
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct SpotlightRegion {
+    #[serde(flatten)]
+    pub rect: Rect,
+    #[serde(default)]
+    pub shape: SpotlightShape,
+    /// Corner radius of rect regions
+    #[serde(default)]
+    pub corner_radius: f32,
+}
+
+/// Regions to highlight and how to treat the rest of the image
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SpotlightOptions {
+    pub regions: Vec<SpotlightRegion>,
+    /// Opacity of the dim colour over the outside area, from 0 to 1
+    pub dim_opacity: f32,
+    pub dim_color: Color,
+    /// Blur radius of the outside area; 0 keeps it sharp
+    pub blur: f32,
+    /// Width of the soft transition at region edges
+    pub feather: f32,
+}
+
+impl Default for SpotlightOptions {
+    fn default() -> Self {
+        Self {
+            regions: Vec::new(),
+            dim_opacity: 0.6,
+            dim_color: Color(Rgba([0, 0, 0, 255])),
+            blur: 0.0,
+            feather: 8.0,
+        }
+    }
+}
+
+/// Return a copy of the image with everything outside the regions dimmed
+pub fn apply_spotlight(img: &DynamicImage, options: &SpotlightOptions) -> AppResult<RgbaImage> {
+    if options.regions.is_empty() {
+        return Err("Spotlight needs at least one region".to_string());
+    }
+
+    let original = img.to_rgba8();
+    let (width, height) = original.dimensions();
+    let mask = soften(
+        &region_mask(width, height, &options.regions),
+        options.feather.max(0.0) / 2.0,
+    );
+
+    let mut outside = match options.blur > 0.0 {
+        true => blur_image(&original, options.blur),
+        false => original.clone(),
+    };
+    let opacity = options.dim_opacity.clamp(0.0, 1.0);
+    for pixel in outside.pixels_mut() {
+        let alpha = pixel[3];
+        blend(pixel, options.dim_color.0, opacity);
+        // Dimming shouldn't fill in transparent areas
+        pixel[3] = alpha;
+    }
+
+    Ok(RgbaImage::from_fn(width, height, |x, y| {
+        let inside = mask.get_pixel(x, y)[0] as f32 / 255.0;
+        let (a, b) = (outside.get_pixel(x, y), original.get_pixel(x, y));
+        let mut pixel = [0u8; 4];
+        for (c, value) in pixel.iter_mut().enumerate() {
+            *value = (a[c] as f32 + (b[c] as f32 - a[c] as f32) * inside).round() as u8;
+        }
+        Rgba(pixel)
+    }))
+}
+
+/// Antialiased coverage of all regions
+fn region_mask(width: u32, height: u32, regions: &[SpotlightRegion]) -> GrayImage {
+    GrayImage::from_fn(width, height, |x, y| {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        let coverage = regions
+            .iter()
+            .map(|region| match region.shape {
+                SpotlightShape::Rect => {
+                    rounded_rect_coverage(px, py, &region.rect, region.corner_radius, Corners::ALL)
+                }
+                SpotlightShape::Ellipse => ellipse_coverage(px, py, &region.rect),
+            })
+            .fold(0.0f32, f32::max);
+        Luma([(coverage * 255.0).round() as u8])
+    })
+}
+
+/// Approximate coverage of the ellipse inscribed in a rectangle
+fn ellipse_coverage(px: f32, py: f32, rect: &Rect) -> f32 {
+    let (rx, ry) = (rect.width / 2.0, rect.height / 2.0);
+    if rx <= 0.0 || ry <= 0.0 {
+        return 0.0;
+    }
+    let dx = (px - rect.x - rx) / rx;
+    let dy = (py - rect.y - ry) / ry;
+    let distance = (dx.hypot(dy) - 1.0) * rx.min(ry);
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn white(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, WHITE))
+    }
+
+    fn region(shape: SpotlightShape) -> SpotlightRegion {
+        SpotlightRegion {
+            rect: Rect::new(20.0, 20.0, 40.0, 20.0),
+            shape,
+            corner_radius: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_dims_outside_regions() {
+        let options = SpotlightOptions {
+            regions: vec![region(SpotlightShape::Rect)],
+            dim_opacity: 0.5,
+            feather: 0.0,
+            ..Default::default()
+        };
+
+        let result = apply_spotlight(&white(80, 60), &options).unwrap();
+
+        assert_eq!(result.get_pixel(30, 30), &WHITE);
+        assert_eq!(result.get_pixel(5, 5), &Rgba([128, 128, 128, 255]));
+        assert_eq!(result.get_pixel(60, 30), &Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn test_ellipse_leaves_corners_dimmed() {
+        let options = SpotlightOptions {
+            regions: vec![region(SpotlightShape::Ellipse)],
+            dim_opacity: 1.0,
+            feather: 0.0,
+            ..Default::default()
+        };
+
+        let result = apply_spotlight(&white(80, 60), &options).unwrap();
+
+        assert_eq!(result.get_pixel(40, 30), &WHITE);
+        assert_eq!(result.get_pixel(21, 21), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_feather_softens_edges() {
+        let options = SpotlightOptions {
+            regions: vec![region(SpotlightShape::Rect)],
+            dim_opacity: 1.0,
+            feather: 12.0,
+            ..Default::default()
+        };
+
+        let result = apply_spotlight(&white(80, 60), &options).unwrap();
+
+        let edge = result.get_pixel(20, 30)[0];
+        assert!(edge > 40 && edge < 215, "{}", edge);
+        assert_eq!(result.get_pixel(2, 2)[0], 0);
+    }
+
+    #[test]
+    fn test_blur_outside_only() {
+        // Vertical stripes; the region keeps them, the outside is smoothed
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(80, 60, |x, _| {
+            if x % 2 == 0 {
+                WHITE
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        }));
+        let options = SpotlightOptions {
+            regions: vec![region(SpotlightShape::Rect)],
+            dim_opacity: 0.0,
+            blur: 4.0,
+            feather: 0.0,
+            ..Default::default()
+        };
+
+        let result = apply_spotlight(&img, &options).unwrap();
+
+        assert_eq!(result.get_pixel(30, 30), &WHITE);
+        assert_eq!(result.get_pixel(31, 30), &Rgba([0, 0, 0, 255]));
+        let outside = result.get_pixel(6, 50)[0];
+        assert!(outside > 80 && outside < 180, "{}", outside);
+    }
+
+    #[test]
+    fn test_regions_from_json() {
+        let json = r##"{"regions": [{"x": 1, "y": 2, "width": 3, "height": 4, "shape": "ellipse"}],
+                        "dim_color": "#102030"}"##;
+        let options: SpotlightOptions = serde_json::from_str(json).unwrap();
+
+        assert_eq!(options.regions[0].rect, Rect::new(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(options.regions[0].shape, SpotlightShape::Ellipse);
+        assert_eq!(options.dim_opacity, 0.6);
+        assert!(apply_spotlight(&white(4, 4), &SpotlightOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Streaming module
+//!
+//! Crops and re-encodes PNGs row by row, so memory use stays at a few rows
+//! however large the capture is.
+
+use png::{BitDepth, Decoder, Encoder, PixelDimensions, Transformations, Unit};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::image::{pixels_per_metre, CropRegion, MAX_EXPORT_DIMENSION};
+use crate::utils::AppResult;
+
+/// Decoder allocation budget, enough for rows and metadata but never a whole frame
+const STREAM_LIMIT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Size and layout from a PNG header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PngHeader {
+    pub width: u32,
+    pub height: u32,
+    /// Interlaced rows arrive in passes, so they can't be streamed
+    pub interlaced: bool,
+}
+
+/// Read a PNG header without decoding any pixels
+pub fn read_png_header<R: Read>(source: R) -> AppResult<PngHeader> {
+    let decoder = Decoder::new_with_limits(
+        BufReader::new(source),
+        png::Limits {
+            bytes: STREAM_LIMIT_BYTES,
+        },
+    );
+    let reader = decoder
+        .read_info()
+        .map_err(|e| format!("Failed to read PNG header: {}", e))?;
+    let info = reader.info();
+
+    Ok(PngHeader {
+        width: info.width,
+        height: info.height,
+        interlaced: info.interlaced,
+    })
+}
+
+/// Copy a PNG, or a region of it, to a new file one row at a time
+///
+/// The colour profile is carried over. `scale` sets the pixel density for
+/// the display scale; without it the source density is kept.
+pub fn stream_png<R: Read>(
+    source: R,
+    region: Option<CropRegion>,
+    dest: &Path,
+    scale: Option<f32>,
+) -> AppResult<()> {
+    let mut decoder = Decoder::new_with_limits(
+        BufReader::new(source),
+        png::Limits {
+            bytes: STREAM_LIMIT_BYTES,
+        },
+    );
+    // Palettes and low bit depths become plain 8-bit channels
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| format!("Failed to read PNG header: {}", e))?;
+
+    let info = reader.info();
+    let (width, height) = (info.width, info.height);
+    if width > MAX_EXPORT_DIMENSION || height > MAX_EXPORT_DIMENSION {
+        return Err(format!(
+            "Image is too large: {}x{} exceeds {} pixels per side",
+            width, height, MAX_EXPORT_DIMENSION
+        ));
+    }
+    if info.interlaced {
+        return Err("Interlaced PNGs can't be streamed".to_string());
+    }
+
+    let region = region.unwrap_or(CropRegion {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    });
+    let region = CropRegion::clamped(
+        region.x,
+        region.y,
+        region.width,
+        region.height,
+        width,
+        height,
+    );
+    if !region.is_valid() {
+        return Err(format!(
+            "Invalid crop region: x={}, y={}, w={}, h={} (image: {}x{})",
+            region.x, region.y, region.width, region.height, width, height
+        ));
+    }
+
+    let (color_type, bit_depth) = reader.output_color_type();
+    let bytes_per_pixel = color_type.samples() * if bit_depth == BitDepth::Sixteen { 2 } else { 1 };
+
+    let mut out = png::Info::with_size(region.width, region.height);
+    out.color_type = color_type;
+    out.bit_depth = bit_depth;
+    out.icc_profile = info.icc_profile.clone();
+    out.srgb = info.srgb;
+    out.pixel_dims = match scale {
+        Some(scale) => {
+            let ppm = pixels_per_metre(scale);
+            Some(PixelDimensions {
+                xppu: ppm,
+                yppu: ppm,
+                unit: Unit::Meter,
+            })
+        }
+        None => info.pixel_dims,
+    };
+
+    let file = File::create(dest).map_err(|e| format!("Failed to save image: {}", e))?;
+    let result = (|| {
+        let encoder = Encoder::with_info(BufWriter::new(file), out)
+            .map_err(|e| format!("Failed to encode image: {}", e))?;
+        let mut stream = encoder
+            .write_header()
+            .and_then(|writer| writer.into_stream_writer())
+            .map_err(|e| format!("Failed to encode image: {}", e))?;
+
+        let columns = region.x as usize * bytes_per_pixel
+            ..(region.x + region.width) as usize * bytes_per_pixel;
+        let rows = region.y..region.y + region.height;
+        let mut y = 0;
+        while y < rows.end {
+            let row = reader
+                .next_row()
+                .map_err(|e| format!("Failed to decode image: {}", e))?
+                .ok_or("Image ended before the last row")?;
+            if rows.contains(&y) {
+                stream
+                    .write_all(&row.data()[columns.clone()])
+                    .map_err(|e| format!("Failed to encode image: {}", e))?;
+            }
+            y += 1;
+        }
+
+        stream
+            .finish()
+            .map_err(|e| format!("Failed to encode image: {}", e))
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(dest);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn pattern() -> RgbaImage {
+        RgbaImage::from_fn(64, 48, |x, y| {
+            Rgba([x as u8 * 4, y as u8 * 5, 128, 255 - x as u8])
+        })
+    }
+
+    fn png_bytes(img: &DynamicImage) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        img.write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}_{}.png", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_crop_matches_full_decode() {
+        let img = pattern();
+        let dest = temp_path("stream_crop");
+        let region = CropRegion {
+            x: 10,
+            y: 5,
+            width: 30,
+            height: 20,
+        };
+
+        stream_png(
+            Cursor::new(png_bytes(&DynamicImage::ImageRgba8(img.clone()))),
+            Some(region),
+            &dest,
+            None,
+        )
+        .unwrap();
+        let cropped = image::open(&dest).unwrap().to_rgba8();
+        let _ = fs::remove_file(&dest);
+
+        let expected = image::imageops::crop_imm(&img, 10, 5, 30, 20).to_image();
+        assert_eq!(cropped, expected);
+    }
+
+    #[test]
+    fn test_expands_grey_and_keeps_density() {
+        let grey = DynamicImage::ImageLuma8(image::GrayImage::from_fn(8, 8, |x, _| {
+            image::Luma([x as u8 * 30])
+        }));
+        let dest = temp_path("stream_grey");
+
+        stream_png(Cursor::new(png_bytes(&grey)), None, &dest, Some(2.0)).unwrap();
+        let bytes = fs::read(&dest).unwrap();
+        let _ = fs::remove_file(&dest);
+
+        let copied = image::load_from_memory(&bytes).unwrap().to_luma8();
+        assert_eq!(copied, grey.to_luma8());
+        let decoder = Decoder::new(Cursor::new(&bytes));
+        let reader = decoder.read_info().unwrap();
+        let dims = reader.info().pixel_dims.unwrap();
+        assert_eq!(dims.xppu, pixels_per_metre(2.0));
+    }
+
+    #[test]
+    fn test_header_limits() {
+        // Wide but short, so the test itself stays small
+        let mut bytes = Vec::new();
+        let mut encoder = Encoder::new(&mut bytes, MAX_EXPORT_DIMENSION + 1, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&vec![0; MAX_EXPORT_DIMENSION as usize + 1])
+            .unwrap();
+        drop(writer);
+
+        let header = read_png_header(Cursor::new(&bytes)).unwrap();
+        assert_eq!(header.width, MAX_EXPORT_DIMENSION + 1);
+        assert!(!header.interlaced);
+
+        let dest = temp_path("stream_limit");
+        assert!(stream_png(Cursor::new(&bytes), None, &dest, None).is_err());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_truncated_data_removes_output() {
+        let bytes = png_bytes(&DynamicImage::ImageRgba8(pattern()));
+        let dest = temp_path("stream_truncated");
+
+        let result = stream_png(Cursor::new(&bytes[..bytes.len() / 2]), None, &dest, None);
+
+        assert!(result.is_err());
+        assert!(!dest.exists());
+    }
+}
@@ -0,0 +1,192 @@
+//! Text rendering module
+//!
+//! Draws single lines of text onto images using a font from the system,
+//! or from a file chosen by the user.
+
+use ab_glyph::{point, Font, FontArc, FontVec, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use serde::Deserialize;
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::draw::blend;
+use crate::utils::AppResult;
+
+/// Fonts tried in order when no font file is given
+const SYSTEM_FONTS: [&str; 8] = [
+    "/System/Library/Fonts/SFNS.ttf",
+    "/System/Library/Fonts/Helvetica.ttc",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "C:\\Windows\\Fonts\\segoeui.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+];
+
+static SYSTEM_FONT: OnceLock<Option<FontArc>> = OnceLock::new();
+
+/// Horizontal alignment of text relative to its anchor point
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Size, colour and alignment of a line of text
+#[derive(Debug, Clone, Copy)]
+pub struct TextStyle {
+    pub size: f32,
+    pub color: Rgba<u8>,
+    pub align: TextAlign,
+}
+
+/// Load a font file, using the first face of a collection
+pub fn load_font(path: &str) -> AppResult<FontArc> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read font: {}", e))?;
+    FontVec::try_from_vec_and_index(data, 0)
+        .map(FontArc::new)
+        .map_err(|e| format!("Failed to load font: {}", e))
+}
+
+/// Default sans-serif font of this system, loaded once
+pub fn system_font() -> Option<FontArc> {
+    SYSTEM_FONT
+        .get_or_init(|| {
+            let font = SYSTEM_FONTS.iter().find_map(|path| load_font(path).ok());
+            if font.is_none() {
+                eprintln!("No system font found, text will not be drawn");
+            }
+            font
+        })
+        .clone()
+}
+
+/// Width of a line of text, in pixels
+pub fn text_width(font: &FontArc, size: f32, text: &str) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Height of a line of text from ascender to descender, in pixels
+pub fn line_height(font: &FontArc, size: f32) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(size));
+    scaled.ascent() - scaled.descent()
+}
+
+/// Shorten text with an ellipsis until it fits within a width
+pub fn fit_text(font: &FontArc, size: f32, text: &str, max_width: f32) -> String {
+    if text_width(font, size, text) <= max_width {
+        return text.to_string();
+    }
+
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate: String = chars.iter().collect::<String>() + "…";
+        if text_width(font, size, &candidate) <= max_width {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
+/// Draw a line of text with its top edge at `y`
+pub fn draw_text(
+    img: &mut RgbaImage,
+    font: &FontArc,
+    style: &TextStyle,
+    x: f32,
+    y: f32,
+    text: &str,
+) {
+    let size = style.size;
+    let scaled = font.as_scaled(PxScale::from(size));
+    let mut caret = match style.align {
+        TextAlign::Left => x,
+        TextAlign::Center => x - text_width(font, size, text) / 2.0,
+        TextAlign::Right => x - text_width(font, size, text),
+    };
+    let baseline = y + scaled.ascent();
+
+    let mut previous = None;
+    for c in text.chars() {
+        let mut glyph = scaled.scaled_glyph(c);
+        if let Some(previous) = previous {
+            caret += scaled.kern(previous, glyph.id);
+        }
+        glyph.position = point(caret, baseline);
+        caret += scaled.h_advance(glyph.id);
+        previous = Some(glyph.id);
+
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + gx as i64;
+            let py = bounds.min.y as i64 + gy as i64;
+            if px >= 0 && py >= 0 && px < img.width() as i64 && py < img.height() as i64 {
+                blend(
+                    img.get_pixel_mut(px as u32, py as u32),
+                    style.color,
+                    coverage,
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_font_file() {
+        assert!(load_font("/nonexistent/font.ttf").is_err());
+    }
+
+    #[test]
+    fn test_draw_and_fit_text() {
+        // Rendering depends on a font being installed
+        let Some(font) = system_font() else {
+            return;
+        };
+
+        let width = text_width(&font, 20.0, "Hello");
+        assert!(width > 20.0 && width < 100.0, "{}", width);
+
+        let fitted = fit_text(&font, 20.0, "https://example.com/a/long/path", 120.0);
+        assert!(fitted.ends_with('…'));
+        assert!(text_width(&font, 20.0, &fitted) <= 120.0);
+
+        let mut img = RgbaImage::new(120, 40);
+        let style = TextStyle {
+            size: 20.0,
+            color: Rgba([0, 0, 0, 255]),
+            align: TextAlign::Center,
+        };
+        draw_text(&mut img, &font, &style, 60.0, 10.0, "Hello");
+        let inked: Vec<u32> = img
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p[3] > 0)
+            .map(|(x, _, _)| x)
+            .collect();
+        assert!(!inked.is_empty());
+        let centre = inked.iter().sum::<u32>() as f32 / inked.len() as f32;
+        assert!((centre - 60.0).abs() < 8.0, "{}", centre);
+    }
+}
@@ -0,0 +1,236 @@
+//! Auto-trim module
+//!
+//! Removes solid-colour or transparent margins, such as wallpaper strips
+//! around window and region captures.
+
+use image::{DynamicImage, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::image::{load_image, replace_png_file, CropRegion};
+use crate::utils::AppResult;
+
+/// Post-capture trimming, persisted in settings
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct AutoTrimOptions {
+    /// Trim region and window captures as soon as they are taken
+    pub enabled: bool,
+    /// Largest per-channel difference still treated as the border colour
+    pub tolerance: u8,
+}
+
+impl Default for AutoTrimOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tolerance: 8,
+        }
+    }
+}
+
+/// Capture that was trimmed, and the part of it that was kept
+#[derive(Serialize, Clone, Debug)]
+pub struct TrimmedCapture {
+    pub path: String,
+    pub region: CropRegion,
+}
+
+/// Find the region left after removing uniform borders from every edge
+///
+/// Each edge is trimmed while its rows or columns match the colour of its
+/// outermost pixel, so solid bars inside the content are kept. Fully
+/// transparent pixels match regardless of colour.
+/// An image that is uniform throughout is left whole.
+pub fn detect_trim(img: &DynamicImage, tolerance: u8) -> CropRegion {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let full = CropRegion {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    };
+    if width == 0 || height == 0 {
+        return full;
+    }
+
+    let row_matches = |y: u32, reference: &Rgba<u8>| {
+        (0..width).all(|x| matches(rgba.get_pixel(x, y), reference, tolerance))
+    };
+    let column_matches = |x: u32, y0: u32, y1: u32, reference: &Rgba<u8>| {
+        (y0..y1).all(|y| matches(rgba.get_pixel(x, y), reference, tolerance))
+    };
+
+    let reference = *rgba.get_pixel(0, 0);
+    let mut top = 0;
+    while top < height && row_matches(top, &reference) {
+        top += 1;
+    }
+    if top == height {
+        return full;
+    }
+
+    let reference = *rgba.get_pixel(0, height - 1);
+    let mut bottom = height;
+    while bottom > top + 1 && row_matches(bottom - 1, &reference) {
+        bottom -= 1;
+    }
+
+    let reference = *rgba.get_pixel(0, top);
+    let mut left = 0;
+    while left < width - 1 && column_matches(left, top, bottom, &reference) {
+        left += 1;
+    }
+
+    let reference = *rgba.get_pixel(width - 1, top);
+    let mut right = width;
+    while right > left + 1 && column_matches(right - 1, top, bottom, &reference) {
+        right -= 1;
+    }
+
+    CropRegion {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    }
+}
+
+fn matches(pixel: &Rgba<u8>, reference: &Rgba<u8>, tolerance: u8) -> bool {
+    if pixel[3] <= tolerance && reference[3] <= tolerance {
+        return true;
+    }
+    pixel
+        .0
+        .iter()
+        .zip(reference.0.iter())
+        .all(|(a, b)| a.abs_diff(*b) <= tolerance)
+}
+
+/// Trim a PNG capture in place, returning the region that was kept
+pub fn trim_png_file(path: &str, tolerance: u8) -> AppResult<CropRegion> {
+    let img = load_image(path)?;
+    let region = detect_trim(&img, tolerance);
+    if region.width == img.width() && region.height == img.height() {
+        return Ok(region);
+    }
+
+    let trimmed = img.crop_imm(region.x, region.y, region.width, region.height);
+    replace_png_file(path, &trimmed)?;
+
+    Ok(region)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    const WALLPAPER: Rgba<u8> = Rgba([30, 90, 160, 255]);
+
+    /// Textured content block inside a uniform border
+    fn framed(
+        width: u32,
+        height: u32,
+        content: (u32, u32, u32, u32),
+        border: Rgba<u8>,
+    ) -> RgbaImage {
+        let (cx, cy, cw, ch) = content;
+        RgbaImage::from_fn(width, height, |x, y| {
+            if x >= cx && x < cx + cw && y >= cy && y < cy + ch {
+                Rgba([(x * 9) as u8, (y * 5) as u8, 200, 255])
+            } else {
+                border
+            }
+        })
+    }
+
+    #[test]
+    fn test_trims_solid_border() {
+        let img = DynamicImage::ImageRgba8(framed(60, 40, (7, 5, 30, 20), WALLPAPER));
+
+        let region = detect_trim(&img, 0);
+
+        assert_eq!(
+            region,
+            CropRegion {
+                x: 7,
+                y: 5,
+                width: 30,
+                height: 20
+            }
+        );
+    }
+
+    #[test]
+    fn test_trims_transparent_border_of_any_colour() {
+        let mut img = framed(40, 40, (10, 12, 15, 10), Rgba([0, 0, 0, 0]));
+        img.put_pixel(1, 1, Rgba([255, 255, 255, 0]));
+
+        let region = detect_trim(&DynamicImage::ImageRgba8(img), 0);
+
+        assert_eq!(
+            (region.x, region.y, region.width, region.height),
+            (10, 12, 15, 10)
+        );
+    }
+
+    #[test]
+    fn test_tolerance_absorbs_noise() {
+        let mut img = framed(50, 50, (10, 10, 20, 20), WALLPAPER);
+        img.put_pixel(3, 3, Rgba([33, 88, 163, 255]));
+
+        let strict = detect_trim(&DynamicImage::ImageRgba8(img.clone()), 0);
+        let tolerant = detect_trim(&DynamicImage::ImageRgba8(img), 4);
+
+        assert_eq!(strict.y, 3);
+        assert_eq!((tolerant.x, tolerant.y), (10, 10));
+    }
+
+    #[test]
+    fn test_edges_use_their_own_colour() {
+        // Wallpaper on the left, a different strip along the bottom
+        let mut img = framed(40, 30, (8, 0, 32, 24), WALLPAPER);
+        for y in 24..30 {
+            for x in 0..40 {
+                img.put_pixel(x, y, Rgba([250, 250, 250, 255]));
+            }
+        }
+
+        let region = detect_trim(&DynamicImage::ImageRgba8(img), 0);
+
+        assert_eq!(
+            (region.x, region.y, region.width, region.height),
+            (8, 0, 32, 24)
+        );
+    }
+
+    #[test]
+    fn test_keeps_solid_bars_inside_content() {
+        // White top margin over a full-width red header, then page content
+        let img = RgbaImage::from_fn(100, 100, |x, y| match y {
+            0..=9 => Rgba([255, 255, 255, 255]),
+            10..=29 => Rgba([220, 30, 30, 255]),
+            _ => Rgba([(x * 3) as u8, (y * 2) as u8, 90, 255]),
+        });
+
+        let region = detect_trim(&DynamicImage::ImageRgba8(img), 0);
+
+        assert_eq!(
+            (region.x, region.y, region.width, region.height),
+            (0, 10, 100, 90)
+        );
+    }
+
+    #[test]
+    fn test_uniform_image_is_kept_whole() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(16, 9, WALLPAPER));
+
+        let region = detect_trim(&img, 0);
+
+        assert_eq!(
+            (region.x, region.y, region.width, region.height),
+            (0, 0, 16, 9)
+        );
+    }
+}
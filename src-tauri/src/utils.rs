@@ -26,12 +26,34 @@ pub fn ensure_dir(path: &PathBuf) -> AppResult<()> {
     fs::create_dir_all(path).map_err(|e| format!("Failed to create directory: {}", e))
 }
 
+/// Replace a file's contents without ever leaving it truncated
+///
+/// The data is written next to the target and renamed over it.
+pub fn write_file_atomic(path: &str, data: &[u8]) -> AppResult<()> {
+    let tmp_path = PathBuf::from(format!("{}.tmp", path));
+    fs::write(&tmp_path, data).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    fs::rename(&tmp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("Failed to replace {}: {}", path, e)
+    })
+}
+
 /// Generate a unique filename with a prefix and timestamp
 pub fn generate_filename(prefix: &str, extension: &str) -> AppResult<String> {
     let timestamp = get_timestamp()?;
     Ok(format!("{}_{}.{}", prefix, timestamp, extension))
 }
 
+/// Generate a unique filename with a prefix, timestamp and a suffix before the extension
+pub fn generate_filename_with_suffix(
+    prefix: &str,
+    suffix: &str,
+    extension: &str,
+) -> AppResult<String> {
+    let timestamp = get_timestamp()?;
+    Ok(format!("{}_{}{}.{}", prefix, timestamp, suffix, extension))
+}
+
 /// Generate a unique filename with prefix, id, and timestamp
 pub fn generate_filename_with_id(prefix: &str, id: u32, extension: &str) -> AppResult<String> {
     let timestamp = get_timestamp()?;
@@ -51,6 +73,21 @@ mod tests {
         assert!(timestamp > 0);
     }
 
+    #[test]
+    fn test_write_file_atomic_replaces_contents() {
+        let path = std::env::temp_dir().join(format!("atomic_{}.txt", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        fs::write(&path, b"old contents").unwrap();
+
+        write_file_atomic(&path, b"new").unwrap();
+        let contents = fs::read(&path).unwrap();
+        let leftover = PathBuf::from(format!("{}.tmp", path)).exists();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents, b"new");
+        assert!(!leftover);
+    }
+
     #[test]
     fn test_generate_filename_format() {
         let result = generate_filename("screenshot", "png");
@@ -61,6 +98,14 @@ mod tests {
         assert!(filename.ends_with(".png"));
     }
 
+    #[test]
+    fn test_generate_filename_with_suffix_format() {
+        let filename = generate_filename_with_suffix("shot", "@2x", "png").unwrap();
+
+        assert!(filename.starts_with("shot_"));
+        assert!(filename.ends_with("@2x.png"));
+    }
+
     #[test]
     fn test_generate_filename_with_id_format() {
         let result = generate_filename_with_id("monitor", 1, "png");
@@ -0,0 +1,321 @@
+//! Watermark module
+//!
+//! Stamps a text label or logo image onto exported images, either once at
+//! a chosen position or tiled across the whole image.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::Deserialize;
+
+use crate::draw::Color;
+use crate::image::load_image;
+use crate::text::{
+    draw_text, line_height, load_font, system_font, text_width, TextAlign, TextStyle,
+};
+use crate::utils::AppResult;
+
+/// Font size used to measure text before scaling it to the target width
+const MEASURE_SIZE: f32 = 100.0;
+
+/// What the watermark shows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatermarkKind {
+    #[default]
+    Text,
+    Image,
+}
+
+/// Where a single watermark is placed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatermarkPosition {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    #[default]
+    BottomRight,
+}
+
+impl WatermarkPosition {
+    /// Horizontal and vertical placement, from 0 (left, top) to 1 (right, bottom)
+    fn alignment(self) -> (f32, f32) {
+        match self {
+            WatermarkPosition::TopLeft => (0.0, 0.0),
+            WatermarkPosition::Top => (0.5, 0.0),
+            WatermarkPosition::TopRight => (1.0, 0.0),
+            WatermarkPosition::Left => (0.0, 0.5),
+            WatermarkPosition::Center => (0.5, 0.5),
+            WatermarkPosition::Right => (1.0, 0.5),
+            WatermarkPosition::BottomLeft => (0.0, 1.0),
+            WatermarkPosition::Bottom => (0.5, 1.0),
+            WatermarkPosition::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+/// Watermark stamped on exports, persisted in settings
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct WatermarkOptions {
+    pub enabled: bool,
+    pub kind: WatermarkKind,
+    /// Label for text watermarks
+    pub text: String,
+    /// Colour of text watermarks
+    pub color: Color,
+    /// Font file for text watermarks, defaulting to the system font
+    pub font_path: Option<String>,
+    /// Logo file for image watermarks
+    pub image_path: Option<String>,
+    pub position: WatermarkPosition,
+    /// Gap to the image edges, and between tiles, as a fraction of the shorter side
+    pub margin: f32,
+    /// Opacity from 0 to 1
+    pub opacity: f32,
+    /// Watermark width as a fraction of the image width
+    pub scale: f32,
+    /// Repeat the watermark across the whole image, ignoring the position
+    pub tile: bool,
+}
+
+impl Default for WatermarkOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            kind: WatermarkKind::Text,
+            text: "CONFIDENTIAL".to_string(),
+            color: Color(Rgba([255, 255, 255, 255])),
+            font_path: None,
+            image_path: None,
+            position: WatermarkPosition::BottomRight,
+            margin: 0.03,
+            opacity: 0.5,
+            scale: 0.2,
+            tile: false,
+        }
+    }
+}
+
+/// Return a copy of the image with the watermark applied
+pub fn apply_watermark(img: &DynamicImage, options: &WatermarkOptions) -> AppResult<DynamicImage> {
+    let mut canvas = img.to_rgba8();
+    let (width, height) = canvas.dimensions();
+
+    let target_width = (width as f32 * options.scale.clamp(0.0, 1.0)).round() as u32;
+    if target_width == 0 {
+        return Ok(img.clone());
+    }
+    let mut stamp = match options.kind {
+        WatermarkKind::Text => text_stamp(options, target_width)?,
+        WatermarkKind::Image => image_stamp(options, target_width)?,
+    };
+
+    let opacity = options.opacity.clamp(0.0, 1.0);
+    for pixel in stamp.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+
+    let margin = (width.min(height) as f32 * options.margin.max(0.0)).round() as i64;
+    let (stamp_width, stamp_height) = (stamp.width() as i64, stamp.height() as i64);
+    if options.tile {
+        // Offset every other row by half a step so the tiles interlock
+        let step_x = stamp_width + margin.max(1);
+        let step_y = stamp_height + margin.max(1);
+        let mut y = margin / 2;
+        let mut row = 0;
+        while y < height as i64 {
+            let mut x = margin / 2 - (row % 2) * step_x / 2;
+            while x < width as i64 {
+                imageops::overlay(&mut canvas, &stamp, x, y);
+                x += step_x;
+            }
+            y += step_y;
+            row += 1;
+        }
+    } else {
+        let (align_x, align_y) = options.position.alignment();
+        let free_x = width as i64 - stamp_width - margin * 2;
+        let free_y = height as i64 - stamp_height - margin * 2;
+        let x = margin + (free_x as f32 * align_x).round() as i64;
+        let y = margin + (free_y as f32 * align_y).round() as i64;
+        imageops::overlay(&mut canvas, &stamp, x, y);
+    }
+
+    Ok(DynamicImage::ImageRgba8(canvas))
+}
+
+/// Render the label at the size that makes it `target_width` wide
+fn text_stamp(options: &WatermarkOptions, target_width: u32) -> AppResult<RgbaImage> {
+    let font = match &options.font_path {
+        Some(path) => load_font(path)?,
+        None => system_font().ok_or("No font available for the text watermark")?,
+    };
+    if options.text.trim().is_empty() {
+        return Err("Watermark text is empty".to_string());
+    }
+
+    let measured = text_width(&font, MEASURE_SIZE, &options.text);
+    let size = MEASURE_SIZE * target_width as f32 / measured.max(1.0);
+    let style = TextStyle {
+        size,
+        color: options.color.0,
+        align: TextAlign::Left,
+    };
+
+    let width = text_width(&font, size, &options.text).ceil() as u32 + 1;
+    let height = line_height(&font, size).ceil() as u32 + 1;
+    let mut stamp = RgbaImage::new(width.max(1), height.max(1));
+    draw_text(&mut stamp, &font, &style, 0.0, 0.0, &options.text);
+
+    Ok(stamp)
+}
+
+/// Load the logo, scaled to `target_width` wide
+fn image_stamp(options: &WatermarkOptions, target_width: u32) -> AppResult<RgbaImage> {
+    let path = options
+        .image_path
+        .as_deref()
+        .ok_or("No image selected for the watermark")?;
+    let logo = load_image(path)?;
+
+    let height = (logo.height() as f32 * target_width as f32 / logo.width() as f32).round();
+    Ok(logo
+        .resize_exact(target_width, (height as u32).max(1), FilterType::Lanczos3)
+        .to_rgba8())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    const GREY: Rgba<u8> = Rgba([128, 128, 128, 255]);
+
+    /// Solid red logo written to a temporary file
+    fn logo(name: &str, width: u32, height: u32) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}.png", name, std::process::id()));
+        RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255]))
+            .save(&path)
+            .unwrap();
+        path
+    }
+
+    fn image_options(path: &Path) -> WatermarkOptions {
+        WatermarkOptions {
+            enabled: true,
+            kind: WatermarkKind::Image,
+            image_path: Some(path.to_string_lossy().into_owned()),
+            margin: 0.1,
+            opacity: 1.0,
+            scale: 0.25,
+            ..Default::default()
+        }
+    }
+
+    fn is_red(pixel: &Rgba<u8>) -> bool {
+        pixel[0] > 200 && pixel[1] < 50
+    }
+
+    #[test]
+    fn test_image_watermark_position_and_scale() {
+        let path = logo("wm_position", 40, 20);
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(200, 100, GREY));
+
+        let stamped = apply_watermark(&img, &image_options(&path))
+            .unwrap()
+            .to_rgba8();
+        let _ = std::fs::remove_file(&path);
+
+        // 50x25 logo, 10px in from the bottom-right corner
+        assert!(is_red(stamped.get_pixel(189, 89)));
+        assert!(is_red(stamped.get_pixel(140, 65)));
+        assert_eq!(stamped.get_pixel(139, 64), &GREY);
+        assert_eq!(stamped.get_pixel(190, 90), &GREY);
+    }
+
+    #[test]
+    fn test_opacity_blends_with_image() {
+        let path = logo("wm_opacity", 10, 10);
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 100, GREY));
+        let options = WatermarkOptions {
+            opacity: 0.5,
+            position: WatermarkPosition::Center,
+            ..image_options(&path)
+        };
+
+        let stamped = apply_watermark(&img, &options).unwrap().to_rgba8();
+        let _ = std::fs::remove_file(&path);
+
+        let centre = stamped.get_pixel(50, 50);
+        assert!((centre[0] as i32 - 191).abs() <= 2, "{:?}", centre);
+        assert!((centre[1] as i32 - 64).abs() <= 2, "{:?}", centre);
+    }
+
+    #[test]
+    fn test_tiling_repeats_across_image() {
+        let path = logo("wm_tile", 10, 10);
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(200, 200, GREY));
+        let options = WatermarkOptions {
+            tile: true,
+            scale: 0.1,
+            margin: 0.05,
+            ..image_options(&path)
+        };
+
+        let stamped = apply_watermark(&img, &options).unwrap().to_rgba8();
+        let _ = std::fs::remove_file(&path);
+
+        let red = stamped.pixels().filter(|p| is_red(p)).count();
+        // Roughly half the image is covered by 20x20 tiles with 10px gaps
+        assert!(red > 200 * 200 / 3, "{}", red);
+        assert!(is_red(stamped.get_pixel(10, 10)));
+        assert!(is_red(stamped.get_pixel(185, 160)));
+    }
+
+    #[test]
+    fn test_text_watermark_width() {
+        // Rendering depends on a font being installed
+        if system_font().is_none() {
+            return;
+        }
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(400, 200, GREY));
+        let options = WatermarkOptions {
+            enabled: true,
+            color: Color(Rgba([0, 0, 0, 255])),
+            opacity: 1.0,
+            scale: 0.5,
+            margin: 0.0,
+            position: WatermarkPosition::TopLeft,
+            ..Default::default()
+        };
+
+        let stamped = apply_watermark(&img, &options).unwrap().to_rgba8();
+
+        let inked: Vec<u32> = stamped
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p[0] < 100)
+            .map(|(x, _, _)| x)
+            .collect();
+        let right = *inked.iter().max().unwrap();
+        assert!(*inked.iter().min().unwrap() < 20);
+        assert!((180..=205).contains(&right), "{}", right);
+    }
+
+    #[test]
+    fn test_missing_logo_is_an_error() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(10, 10));
+        let options = WatermarkOptions {
+            kind: WatermarkKind::Image,
+            image_path: None,
+            ..Default::default()
+        };
+
+        assert!(apply_watermark(&img, &options).is_err());
+    }
+}
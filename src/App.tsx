@@ -28,6 +28,12 @@ const UpdateDialog = lazy(() => import("./components/UpdateDialog").then(m => ({
 type AppMode = "main" | "editing" | "preferences";
 type CaptureMode = "region" | "fullscreen" | "window";
 
+interface ScannedCode {
+  kind: string;
+  payload: string;
+  polygon: { x: number; y: number }[];
+}
+
 // Loading fallback for lazy loaded components
 function LoadingFallback() {
   return (
@@ -382,6 +388,7 @@ function App() {
             imageData: processedImageData,
             saveDir: currentSaveDir,
             copyToClip: shouldCopyToClipboard,
+            sourcePath: screenshotPath,
           });
 
           toast.success("Screenshot processed and saved", {
@@ -442,6 +449,61 @@ function App() {
     }
   }, [isCapturing]);
 
+  const handleScanQr = useCallback(async () => {
+    if (isCapturing) return;
+
+    setIsCapturing(true);
+    setError(null);
+    const appWindow = getCurrentWindow();
+    const { tempDir: currentTempDir } = settingsRef.current;
+
+    try {
+      await appWindow.hide();
+      await new Promise((resolve) => setTimeout(resolve, 400));
+
+      const screenshotPath = await invoke<string>("native_capture_interactive", {
+        saveDir: currentTempDir,
+      });
+      const codes = await invoke<ScannedCode[]>("scan_codes", {
+        imagePath: screenshotPath,
+        copyToClip: true,
+      });
+
+      if (codes.length === 0) {
+        toast.error("No QR code found", { duration: 3000 });
+      } else {
+        toast.success("QR code copied to clipboard", {
+          description: codes[0].payload,
+          duration: 5000,
+        });
+      }
+    } catch (err) {
+      const errorMessage = err instanceof Error ? err.message : String(err);
+      if (errorMessage.includes("cancelled")) {
+        // Selection was dismissed, nothing to report
+      } else if (errorMessage.includes("already in progress")) {
+        setError("Please wait for the current screenshot to complete");
+      } else if (
+        errorMessage.toLowerCase().includes("permission") ||
+        errorMessage.toLowerCase().includes("access") ||
+        errorMessage.toLowerCase().includes("denied")
+      ) {
+        setError(
+          "Screen Recording permission required. Please go to System Settings > Privacy & Security > Screen Recording and enable access for Better Shot, then restart the app."
+        );
+      } else {
+        toast.error("Failed to scan QR code", {
+          description: errorMessage,
+          duration: 5000,
+        });
+      }
+    } finally {
+      setIsCapturing(false);
+      // Bring the window back so the result or error is visible
+      await restoreWindow();
+    }
+  }, [isCapturing]);
+
   // Setup hotkeys whenever settings change
   useEffect(() => {
     const setupHotkeys = async () => {
@@ -497,11 +559,13 @@ function App() {
     let unlisten1: (() => void) | null = null;
     let unlisten2: (() => void) | null = null;
     let unlisten3: (() => void) | null = null;
+    let unlisten4: (() => void) | null = null;
 
     const setupListeners = async () => {
       unlisten1 = await listen("capture-triggered", () => handleCapture("region"));
       unlisten2 = await listen("capture-fullscreen", () => handleCapture("fullscreen"));
       unlisten3 = await listen("capture-window", () => handleCapture("window"));
+      unlisten4 = await listen("scan-qr-triggered", () => handleScanQr());
     };
 
     setupListeners();
@@ -510,8 +574,9 @@ function App() {
       unlisten1?.();
       unlisten2?.();
       unlisten3?.();
+      unlisten4?.();
     };
-  }, [handleCapture]);
+  }, [handleCapture, handleScanQr]);
 
   // Reload settings when coming back from preferences
   const handleSettingsChange = useCallback(async () => {
@@ -544,6 +609,7 @@ function App() {
         imageData: editedImageData,
         saveDir,
         copyToClip: copyToClipboard,
+        sourcePath: tempScreenshotPath,
       });
 
       toast.success("Image saved", {
@@ -5,12 +5,20 @@ import { Upload, X, Check } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { assetCategories } from "@/hooks/useEditorSettings";
 import { cn } from "@/lib/utils";
+import type { DefaultBackgroundMode, DerivedBackgroundMode } from "@/lib/auto-process";
 import { 
   toStorableValue, 
   isDataUrl,
   getAssetIdFromPath 
 } from "@/lib/asset-registry";
 
+const derivedModes: { id: DerivedBackgroundMode; name: string }[] = [
+  { id: "blurred", name: "Blurred" },
+  { id: "edge_average", name: "Edge average" },
+  { id: "edge_dominant", name: "Edge dominant" },
+  { id: "palette", name: "Palette" },
+];
+
 interface BackgroundImageSelectorProps {
   onImageSelect: (imageSrc: string) => void;
 }
@@ -19,11 +27,12 @@ export function BackgroundImageSelector({ onImageSelect }: BackgroundImageSelect
   // selectedImage stores the asset ID or data URL (storable value)
   const [selectedImage, setSelectedImage] = useState<string | null>(null);
   const [uploadedImages, setUploadedImages] = useState<string[]>([]);
+  const [backgroundMode, setBackgroundMode] = useState<DefaultBackgroundMode>("image");
   const fileInputRef = useRef<HTMLInputElement>(null);
 
   // Helper to check if an asset is selected (compares by ID)
   const isSelected = useCallback((assetSrc: string): boolean => {
-    if (!selectedImage) return false;
+    if (!selectedImage || backgroundMode !== "image") return false;
     
     // For data URLs (uploaded images), compare directly
     if (isDataUrl(assetSrc)) {
@@ -33,7 +42,7 @@ export function BackgroundImageSelector({ onImageSelect }: BackgroundImageSelect
     // For registry assets, compare by asset ID
     const assetId = getAssetIdFromPath(assetSrc);
     return assetId === selectedImage;
-  }, [selectedImage]);
+  }, [selectedImage, backgroundMode]);
 
   useEffect(() => {
     const loadSettings = async () => {
@@ -41,6 +50,7 @@ export function BackgroundImageSelector({ onImageSelect }: BackgroundImageSelect
         const store = await Store.load("settings.json");
         const storedBg = await store.get<string>("defaultBackgroundImage");
         const uploaded = await store.get<string[]>("uploadedBackgroundImages");
+        const mode = await store.get<DefaultBackgroundMode>("defaultBackgroundMode");
         
         if (storedBg) {
           // Resolve the stored value to an actual path for display
@@ -50,6 +60,9 @@ export function BackgroundImageSelector({ onImageSelect }: BackgroundImageSelect
         if (uploaded) {
           setUploadedImages(uploaded);
         }
+        if (mode) {
+          setBackgroundMode(mode);
+        }
       } catch (err) {
         console.error("Failed to load background settings:", err);
       }
@@ -68,12 +81,14 @@ export function BackgroundImageSelector({ onImageSelect }: BackgroundImageSelect
     }
     
     setSelectedImage(storableValue);
+    setBackgroundMode("image");
     onImageSelect(imageSrc);
     
     try {
       const store = await Store.load("settings.json");
       // Store the asset ID, not the runtime path
       await store.set("defaultBackgroundImage", storableValue);
+      await store.set("defaultBackgroundMode", "image");
       await store.save();
       toast.success("Default background updated");
     } catch (err) {
@@ -82,6 +97,20 @@ export function BackgroundImageSelector({ onImageSelect }: BackgroundImageSelect
     }
   }, [onImageSelect]);
 
+  const handleModeSelect = useCallback(async (mode: DerivedBackgroundMode) => {
+    setBackgroundMode(mode);
+    
+    try {
+      const store = await Store.load("settings.json");
+      await store.set("defaultBackgroundMode", mode);
+      await store.save();
+      toast.success("Default background updated");
+    } catch (err) {
+      console.error("Failed to save background mode:", err);
+      toast.error("Failed to save default background");
+    }
+  }, []);
+
   const handleFileUpload = useCallback(async (event: React.ChangeEvent<HTMLInputElement>) => {
     const file = event.target.files?.[0];
     if (!file) return;
@@ -159,6 +188,26 @@ export function BackgroundImageSelector({ onImageSelect }: BackgroundImageSelect
       </div>
 
       <div className="space-y-3">
+        <div>
+          <h4 className="text-xs font-medium text-muted-foreground mb-2 uppercase tracking-wide">From Screenshot</h4>
+          <div className="grid grid-cols-4 gap-2">
+            {derivedModes.map((mode) => (
+              <button
+                key={mode.id}
+                onClick={() => handleModeSelect(mode.id)}
+                className={cn(
+                  "relative w-full py-2 rounded-lg border-2 text-xs text-foreground transition-all",
+                  backgroundMode === mode.id
+                    ? "border-blue-500 ring-2 ring-blue-500/50"
+                    : "border-border hover:border-ring"
+                )}
+              >
+                {mode.name}
+              </button>
+            ))}
+          </div>
+        </div>
+
         {uploadedImages.length > 0 && (
           <div>
             <h4 className="text-xs font-medium text-muted-foreground mb-2 uppercase tracking-wide">Uploaded Images</h4>
@@ -1,17 +1,25 @@
-import { convertFileSrc } from "@tauri-apps/api/core";
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
 import { Store } from "@tauri-apps/plugin-store";
 import { createHighQualityCanvas } from "./canvas-utils";
 import { resolveBackgroundPath, getDefaultBackgroundPath } from "./asset-registry";
 
+// Background modes computed from the screenshot instead of a fixed image
+export type DerivedBackgroundMode = "blurred" | "edge_average" | "edge_dominant" | "palette";
+export type DefaultBackgroundMode = "image" | DerivedBackgroundMode;
+
+const PADDING = 100;
+
 export async function processScreenshotWithDefaultBackground(
   imagePath: string
 ): Promise<string> {
   return new Promise(async (resolve, reject) => {
     // Get the default background path, resolving from store if available
     let defaultBgImage: string = getDefaultBackgroundPath();
+    let backgroundMode: DefaultBackgroundMode = "image";
     
     try {
       const store = await Store.load("settings.json");
+      backgroundMode = (await store.get<DefaultBackgroundMode>("defaultBackgroundMode")) ?? "image";
       const storedDefaultBg = await store.get<string>("defaultBackgroundImage");
       if (storedDefaultBg) {
         // Resolve the stored value (asset ID or data URL) to actual path
@@ -40,7 +48,7 @@ export async function processScreenshotWithDefaultBackground(
               blurAmount: 0,
               noiseAmount: 20,
               borderRadius: 18,
-              padding: 100,
+              padding: PADDING,
               shadow: {
                 blur: 33,
                 offsetX: 18,
@@ -76,6 +84,19 @@ export async function processScreenshotWithDefaultBackground(
           reject(new Error("Failed to load background image"));
         };
         
+        if (backgroundMode !== "image") {
+          try {
+            // Rendered at the exact canvas size since the background is stretched to fill it
+            defaultBgImage = await invoke<string>("render_backdrop", {
+              imagePath,
+              options: { mode: backgroundMode },
+              width: img.width + PADDING * 2,
+              height: img.height + PADDING * 2,
+            });
+          } catch (err) {
+            console.error("Failed to derive background, using the default image:", err);
+          }
+        }
         bgImg.src = defaultBgImage;
       } catch (err) {
         reject(err);